@@ -0,0 +1,30 @@
+//! Generates the endgame tablebases for every exchange cost and saves them next to the settings
+//! file. Run with `cargo run --release -p coerceo-core --example generate_tablebase`.
+
+use std::process;
+use std::time::Instant;
+
+use coerceo_core::ai::Tablebase;
+use coerceo_core::config;
+use coerceo_core::model::EXCHANGE_COSTS;
+
+fn main() {
+    for hexes_to_exchange in EXCHANGE_COSTS {
+        let start = Instant::now();
+        let tablebase = Tablebase::generate(hexes_to_exchange);
+        println!(
+            "{} tile(s) to exchange: {} decisive positions in {} ms",
+            hexes_to_exchange,
+            tablebase.len(),
+            start.elapsed().as_millis()
+        );
+
+        if let Err(e) = config::save_tablebase(&tablebase) {
+            eprintln!("Failed to save tablebase: {}", e);
+            process::exit(1);
+        }
+        if let Some(path) = config::tablebase_path(hexes_to_exchange) {
+            println!("Saved to {}", path.display());
+        }
+    }
+}
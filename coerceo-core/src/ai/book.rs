@@ -0,0 +1,118 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+
+use crate::model::rng::Rng;
+use crate::model::{Board, FieldCoord, Move, Symmetry};
+
+/// An opening book: a set of moves to play in known positions, keyed by the Zobrist hash of the
+/// position.
+///
+/// A book file has one move per line, written as `key weight field [field]`. The key is the
+/// Zobrist hash of the position in hexadecimal. The weight is how often the move should be chosen
+/// relative to the other moves for the position. A single field is an exchange for the piece on
+/// that field, while two fields are a move from the first field to the second. Blank lines and
+/// lines starting with `#` are skipped.
+///
+/// ```text
+/// # Laurentius starting position
+/// a546d9eb4eb8821d 3 d1e d1a
+/// a546d9eb4eb8821d 1 b1a b1e
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Book {
+    entries: HashMap<u64, Vec<(Move, u32)>>,
+}
+
+impl Book {
+    /// Parses a book file. Malformed lines are skipped.
+    pub fn parse(contents: &str) -> Self {
+        let mut entries: HashMap<_, Vec<_>> = HashMap::new();
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, mv, weight)) = parse_line(line) {
+                entries.entry(key).or_default().push((mv, weight));
+            }
+        }
+        Self { entries }
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Picks one of the book moves for `board` at random, weighted by the moves' weights. Moves
+    /// that are illegal in the position (such as from a hash collision) or that `allowed` rejects
+    /// are never picked.
+    ///
+    /// If the position isn't in the book, but a rotation or reflection of it is, the moves for
+    /// that position are rotated or reflected back and used instead.
+    pub fn probe<F>(&self, board: &Board, rng: &mut Rng, allowed: F) -> Option<Move>
+    where
+        F: Fn(&Move) -> bool,
+    {
+        let (symmetry, entries) = Symmetry::all().find_map(|symmetry| {
+            self.entries
+                .get(&board.transformed(symmetry).zobrist)
+                .map(|entries| (symmetry.inverse(), entries))
+        })?;
+        let moves: Vec<_> = entries
+            .iter()
+            .map(|&(mv, weight)| (symmetry.apply_move(&mv), weight))
+            .filter(|(mv, weight)| *weight > 0 && board.can_apply_move(mv) && allowed(mv))
+            .collect();
+
+        let total: u64 = moves.iter().map(|(_, weight)| u64::from(*weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.below(total);
+        for (mv, weight) in moves {
+            let weight = u64::from(weight);
+            if pick < weight {
+                return Some(mv);
+            }
+            pick -= weight;
+        }
+        unreachable!()
+    }
+}
+
+fn parse_line(line: &str) -> Option<(u64, Move, u32)> {
+    let mut words = line.split_whitespace();
+    let key = u64::from_str_radix(words.next()?, 16).ok()?;
+    let weight = words.next()?.parse().ok()?;
+    let from = FieldCoord::from_notation(words.next()?)?;
+    let mv = match words.next() {
+        Some(to) => {
+            let to = FieldCoord::from_notation(to)?;
+            if to.color() != from.color() {
+                return None;
+            }
+            Move::move_from_field(from, to)
+        }
+        None => Move::exchange_from_field(from),
+    };
+    if words.next().is_some() {
+        return None;
+    }
+    Some((key, mv, weight))
+}
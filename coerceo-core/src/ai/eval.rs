@@ -0,0 +1,327 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::cmp;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::config;
+use crate::model::bitboard::BitBoardExt;
+use crate::model::{Board, Color, ColorMap, GameType};
+
+/// The range every evaluation parameter is kept within. Even with every weight at its maximum, a
+/// position scores far from the scores of a forced win or loss, and the search's margins can be
+/// added to it without overflowing.
+pub const PARAM_RANGE: RangeInclusive<i16> = 0..=100;
+
+/// Brings a parameter value into `PARAM_RANGE`.
+pub fn clamp_param(value: i32) -> i16 {
+    let (min, max) = (
+        i32::from(*PARAM_RANGE.start()),
+        i32::from(*PARAM_RANGE.end()),
+    );
+    cmp::max(min, cmp::min(value, max)) as i16
+}
+
+/// The tunable constants of the evaluation function. They can be loaded from a file, so that
+/// different weight sets can be compared without recompiling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvalParams {
+    /// The value of a piece for each tile needed to exchange for it
+    pub piece: i16,
+    /// A bonus for the side to move
+    pub tempo: i16,
+    pub opening: Weights,
+    pub endgame: Weights,
+}
+
+/// The evaluation weights for one phase of the game. The evaluation blends the opening and
+/// endgame weights according to how much of the board is left.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weights {
+    /// The value of a captured tile
+    pub tile: i16,
+    /// The percentage of the field-square table values to count
+    pub field_percent: i16,
+    /// The penalty for each piece on a fragile tile, indexed by the tile's fragility (see
+    /// `Board::hex_fragility`). Fragile tiles have already lost neighbors, so pieces on them have
+    /// fewer fields to retreat to and are easier to surround.
+    pub fragile_tile: [i16; 2],
+    /// The penalty for each piece the other player could capture by surrounding it with their
+    /// next move (see `Board::pieces_en_prise`)
+    pub en_prise: i16,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        // If it's two hexes to exchange, then a piece is worth two opening tiles. If it's one hex,
+        // then a piece is worth one. We could instead up the value of a hex, but this way we don't
+        // need to change the width of the aspiration window.
+        //
+        // Centrality matters less once the board has shrunk, while captured tiles become more
+        // valuable as exchanges start to decide the game.
+        Self {
+            piece: 50,
+            tempo: 5,
+            opening: Weights {
+                tile: 50,
+                field_percent: 100,
+                fragile_tile: [8, 3],
+                en_prise: 10,
+            },
+            endgame: Weights {
+                tile: 60,
+                field_percent: 50,
+                fragile_tile: [12, 5],
+                en_prise: 15,
+            },
+        }
+    }
+}
+
+impl EvalParams {
+    /// Returns every parameter along with its name in the parameter file.
+    pub fn named_values_mut(&mut self) -> Vec<(&'static str, &mut i16)> {
+        let EvalParams {
+            piece,
+            tempo,
+            opening,
+            endgame,
+        } = self;
+        let [opening_fragile, opening_nearly_fragile] = &mut opening.fragile_tile;
+        let [endgame_fragile, endgame_nearly_fragile] = &mut endgame.fragile_tile;
+        vec![
+            ("piece", piece),
+            ("tempo", tempo),
+            ("opening_tile", &mut opening.tile),
+            ("opening_field_percent", &mut opening.field_percent),
+            ("opening_fragile_tile", opening_fragile),
+            ("opening_nearly_fragile_tile", opening_nearly_fragile),
+            ("opening_en_prise", &mut opening.en_prise),
+            ("endgame_tile", &mut endgame.tile),
+            ("endgame_field_percent", &mut endgame.field_percent),
+            ("endgame_fragile_tile", endgame_fragile),
+            ("endgame_nearly_fragile_tile", endgame_nearly_fragile),
+            ("endgame_en_prise", &mut endgame.en_prise),
+        ]
+    }
+    /// Parses a parameter file of `name = value` lines. Missing or invalid values keep their
+    /// defaults, and values outside `PARAM_RANGE` are brought into it.
+    pub fn parse(contents: &str) -> Self {
+        let values = config::parse_values(contents);
+        let mut params = Self::default();
+        for (name, value) in params.named_values_mut() {
+            if let Some(v) = values.get(name).and_then(|v| v.parse().ok()) {
+                *value = clamp_param(v);
+            }
+        }
+        params
+    }
+}
+
+impl fmt::Display for EvalParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut params = *self;
+        for (name, value) in params.named_values_mut() {
+            writeln!(f, "{} = {}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The phase of a game that hasn't lost any tiles or pieces
+const PHASE_MAX: i32 = 256;
+
+pub(crate) fn evaluate(board: &Board, params: &EvalParams) -> i16 {
+    let us = Features::new(board, board.turn);
+    let them = Features::new(board, board.turn.switch());
+
+    let piece = params.piece * i16::from(board.hexes_to_exchange);
+    let opening = us.score(piece, &params.opening) - them.score(piece, &params.opening);
+    let endgame = us.score(piece, &params.endgame) - them.score(piece, &params.endgame);
+
+    let phase = phase(board);
+    let blended = (opening * phase + endgame * (PHASE_MAX - phase)) / PHASE_MAX;
+    blended as i16 + params.tempo
+}
+
+/// Returns how far the game is from the endgame, from `PHASE_MAX` at the start of the game down to
+/// 0 once every tile and piece is gone.
+fn phase(board: &Board) -> i32 {
+    let (hexes, pieces) = match board.game_type() {
+        GameType::Laurentius | GameType::Random(_) => (19, 36),
+        GameType::Ocius => (7, 14),
+    };
+    let extant_hexes = board.hexes_iter().count() as i32;
+    let material =
+        extant_hexes + i32::from(board.pieces(Color::White) + board.pieces(Color::Black));
+    cmp::min(material * PHASE_MAX / (hexes + pieces), PHASE_MAX)
+}
+
+/// The unweighted terms of the evaluation for one player.
+struct Features {
+    pieces: i32,
+    tiles: i32,
+    fields: i32,
+    /// The number of pieces on fragile tiles, indexed by fragility
+    fragile_pieces: [i32; 2],
+    en_prise: i32,
+}
+
+impl Features {
+    fn new(board: &Board, color: Color) -> Self {
+        let table = match board.game_type() {
+            GameType::Laurentius | GameType::Random(_) => LAURENTIUS_FIELDS.get_ref(color),
+            GameType::Ocius => OCIUS_FIELDS.get_ref(color),
+        };
+        let our_fields = board.fields(color);
+
+        let mut fragile_pieces = [0; 2];
+        for hex in board.hexes_iter() {
+            let index = hex.to_index();
+            let pieces = (our_fields & (0b111 << (index * 3))).count_ones();
+            if pieces == 0 {
+                continue;
+            }
+            if let Some(fragility) = board.hex_fragility(index) {
+                fragile_pieces[fragility as usize] += pieces as i32;
+            }
+        }
+
+        Self {
+            pieces: i32::from(board.pieces(color)),
+            tiles: i32::from(board.hexes(color)),
+            fields: our_fields
+                .iter()
+                .map(|field| i32::from(table[field.trailing_zeros() as usize]))
+                .sum(),
+            fragile_pieces,
+            en_prise: board.pieces_en_prise(color).count_ones() as i32,
+        }
+    }
+    fn score(&self, piece: i16, weights: &Weights) -> i32 {
+        let fragile: i32 = self
+            .fragile_pieces
+            .iter()
+            .zip(&weights.fragile_tile)
+            .map(|(&count, &penalty)| count * i32::from(penalty))
+            .sum();
+
+        i32::from(piece) * self.pieces
+            + i32::from(weights.tile) * self.tiles
+            + self.fields * i32::from(weights.field_percent) / 100
+            - fragile
+            - i32::from(weights.en_prise) * self.en_prise
+    }
+}
+
+// Field-square tables, indexed by the bit index of a field in a player's field bitboard (one row
+// per hex). A field is worth more the closer it is to the center of the board. Central pieces have
+// more neighboring fields, so they are harder to surround, and the tiles under them are the last
+// to become removable. Fields on edge tiles are worth the least. Ocius uses only the seven central
+// hexes, so the rest of its table is zero.
+
+#[rustfmt::skip]
+const LAURENTIUS_FIELDS: ColorMap<[i16; 57]> = ColorMap {
+    white: [
+        2, 0, 2,
+        3, 1, 4,
+        1, 1, 3,
+        4, 1, 3,
+        7, 5, 7,
+        6, 6, 9,
+        1, 3, 4,
+        3, 1, 1,
+        9, 6, 6,
+        10, 10, 10,
+        5, 7, 7,
+        0, 2, 2,
+        4, 3, 1,
+        7, 7, 5,
+        6, 9, 6,
+        1, 4, 3,
+        2, 2, 0,
+        3, 4, 1,
+        1, 3, 1,
+    ],
+    black: [
+        3, 1, 1,
+        4, 1, 3,
+        2, 0, 2,
+        4, 3, 1,
+        9, 6, 6,
+        7, 5, 7,
+        3, 1, 4,
+        2, 2, 0,
+        7, 7, 5,
+        10, 10, 10,
+        6, 6, 9,
+        1, 1, 3,
+        3, 4, 1,
+        6, 9, 6,
+        5, 7, 7,
+        1, 3, 4,
+        1, 3, 1,
+        1, 4, 3,
+        0, 2, 2,
+    ],
+};
+#[rustfmt::skip]
+const OCIUS_FIELDS: ColorMap<[i16; 57]> = ColorMap {
+    white: [
+        0, 0, 0,
+        0, 0, 0,
+        0, 0, 0,
+        0, 0, 0,
+        4, 0, 4,
+        2, 2, 6,
+        0, 0, 0,
+        0, 0, 0,
+        6, 2, 2,
+        9, 9, 9,
+        0, 4, 4,
+        0, 0, 0,
+        0, 0, 0,
+        4, 4, 0,
+        2, 6, 2,
+        0, 0, 0,
+        0, 0, 0,
+        0, 0, 0,
+        0, 0, 0,
+    ],
+    black: [
+        0, 0, 0,
+        0, 0, 0,
+        0, 0, 0,
+        0, 0, 0,
+        6, 2, 2,
+        4, 0, 4,
+        0, 0, 0,
+        0, 0, 0,
+        4, 4, 0,
+        9, 9, 9,
+        2, 2, 6,
+        0, 0, 0,
+        0, 0, 0,
+        2, 6, 2,
+        0, 4, 4,
+        0, 0, 0,
+        0, 0, 0,
+        0, 0, 0,
+        0, 0, 0,
+    ],
+};
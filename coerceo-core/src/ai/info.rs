@@ -0,0 +1,216 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fmt;
+use std::time::Duration;
+
+use super::{AIMove, TimeLimit, LOSE};
+use crate::model::{Color, Move};
+
+/// Live statistics about the AI's latest search, shown in the AI Debug window. Search engines
+/// update it after each iteration.
+#[derive(Clone, Debug, Default)]
+pub struct SearchInfo {
+    /// The full move number of the position being searched
+    pub turn: u64,
+    /// The player to move, or `None` before the first search
+    pub color: Option<Color>,
+    pub time_limit: Option<TimeLimit>,
+    pub node_limit: Option<u64>,
+    /// The depth to search to, if there is no time or node limit
+    pub depth_limit: u8,
+    /// The depth of the last finished iteration, in plies
+    pub depth: u8,
+    /// The deepest ply any line reached, including the quiescence search
+    pub seldepth: u8,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub tt_hits: u64,
+    pub tt_misses: u64,
+    /// The score of the best move for the player to move, in centipieces
+    pub score: Option<i16>,
+    /// The best move so far, followed by the line the AI expects
+    pub pv: Vec<Move>,
+    /// Every move at the root, best first, with its score from the last finished iteration. Only
+    /// the best move's score is exact; the others are upper bounds. Engines that don't score every
+    /// root move leave this empty.
+    pub root_moves: Vec<(Move, i16)>,
+    /// Set if the move was taken from the opening book without searching
+    pub book: bool,
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().cloned()
+    }
+    /// Returns the number of nodes searched per second.
+    pub fn nps(&self) -> u64 {
+        match self.elapsed.as_millis() {
+            0 => 0,
+            millis => (u128::from(self.nodes) * 1000 / millis) as u64,
+        }
+    }
+    /// Returns the fraction of transposition table probes that found an entry, or `None` if the
+    /// table wasn't probed.
+    pub fn tt_hit_rate(&self) -> Option<f32> {
+        match self.tt_hits + self.tt_misses {
+            0 => None,
+            probes => Some(self.tt_hits as f32 / probes as f32),
+        }
+    }
+    /// Returns the best move found so far as if the search had chosen it, so it can be played
+    /// without waiting for the search to finish.
+    pub fn ai_move(&self) -> Option<AIMove> {
+        let mv = self.best_move()?;
+        Some(AIMove {
+            mv,
+            score: self.score.unwrap_or(0),
+            margin: match (self.root_moves.first(), self.root_moves.get(1)) {
+                (Some(&(_, best)), Some(&(_, next))) => Some(best - next),
+                _ => None,
+            },
+            pv: self.pv.clone(),
+            book: self.book,
+        })
+    }
+    /// Returns roughly how far the search has got, from 0 to 1, going by the iterations finished
+    /// so far. A search with a time or node limit is measured against that limit instead of its
+    /// depth.
+    pub fn progress(&self) -> f32 {
+        let progress = if self.book {
+            1.0
+        } else if let Some(limit) = self.time_limit {
+            self.elapsed.as_millis() as f32 / limit.target.as_millis().max(1) as f32
+        } else if let Some(limit) = self.node_limit {
+            self.nodes as f32 / limit.max(1) as f32
+        } else {
+            f32::from(self.depth) / f32::from(self.depth_limit.max(1))
+        };
+        progress.min(1.0)
+    }
+    /// Sums up the search so far on one line, for showing while the computer thinks.
+    pub fn progress_text(&self) -> String {
+        if self.book {
+            return String::from("Playing a move from the opening book");
+        }
+        match self.best_move() {
+            Some(mv) => format!(
+                "Depth {}, {} nodes, best so far {}",
+                self.depth, self.nodes, mv
+            ),
+            None => String::from("Starting the search"),
+        }
+    }
+    /// Returns the statistics as labels and values, in the order they should be shown.
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let mut rows = vec![];
+        if let Some(color) = self.color {
+            rows.push(("Turn", format!("{} ({:?})", self.turn, color)));
+        }
+        let mut limits = vec![];
+        if let Some(limit) = self.time_limit {
+            limits.push(format!(
+                "{}-{} ms",
+                limit.target.as_millis(),
+                limit.max.as_millis()
+            ));
+        }
+        if let Some(limit) = self.node_limit {
+            limits.push(format!("{} nodes", limit));
+        }
+        if limits.is_empty() {
+            limits.push(format!("Depth {}", self.depth_limit));
+        }
+        rows.push(("Limit", limits.join(", ")));
+        if self.book {
+            rows.push(("Source", String::from("Opening book")));
+        } else {
+            rows.push(("Depth", format!("{}/{}", self.depth, self.seldepth)));
+            rows.push(("Nodes", self.nodes.to_string()));
+            rows.push(("Time", format!("{} ms", self.elapsed.as_millis())));
+            rows.push(("Nodes/s", self.nps().to_string()));
+            rows.push((
+                "TT hit rate",
+                match self.tt_hit_rate() {
+                    Some(rate) => format!("{:.1}%", rate * 100.0),
+                    None => String::from("-"),
+                },
+            ));
+            rows.push((
+                "Score",
+                match self.score {
+                    Some(score) => score_text(score),
+                    None => String::from("-"),
+                },
+            ));
+        }
+        rows.push((
+            "Best move",
+            match self.best_move() {
+                Some(mv) => mv.to_string(),
+                None => String::from("-"),
+            },
+        ));
+        rows
+    }
+}
+
+impl fmt::Display for SearchInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (label, value) in self.rows() {
+            writeln!(f, "{}: {}", label, value)?;
+        }
+        if !self.pv.is_empty() {
+            writeln!(f, "PV:")?;
+            for mv in &self.pv {
+                writeln!(f, "    {}", mv)?;
+            }
+        }
+        if !self.root_moves.is_empty() {
+            writeln!(f, "Root moves:")?;
+            for &(mv, score) in &self.root_moves {
+                writeln!(f, "    {} {}", mv, score)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sums up one finished iteration of a search on one line, for the log.
+pub fn iteration_text(depth: u8, nodes: u64, elapsed: Duration, score: i16, pv: &[Move]) -> String {
+    let pv: Vec<String> = pv.iter().map(Move::to_string).collect();
+    format!(
+        "Depth {}: {} nodes in {} ms, score {}, PV {}",
+        depth,
+        nodes,
+        elapsed.as_millis(),
+        score_text(score),
+        pv.join(" ")
+    )
+}
+
+/// Describes a score for the player it belongs to, in pieces.
+pub fn score_text(score: i16) -> String {
+    // Win and loss scores are offset by at most the search depth
+    if score > -LOSE - 0x100 {
+        String::from("Forced win")
+    } else if score < LOSE + 0x100 {
+        String::from("Forced loss")
+    } else {
+        format!("{:+.2}", f32::from(score) / 100.0)
+    }
+}
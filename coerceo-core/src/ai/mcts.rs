@@ -0,0 +1,239 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::Instant;
+
+use super::{iteration_text, root_moves, AIMove, SearchEngine, SearchInfo, SearchSettings, Waker};
+use crate::model::{Board, Move, Outcome};
+
+// The number of playouts for a search depth of 1. Each extra ply of depth doubles it.
+const BASE_PLAYOUTS: u64 = 2000;
+// How often (in playouts) the search checks the clock. Must be a power of two.
+const TIME_CHECK_INTERVAL: u64 = 1024;
+// The exploration constant of UCT. Larger values try less promising moves more often.
+const EXPLORATION: f32 = 1.4;
+// How many centipieces of evaluation make a position about 73% likely to be won
+const EVAL_SCALE: f32 = 200.0;
+
+/// A Monte-Carlo tree search. Instead of playing random games to the end, which takes hundreds of
+/// moves in Coerceo, each new position is scored by the evaluation function. The search keeps
+/// growing the most promising lines, and plays the move it explored the most. Draws by repetition
+/// aren't detected.
+#[derive(Default)]
+pub struct Mcts {
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    board: Board,
+    // The move that led here, or `None` for the root
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    // The sum of the results for the player who made `mv`, from 0 for a loss to 1 for a win
+    wins: f32,
+}
+
+impl Mcts {
+    pub fn new() -> Self {
+        Self { nodes: vec![] }
+    }
+    fn add_node(&mut self, board: Board, mv: Option<Move>, parent: Option<usize>) -> usize {
+        let untried = match board.outcome() {
+            Outcome::InProgress => board.generate_moves().collect(),
+            _ => vec![],
+        };
+        self.nodes.push(Node {
+            board,
+            mv,
+            parent,
+            children: vec![],
+            untried,
+            visits: 0,
+            wins: 0.0,
+        });
+        self.nodes.len() - 1
+    }
+    /// Walks down the tree, picking the child with the best upper confidence bound until it finds
+    /// a node with untried moves (or the end of the game).
+    fn select(&self, mut index: usize) -> usize {
+        while self.nodes[index].untried.is_empty() && !self.nodes[index].children.is_empty() {
+            let log_visits = (self.nodes[index].visits as f32).ln();
+            index = *self.nodes[index]
+                .children
+                .iter()
+                .max_by(|&&a, &&b| {
+                    let a = self.ucb(a, log_visits);
+                    let b = self.ucb(b, log_visits);
+                    a.partial_cmp(&b).expect("UCB scores are never NaN")
+                })
+                .unwrap();
+        }
+        index
+    }
+    fn ucb(&self, index: usize, parent_log_visits: f32) -> f32 {
+        let node = &self.nodes[index];
+        let visits = node.visits as f32;
+        node.wins / visits + EXPLORATION * (parent_log_visits / visits).sqrt()
+    }
+    /// Returns the result of a position for the player who just moved.
+    fn score(board: &Board, settings: &SearchSettings) -> f32 {
+        match board.outcome() {
+            // The player to move has no pieces left
+            Outcome::Win(_) => 1.0,
+            Outcome::InProgress => {
+                let eval = f32::from(settings.evaluate(board));
+                1.0 - 1.0 / (1.0 + (-eval / EVAL_SCALE).exp())
+            }
+            _ => 0.5,
+        }
+    }
+    fn backpropagate(&mut self, mut index: usize, mut result: f32) {
+        loop {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            node.wins += result;
+            result = 1.0 - result;
+            match node.parent {
+                Some(parent) => index = parent,
+                None => break,
+            }
+        }
+    }
+    /// Returns the length of the longest line in the tree.
+    fn max_depth(&self) -> usize {
+        // Children are always added after their parents
+        let mut depths = vec![0; self.nodes.len()];
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let Some(parent) = node.parent {
+                depths[index] = depths[parent] + 1;
+            }
+        }
+        depths.into_iter().max().unwrap_or(0)
+    }
+    /// Returns the principal variation: the most visited child at each level.
+    fn pv(&self) -> Vec<Move> {
+        let mut pv = vec![];
+        let mut index = 0;
+        while let Some(&child) = self.nodes[index]
+            .children
+            .iter()
+            .max_by_key(|&&child| self.nodes[child].visits)
+        {
+            pv.extend(self.nodes[child].mv);
+            index = child;
+        }
+        pv
+    }
+}
+
+impl SearchEngine for Mcts {
+    fn search(
+        &mut self,
+        board: Board,
+        _board_list: Vec<Board>,
+        settings: &SearchSettings,
+        stop_signal: &AtomicBool,
+        info: &RwLock<SearchInfo>,
+        waker: &Waker,
+    ) -> Option<AIMove> {
+        let start = Instant::now();
+        let mut playout_limit = match (settings.node_limit, settings.time_limit) {
+            (Some(limit), _) => Some(limit),
+            (None, Some(_)) => None,
+            (None, None) => Some(BASE_PLAYOUTS << (settings.depth - 1)),
+        };
+        if let Some(limit) = settings.strength.node_limit() {
+            playout_limit = Some(playout_limit.unwrap_or(limit).min(limit));
+        }
+
+        // The tree from the last search isn't reused, since the positions below it have changed
+        self.nodes.clear();
+        let root = self.add_node(board, None, None);
+        self.nodes[root].untried = root_moves(&board, settings.constraint);
+
+        for playout in 0.. {
+            if Some(playout) == playout_limit {
+                break;
+            }
+            if stop_signal.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            let mut index = self.select(root);
+            if let Some(mv) = self.nodes[index].untried.pop() {
+                let mut new_board = self.nodes[index].board;
+                new_board.apply_move(&mv);
+                let child = self.add_node(new_board, Some(mv), Some(index));
+                self.nodes[index].children.push(child);
+                index = child;
+            }
+            let result = Self::score(&self.nodes[index].board, settings);
+            self.backpropagate(index, result);
+
+            if playout & (TIME_CHECK_INTERVAL - 1) == TIME_CHECK_INTERVAL - 1 {
+                if let Some(limit) = settings.time_limit {
+                    // The best move rarely changes late, so stop at the target time
+                    if Instant::now() - start >= limit.target {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let pv = self.pv();
+        let best = self.nodes[root]
+            .children
+            .iter()
+            .max_by_key(|&&child| self.nodes[child].visits)
+            .map(|&child| &self.nodes[child])?;
+        // Convert the win rate back to centipieces. Adding a win and a loss keeps the score finite,
+        // so it never looks like a forced win or loss.
+        let score = EVAL_SCALE * ((best.wins + 1.0) / (best.visits as f32 - best.wins + 1.0)).ln();
+
+        if let Ok(mut info) = info.write() {
+            // The tree is as deep as its longest line, which is at least as long as the PV
+            info.depth = pv.len() as u8;
+            info.seldepth = self.max_depth() as u8;
+            info.nodes = u64::from(self.nodes[root].visits);
+            info.elapsed = Instant::now() - start;
+            info.score = Some(score as i16);
+            info.pv = pv.clone();
+            waker.wake();
+        }
+        if let Some(ref log) = settings.log {
+            log.debug(iteration_text(
+                pv.len() as u8,
+                u64::from(self.nodes[root].visits),
+                Instant::now() - start,
+                score as i16,
+                &pv,
+            ));
+        }
+        Some(AIMove {
+            mv: best.mv.expect("Only the root has no move"),
+            score: score as i16,
+            margin: None,
+            pv,
+            book: false,
+        })
+    }
+}
@@ -0,0 +1,1095 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+mod book;
+mod eval;
+mod info;
+mod mcts;
+mod network;
+mod pns;
+mod see;
+mod tablebase;
+mod waker;
+
+use std::cmp;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub use self::book::Book;
+pub(crate) use self::eval::evaluate;
+pub use self::eval::{clamp_param, EvalParams, Weights, PARAM_RANGE};
+pub(crate) use self::info::iteration_text;
+pub use self::info::{score_text, SearchInfo};
+pub use self::mcts::Mcts;
+pub use self::network::{Accumulator, Network, HIDDEN};
+pub use self::pns::{adjudicate, solve, Solution, Solver, SOLVE_NODE_LIMIT};
+pub(crate) use self::see::see;
+pub use self::tablebase::{Tablebase, Wdl};
+pub use self::waker::Waker;
+use crate::log::Log;
+use crate::model::rng::Rng;
+use crate::model::ttable::{Score, TTable};
+use crate::model::{
+    Board, Color, ColorMap, Move, MoveAnnotated, Outcome, PlayerVitals, RepetitionHistory, UndoInfo,
+};
+
+const AI_MOVE_DELAY: Duration = Duration::from_millis(300);
+
+const INFINITY: i16 = 0x7000;
+const NEG_INFINITY: i16 = -INFINITY;
+const LOSE: i16 = -0x4000;
+// Small contempt factor to discourage draws
+const DRAW: i16 = 1;
+const ASPIRATION_WIDTH: i16 = 51;
+// Delta pruning: we bet that no capture move will raise alpha by more than this many centipieces
+const DELTA_MARGIN: i16 = 200;
+// Quiescence search stops looking at captures this many plies past the main search
+const MAX_QUIESCENCE_DEPTH: i8 = 8;
+// Futility pruning margins, indexed by remaining depth
+const FUTILITY_MARGINS: [i16; 3] = [0, 120, 300];
+// The deepest a timed search will go. Transposition table depths must fit in an i8.
+const MAX_DEPTH: u8 = 64;
+// How often (in nodes) the search checks whether it should stop. Must be a power of two.
+const ABORT_CHECK_INTERVAL: u64 = 1024;
+// Time management: assume the game lasts this many more moves, and let the search run up to this
+// many times over its target when the best move changes late
+const MOVES_TO_GO: u32 = 30;
+const PANIC_FACTOR: u32 = 3;
+
+pub struct AI {
+    status: Status,
+    engine: Arc<Mutex<Box<dyn SearchEngine>>>,
+    engine_kind: EngineKind,
+    pub search_info: Arc<RwLock<SearchInfo>>,
+}
+
+enum Status {
+    Idle,
+    // Either the AI thread is running, or there is a move waiting to be received
+    Thinking {
+        move_recv: Receiver<AIMove>,
+        // We store and load this atomic with Ordering::Relaxed. It *should* be fine because it
+        // doesn't interact with any other atomics--all we want to do is tell the AI thread to stop
+        // searching for a move
+        stop_signal: Arc<AtomicBool>,
+        handle: JoinHandle<()>,
+    },
+}
+
+impl AI {
+    pub fn new() -> Self {
+        Self {
+            status: Status::Idle,
+            engine: Arc::new(Mutex::new(EngineKind::AlphaBeta.create())),
+            engine_kind: EngineKind::AlphaBeta,
+            search_info: Arc::new(RwLock::new(SearchInfo::default())),
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        match self.status {
+            Status::Idle => true,
+            Status::Thinking { .. } => false,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Status::Thinking {
+            ref stop_signal, ..
+        } = self.status
+        {
+            stop_signal.store(true, Ordering::Relaxed);
+            self.status = Status::Idle;
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<AIMove> {
+        use self::TryRecvError::*;
+        match self.status {
+            Status::Idle => None,
+            Status::Thinking { ref move_recv, .. } => match move_recv.try_recv() {
+                Ok(mv) => {
+                    self.status = Status::Idle;
+                    Some(mv)
+                }
+                Err(Empty) => None,
+                Err(Disconnected) => panic!("Tried to receive move from disconnected sender"),
+            },
+        }
+    }
+
+    pub fn think(
+        &mut self,
+        board: Board,
+        board_list: Vec<Board>,
+        settings: SearchSettings,
+        waker: Waker,
+        delay: bool,
+        ply_count: u64,
+    ) {
+        assert_ne!(settings.depth, 0);
+
+        let prev_status = mem::replace(&mut self.status, Status::Idle);
+
+        // A running search keeps the old engine alive until it stops
+        if settings.engine != self.engine_kind {
+            self.engine = Arc::new(Mutex::new(settings.engine.create()));
+            self.engine_kind = settings.engine;
+        }
+
+        let (move_sender, move_recv) = mpsc::channel();
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let stop_signal_clone = stop_signal.clone();
+
+        let engine_mutex = self.engine.clone();
+        let search_info = self.search_info.clone();
+
+        let handle = thread::spawn(move || {
+            let start = Instant::now();
+
+            if let Status::Thinking {
+                stop_signal,
+                handle,
+                ..
+            } = prev_status
+            {
+                stop_signal.store(true, Ordering::Relaxed);
+                handle
+                    .join()
+                    .expect("Old AI thread panicked when new AI thread joined on it");
+            }
+
+            // If the previous AI thread was sent the stop signal, but hasn't received it yet, we
+            // will block here until it finishes. We won't have joined on its handle above because
+            // stop throws away its Status enum.
+            let mut engine = match engine_mutex.lock() {
+                Ok(engine) => engine,
+                Err(_poison_error) => panic!("Search engine mutex is poisoned"),
+            };
+
+            if let Ok(mut info) = search_info.write() {
+                *info = SearchInfo {
+                    turn: ply_count / 2 + 1,
+                    color: Some(board.turn),
+                    time_limit: settings.time_limit,
+                    node_limit: settings.node_limit,
+                    depth_limit: settings.depth,
+                    ..SearchInfo::default()
+                };
+            }
+
+            let book_move = settings.book.as_ref().and_then(|book| {
+                book.probe(&board, &mut Rng::from_time(), |mv| {
+                    settings.constraint.allows(&board, mv)
+                })
+            });
+            let result = match book_move {
+                Some(mv) => {
+                    if let Ok(mut info) = search_info.write() {
+                        info.book = true;
+                        info.pv = vec![mv];
+                    }
+                    if let Some(ref log) = settings.log {
+                        log.debug(format!("Playing {} from the opening book", mv));
+                    }
+                    Some(AIMove {
+                        mv,
+                        score: 0,
+                        margin: None,
+                        pv: vec![mv],
+                        book: true,
+                    })
+                }
+                None => engine.search(
+                    board,
+                    board_list,
+                    &settings,
+                    &stop_signal_clone,
+                    &search_info,
+                    &waker,
+                ),
+            };
+
+            if let Some(ai_move) = result {
+                if stop_signal_clone.load(Ordering::Relaxed) {
+                    return;
+                }
+
+                let elapsed = Instant::now() - start;
+                if delay && elapsed < AI_MOVE_DELAY {
+                    thread::sleep(AI_MOVE_DELAY - elapsed);
+                }
+
+                move_sender.send(ai_move).expect("AI failed to send Move");
+                waker.wake();
+            }
+        });
+
+        self.status = Status::Thinking {
+            move_recv,
+            stop_signal,
+            handle,
+        };
+    }
+}
+
+/// How the AI should search for its next move.
+#[derive(Clone)]
+pub struct SearchSettings {
+    pub depth: u8,
+    pub constraint: Constraint,
+    /// If set, the AI searches as deep as its time allows instead of to `depth`
+    pub time_limit: Option<TimeLimit>,
+    /// If set, the AI searches as deep as this many nodes allow instead of to `depth`. Unlike a
+    /// time limit, the result doesn't depend on the speed of the machine.
+    pub node_limit: Option<u64>,
+    pub eval_params: EvalParams,
+    pub strength: Strength,
+    /// If set, the AI plays a move from this book instead of searching when it can
+    pub book: Option<Arc<Book>>,
+    pub engine: EngineKind,
+    /// If set, positions are scored by this network instead of the handcrafted evaluation
+    pub network: Option<Arc<Network>>,
+    /// If set, the result of each iteration is written here, for the log window
+    pub log: Option<Log>,
+}
+
+impl SearchSettings {
+    /// Scores a position for the player to move with the evaluation these settings ask for.
+    fn evaluate(&self, board: &Board) -> i16 {
+        match self.network {
+            Some(ref network) => network.evaluate(&network.accumulate(board), board),
+            None => evaluate(board, &self.eval_params),
+        }
+    }
+}
+
+/// A way of searching for the AI's next move. The engine is kept between moves, so it can reuse
+/// what it learned from earlier searches.
+pub trait SearchEngine: Send {
+    /// Searches for a move, writing progress to `info`. Returns `None` if the search was
+    /// stopped before it found a move.
+    fn search(
+        &mut self,
+        board: Board,
+        board_list: Vec<Board>,
+        settings: &SearchSettings,
+        stop_signal: &AtomicBool,
+        info: &RwLock<SearchInfo>,
+        waker: &Waker,
+    ) -> Option<AIMove>;
+}
+
+/// The search engines the AI can use.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EngineKind {
+    AlphaBeta,
+    Mcts,
+}
+
+impl EngineKind {
+    pub fn create(self) -> Box<dyn SearchEngine> {
+        match self {
+            EngineKind::AlphaBeta => Box::new(AlphaBeta::new()),
+            EngineKind::Mcts => Box::new(Mcts::new()),
+        }
+    }
+}
+
+/// A negamax search with alpha-beta pruning, iterative deepening, and a transposition table that
+/// is kept between moves.
+pub struct AlphaBeta {
+    ttable: TTable,
+}
+
+impl AlphaBeta {
+    pub fn new() -> Self {
+        Self {
+            ttable: TTable::new(),
+        }
+    }
+}
+
+impl Default for AlphaBeta {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchEngine for AlphaBeta {
+    fn search(
+        &mut self,
+        board: Board,
+        board_list: Vec<Board>,
+        settings: &SearchSettings,
+        stop_signal: &AtomicBool,
+        info: &RwLock<SearchInfo>,
+        waker: &Waker,
+    ) -> Option<AIMove> {
+        match search_root(
+            board,
+            board_list,
+            settings,
+            &mut self.ttable,
+            stop_signal,
+            info,
+            waker,
+        ) {
+            SearchResult::Move(ai_move) => Some(ai_move),
+            SearchResult::Stopped => None,
+        }
+    }
+}
+
+/// How strongly the AI plays. Below full strength, the AI stops searching after a number of nodes,
+/// and it picks randomly among the moves that are nearly as good as its best move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strength {
+    Beginner,
+    Novice,
+    Intermediate,
+    Full,
+}
+
+impl Strength {
+    /// The number of nodes after which the search stops. The first iteration is always finished.
+    pub fn node_limit(self) -> Option<u64> {
+        match self {
+            Strength::Beginner => Some(2_000),
+            Strength::Novice => Some(20_000),
+            Strength::Intermediate => Some(200_000),
+            Strength::Full => None,
+        }
+    }
+    /// Moves scoring within this many centipieces of the best move may be played instead of it.
+    pub fn randomness(self) -> i16 {
+        match self {
+            Strength::Beginner => 60,
+            Strength::Novice => 30,
+            Strength::Intermediate => 10,
+            Strength::Full => 0,
+        }
+    }
+}
+
+/// How long the AI may think about a move.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeLimit {
+    /// No new iteration is started once this is used up, unless the best move just changed
+    pub target: Duration,
+    /// The search is cut off after this long, even in the middle of an iteration
+    pub max: Duration,
+}
+
+impl TimeLimit {
+    /// Budgets the time for one move from the AI's clock. The delay is free to use on every move,
+    /// but unlike the increment, it can't be saved for later.
+    pub fn from_clock(remaining: Duration, increment: Duration, delay: Duration) -> Self {
+        let target = remaining / MOVES_TO_GO + increment * 3 / 4 + delay;
+        // Never risk more than a quarter of the remaining time on one move
+        let max = cmp::min(target * PANIC_FACTOR, remaining / 4 + delay);
+        Self {
+            target: cmp::min(target, max),
+            max,
+        }
+    }
+}
+
+/// A restriction on the moves the AI may play, used to practice specific skills against it. Only
+/// the AI's next move is restricted, so its search still assumes it can play freely afterwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Constraint {
+    None,
+    NoExchanges,
+    NoCaptures,
+}
+
+impl Constraint {
+    pub fn allows(self, board: &Board, mv: &Move) -> bool {
+        match (self, mv) {
+            (Constraint::None, _) => true,
+            (Constraint::NoExchanges, Move::Exchange(..)) => false,
+            (Constraint::NoExchanges, Move::Move(..)) => true,
+            (Constraint::NoCaptures, _) => {
+                let opp_color = board.turn.switch();
+                let mut new_board = *board;
+                new_board.apply_move(mv);
+                new_board.pieces(opp_color) == board.pieces(opp_color)
+            }
+        }
+    }
+}
+
+/// The move chosen by the AI, along with the search data behind it.
+pub struct AIMove {
+    pub mv: Move,
+    pub score: i16,
+    /// How far ahead of the next best move the chosen move scored, if there was another move
+    pub margin: Option<i16>,
+    /// The principal variation, starting with `mv`
+    pub pv: Vec<Move>,
+    /// Whether the move came from the opening book rather than a search
+    pub book: bool,
+}
+
+enum SearchResult {
+    Move(AIMove),
+    Stopped,
+}
+
+fn search_root(
+    mut board: Board,
+    board_list: Vec<Board>,
+    settings: &SearchSettings,
+    ttable: &mut TTable,
+    stop_signal: &AtomicBool,
+    info: &RwLock<SearchInfo>,
+    waker: &Waker,
+) -> SearchResult {
+    let start = Instant::now();
+    ttable.inc_age();
+
+    let history = RepetitionHistory::from_boards(&board_list);
+
+    let mut moves: Vec<(Move, i16)> = root_moves(&board, settings.constraint)
+        .into_iter()
+        .map(|mv| (mv, NEG_INFINITY))
+        .collect();
+
+    let max_depth = match (settings.time_limit, settings.node_limit) {
+        (None, None) => settings.depth,
+        _ => MAX_DEPTH,
+    };
+    let node_limit = match (settings.node_limit, settings.strength.node_limit()) {
+        (Some(a), Some(b)) => Some(cmp::min(a, b)),
+        (a, b) => a.or(b),
+    };
+
+    let network = settings.network.as_ref().map(Arc::as_ref);
+    let mut searcher = Searcher {
+        ttable,
+        eval_params: settings.eval_params,
+        network,
+        accumulators: network
+            .map(|network| vec![network.accumulate(&board)])
+            .unwrap_or_default(),
+        history,
+        stop_signal,
+        // The first iteration is always finished so that there is a move to play
+        deadline: None,
+        node_limit: None,
+        nodes: 0,
+        root_depth: 0,
+        seldepth: 0,
+        aborted: false,
+    };
+
+    let randomness = settings.strength.randomness();
+
+    let mut best: Option<AIMove> = None;
+    // Moves nearly as good as the best move, which a weakened AI may play instead
+    let mut near_best = vec![];
+    let mut best_move_changed = false;
+    let mut iter_score = searcher.evaluate(&board);
+    'iterative_deepening: for depth in 0..max_depth {
+        if stop_signal.load(Ordering::Relaxed) {
+            return SearchResult::Stopped;
+        }
+
+        if let (Some(limit), Some(_)) = (settings.time_limit, &best) {
+            // If the best move just changed, the position is trickier than it looked, so allow
+            // extra time to settle on a move
+            let target = if best_move_changed {
+                cmp::min(limit.target * PANIC_FACTOR, limit.max)
+            } else {
+                limit.target
+            };
+            // Each iteration takes several times longer than the last, so don't start one that
+            // is unlikely to finish in time
+            if (Instant::now() - start) * 2 >= target {
+                break;
+            }
+            searcher.deadline = Some(start + limit.max);
+        }
+        if best.is_some() {
+            searcher.node_limit = node_limit;
+        }
+
+        searcher.root_depth = depth;
+        let mut pv = None;
+
+        // Aspiration window search loop. On a fail-low or fail-high, only the side of the window
+        // that failed is moved, and the window grows gradually rather than doubling.
+        let mut delta = ASPIRATION_WIDTH;
+        let mut alpha = cmp::max(iter_score - delta, NEG_INFINITY);
+        let mut beta = cmp::min(iter_score + delta, INFINITY);
+        loop {
+            let mut best_score = NEG_INFINITY;
+            let mut max_score = alpha;
+            for pair in &mut moves {
+                let undo = searcher.play(&mut board, &pair.0);
+
+                let mut new_pv = vec![];
+
+                // Widen the window below the best score so far so that moves within the
+                // randomness margin get exact scores
+                let floor = cmp::max(max_score - randomness, NEG_INFINITY);
+                let score =
+                    -searcher.alphabeta_negamax(&mut board, &mut new_pv, -beta, -floor, depth);
+                searcher.unplay(&mut board, undo);
+
+                if searcher.aborted {
+                    break 'iterative_deepening;
+                }
+
+                best_score = cmp::max(score, best_score);
+                if score > max_score {
+                    max_score = score;
+                    pv = Some(new_pv);
+                }
+                pair.1 = score;
+
+                if score >= beta {
+                    // The remaining moves will be searched when the window is widened
+                    break;
+                }
+            }
+
+            if best_score <= alpha {
+                // Fail-low: the true score is at most best_score, so pull the lower bound past it
+                beta = (alpha + beta) / 2;
+                alpha = cmp::max(best_score - delta, NEG_INFINITY);
+            } else if best_score >= beta {
+                // Fail-high: the true score is at least best_score
+                beta = cmp::min(best_score + delta, INFINITY);
+            } else {
+                break;
+            }
+            delta += delta / 2;
+        }
+
+        moves.sort_by(|&(_, a), &(_, b)| b.cmp(&a));
+        iter_score = moves[0].1;
+
+        best_move_changed = match best {
+            Some(ref best) => best.mv != moves[0].0,
+            None => false,
+        };
+
+        // The PV is built from the leaf up, so reverse it
+        let mut line = vec![moves[0].0];
+        if let Some(pv) = pv {
+            line.extend(pv.into_iter().rev());
+        }
+
+        if let Ok(mut info) = info.write() {
+            info.depth = depth + 1;
+            info.seldepth = searcher.seldepth;
+            info.nodes = searcher.nodes;
+            info.elapsed = Instant::now() - start;
+            info.tt_hits = searcher.ttable.hits();
+            info.tt_misses = searcher.ttable.misses();
+            info.score = Some(moves[0].1);
+            info.pv = line.clone();
+            info.root_moves = moves.clone();
+            waker.wake();
+        }
+        if let Some(ref log) = settings.log {
+            log.debug(iteration_text(
+                depth + 1,
+                searcher.nodes,
+                Instant::now() - start,
+                moves[0].1,
+                &line,
+            ));
+        }
+
+        best = Some(AIMove {
+            mv: moves[0].0,
+            score: moves[0].1,
+            margin: moves.get(1).map(|&(_, score)| moves[0].1 - score),
+            pv: line,
+            book: false,
+        });
+        // Scores above the window floor are exact, so they can be compared fairly
+        near_best = moves
+            .iter()
+            .take_while(|&&(_, score)| randomness > 0 && score > moves[0].1 - randomness)
+            .cloned()
+            .collect();
+    }
+
+    if stop_signal.load(Ordering::Relaxed) {
+        return SearchResult::Stopped;
+    }
+    match best {
+        Some(ai_move) => {
+            if near_best.len() > 1 {
+                let (mv, score) =
+                    near_best[Rng::from_time().below(near_best.len() as u64) as usize];
+                if mv != ai_move.mv {
+                    return SearchResult::Move(AIMove {
+                        mv,
+                        score,
+                        margin: None,
+                        pv: vec![mv],
+                        book: false,
+                    });
+                }
+            }
+            SearchResult::Move(ai_move)
+        }
+        None => SearchResult::Stopped,
+    }
+}
+
+/// Returns what a move by `us` won, given the vitals before and after it: the number of opponent
+/// pieces it captured, then the number of tiles it gained (which is negative for an exchange).
+/// Comparing the results puts piece captures before tile captures.
+fn capture_gain(
+    us: Color,
+    before: &ColorMap<PlayerVitals>,
+    after: &ColorMap<PlayerVitals>,
+) -> (u8, i8) {
+    let them = us.switch();
+    (
+        before.get(them).pieces - after.get(them).pieces,
+        after.get(us).hexes as i8 - before.get(us).hexes as i8,
+    )
+}
+
+/// Returns the moves the AI may play at the root, following the constraint if it can.
+fn root_moves(board: &Board, constraint: Constraint) -> Vec<Move> {
+    let mut moves: Vec<_> = board
+        .generate_moves()
+        .filter(|mv| constraint.allows(board, mv))
+        .collect();
+
+    if moves.is_empty() {
+        // If every move breaks the constraint, then we have no choice but to break it
+        moves = board.generate_moves().collect();
+    }
+
+    if moves.is_empty() {
+        panic!("AI has no moves");
+    }
+    moves
+}
+
+/// Describes the AI's move in a sentence, using what it removed from the board and the search data
+/// behind it.
+pub fn explain_move(board: &Board, mv: &MoveAnnotated, ai_move: &AIMove) -> String {
+    let plural = |count: usize, noun: &str| match count {
+        1 => format!("a {}", noun),
+        _ => format!("{} {}s", count, noun),
+    };
+
+    let mut reasons = vec![];
+    if let Move::Exchange(..) = mv.mv {
+        reasons.push(format!(
+            "exchanges {} for a piece",
+            plural(usize::from(board.hexes_to_exchange), "tile")
+        ));
+    }
+    let captured = mv.captured_pieces().count();
+    if captured > 0 {
+        reasons.push(format!("captures {}", plural(captured, "piece")));
+    }
+    if !mv.removed_hexes.is_empty() {
+        reasons.push(format!(
+            "collapses {}",
+            plural(mv.removed_hexes.len(), "tile")
+        ));
+    }
+
+    // If the computer expects to capture a piece with its next move, then say so
+    let opp_color = board.turn.switch();
+    let opp_pieces = board.pieces(opp_color) - mv.removed_pieces.len() as u8;
+    let mut future = *board;
+    let threatens = ai_move.pv.len() >= 3
+        && ai_move.pv[..3].iter().all(|mv| {
+            let legal = future.can_apply_move(mv);
+            if legal {
+                future.apply_move(mv);
+            }
+            legal
+        })
+        && future.pieces(opp_color) < opp_pieces;
+    if threatens {
+        reasons.push(String::from("threatens to capture a piece"));
+    }
+
+    // Win and loss scores are offset by at most the search depth
+    if ai_move.book {
+        reasons.push(String::from("follows the opening book"));
+    } else if ai_move.score > -LOSE - 0x100 {
+        reasons.push(String::from("leads to a forced win"));
+    } else if ai_move.score < LOSE + 0x100 {
+        reasons.push(String::from("delays a forced loss"));
+    } else if let Some(margin) = ai_move.margin {
+        reasons.push(format!("best by {:.1}", f32::from(margin) / 100.0));
+    }
+
+    let mut explanation = if reasons.is_empty() {
+        String::from("quiet move")
+    } else {
+        reasons.join(", ")
+    };
+    explanation[..1].make_ascii_uppercase();
+    explanation.push('.');
+    explanation
+}
+
+/// The state of a single search, shared by all of its nodes.
+struct Searcher<'a> {
+    ttable: &'a mut TTable,
+    eval_params: EvalParams,
+    network: Option<&'a Network>,
+    // The network's hidden layer sums for each position on the current line, if there is a
+    // network
+    accumulators: Vec<Accumulator>,
+    // The positions before the one being searched, from the game and then the current line
+    history: RepetitionHistory,
+    stop_signal: &'a AtomicBool,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    nodes: u64,
+    // The depth of the current iteration, which is passed to the nodes after the root moves
+    root_depth: u8,
+    // The deepest ply reached so far
+    seldepth: u8,
+    // Once set, every node returns immediately and its score must be ignored
+    aborted: bool,
+}
+
+impl<'a> Searcher<'a> {
+    /// Makes a move on the board, updating the network's accumulator along with it. Every call
+    /// must be followed by a call to `unplay` once the new position has been searched.
+    fn play(&mut self, board: &mut Board, mv: &Move) -> UndoInfo {
+        match self.network {
+            Some(network) => {
+                let before = *board;
+                let undo = board.make_move(mv);
+                let mut acc = *self
+                    .accumulators
+                    .last()
+                    .expect("The root has an accumulator");
+                network.update(&mut acc, &before, board);
+                self.accumulators.push(acc);
+                undo
+            }
+            None => board.make_move(mv),
+        }
+    }
+    fn unplay(&mut self, board: &mut Board, undo: UndoInfo) {
+        board.unmake_move(undo);
+        self.accumulators.pop();
+    }
+    /// Scores the position at the end of the current line for the player to move.
+    fn evaluate(&self, board: &Board) -> i16 {
+        match self.network {
+            Some(network) => {
+                let acc = self
+                    .accumulators
+                    .last()
+                    .expect("The root has an accumulator");
+                network.evaluate(acc, board)
+            }
+            None => evaluate(board, &self.eval_params),
+        }
+    }
+    /// Returns the moves of a position with the captures first, ordered by static exchange
+    /// evaluation.
+    fn ordered_moves(&self, board: &Board) -> Vec<Move> {
+        let mut captures: Vec<(i16, Move)> = vec![];
+        for mv in board.generate_captures() {
+            // A move that captures several pieces is generated once for each of them
+            if captures.iter().all(|&(_, capture)| capture != mv) {
+                captures.push((see(board, &mv, &self.eval_params), mv));
+            }
+        }
+        captures.sort_by_key(|&(score, _)| cmp::Reverse(score));
+
+        let mut moves: Vec<_> = captures.iter().map(|&(_, mv)| mv).collect();
+        moves.extend(
+            board
+                .generate_moves()
+                .filter(|mv| captures.iter().all(|&(_, capture)| capture != *mv)),
+        );
+        moves
+    }
+    /// Counts a node and checks whether the search has been stopped or has run out of time.
+    fn should_abort(&mut self) -> bool {
+        self.nodes += 1;
+        if let Some(limit) = self.node_limit {
+            self.aborted |= self.nodes > limit;
+        }
+        if !self.aborted && self.nodes & (ABORT_CHECK_INTERVAL - 1) == 0 {
+            let out_of_time = match self.deadline {
+                Some(deadline) => Instant::now() >= deadline,
+                None => false,
+            };
+            self.aborted = out_of_time || self.stop_signal.load(Ordering::Relaxed);
+        }
+        self.aborted
+    }
+
+    fn alphabeta_negamax(
+        &mut self,
+        board: &mut Board,
+        pv: &mut Vec<Move>,
+        mut alpha: i16,
+        mut beta: i16,
+        depth: u8,
+    ) -> i16 {
+        if self.should_abort() {
+            return 0;
+        }
+
+        let mut set_pv = move |score, new_pv| {
+            if score > alpha && score < beta {
+                *pv = new_pv;
+            }
+        };
+
+        use self::Outcome::*;
+        match board.outcome() {
+            DrawStalemate | DrawInsufficientMaterial => {
+                // This is safe to do because Board does not detect draws by threefold repetition
+                self.ttable
+                    .set(board.zobrist, Score::Exact(DRAW), depth as i8);
+                set_pv(DRAW, vec![]);
+                return DRAW;
+            }
+            Win(color) => {
+                assert_ne!(color, board.turn);
+                // Weight score by depth to encourage shorter wins. The shorter the win, the
+                // greater `depth` will be, and so the larger the score will be. This also
+                // encourages the AI to prolong a loss.
+                let score = LOSE - i16::from(depth);
+                self.ttable
+                    .set(board.zobrist, Score::Exact(score), depth as i8);
+                set_pv(score, vec![]);
+                return score;
+            }
+            DrawNoProgress => {
+                // Not stored in the table, as the same position can come up with fewer
+                // reversible plies played
+                set_pv(DRAW, vec![]);
+                return DRAW;
+            }
+            InProgress => {}
+            DrawThreefoldRepetition | WinByResignation(_) | WinOnTime(_) => unreachable!(),
+        }
+
+        if self.history.is_repetition(board) {
+            set_pv(DRAW, vec![]);
+            return DRAW;
+        }
+
+        if depth == 0 {
+            let score = self.quiescence_search(board, alpha, beta, depth as i8);
+            set_pv(score, vec![]);
+            return score;
+        }
+
+        match self.ttable.get(board.zobrist, depth as i8) {
+            Some(Score::Exact(score)) => {
+                // This will cut the PV short
+                // TODO: Store the best move in the table and get the PV from that?
+                set_pv(score, vec![]);
+                return score;
+            }
+            Some(Score::Beta(score)) => {
+                if score >= beta {
+                    return score;
+                }
+                alpha = cmp::max(alpha, score);
+            }
+            Some(Score::Alpha(score)) => {
+                if score <= alpha {
+                    return score;
+                }
+                beta = cmp::min(beta, score);
+            }
+            None => {}
+        }
+
+        let mut best_score = NEG_INFINITY;
+        let mut best_move = None;
+
+        // Only needed for futility pruning, which is limited to shallow depths
+        let static_eval = if (depth as usize) < FUTILITY_MARGINS.len() {
+            self.evaluate(board)
+        } else {
+            0
+        };
+
+        let (hash, vitals) = (board.zobrist, board.vitals);
+        let mut new_pv = vec![];
+        for mv in self.ordered_moves(board) {
+            let undo = self.play(board, &mv);
+
+            if is_futile(undo.is_quiet(), depth, static_eval, alpha) {
+                self.unplay(board, undo);
+                best_score = cmp::max(static_eval + FUTILITY_MARGINS[depth as usize], best_score);
+                continue;
+            }
+
+            self.history.push(hash, vitals);
+            let score = -self.alphabeta_negamax(board, &mut new_pv, -beta, -alpha, depth - 1);
+            self.history.pop();
+            self.unplay(board, undo);
+
+            if self.aborted {
+                return 0;
+            }
+
+            best_score = cmp::max(score, best_score);
+
+            if score >= beta {
+                self.ttable
+                    .set(board.zobrist, Score::Beta(score), depth as i8);
+                return score;
+            } else if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+            }
+        }
+
+        if let Some(mv) = best_move {
+            self.ttable
+                .set(board.zobrist, Score::Exact(best_score), depth as i8);
+            new_pv.push(mv);
+            set_pv(best_score, new_pv);
+        } else {
+            self.ttable
+                .set(board.zobrist, Score::Alpha(best_score), depth as i8);
+        }
+        best_score
+    }
+
+    fn quiescence_search(
+        &mut self,
+        board: &mut Board,
+        mut alpha: i16,
+        mut beta: i16,
+        depth: i8,
+    ) -> i16 {
+        if self.should_abort() {
+            return 0;
+        }
+
+        // Quiescence depths count down from 0, so this is the number of plies from the root
+        let ply = (i16::from(self.root_depth) + 1 - i16::from(depth)) as u8;
+        self.seldepth = cmp::max(self.seldepth, ply);
+
+        let stand_pat = self.evaluate(board);
+        if stand_pat >= beta {
+            return stand_pat;
+        } else if stand_pat + DELTA_MARGIN < alpha {
+            // We don't switch delta pruning off in the endgame because a draw by insufficient
+            // material is very unlikely (both sides must be unable to exchange and each have only
+            // 1 piece left)
+            return stand_pat + DELTA_MARGIN;
+        } else if alpha < stand_pat {
+            alpha = stand_pat;
+        }
+        if depth <= -MAX_QUIESCENCE_DEPTH {
+            return stand_pat;
+        }
+        let alpha_orig = alpha;
+
+        match self.ttable.get(board.zobrist, depth) {
+            Some(Score::Exact(score)) => {
+                return score;
+            }
+            Some(Score::Beta(score)) => {
+                if score >= beta {
+                    return score;
+                }
+                alpha = cmp::max(alpha, score);
+            }
+            Some(Score::Alpha(score)) => {
+                if score <= alpha {
+                    return score;
+                }
+                beta = cmp::min(beta, score);
+            }
+            None => {}
+        };
+
+        // Skip captures that lose material to recaptures, and try the captures that win the most
+        // first, so that the rest are more likely to be cut off
+        let mut captures = vec![];
+        for mv in board.generate_captures() {
+            if see(board, &mv, &self.eval_params) >= 0 {
+                let before = board.vitals;
+                let undo = board.make_move(&mv);
+                captures.push((
+                    capture_gain(board.turn.switch(), &before, &board.vitals),
+                    mv,
+                ));
+                board.unmake_move(undo);
+            }
+        }
+        captures.sort_by_key(|&(gain, _)| cmp::Reverse(gain));
+
+        let mut best_score = stand_pat;
+        for (_, mv) in captures {
+            let undo = self.play(board, &mv);
+            let score = -self.quiescence_search(board, -beta, -alpha, depth - 1);
+            self.unplay(board, undo);
+
+            if self.aborted {
+                return 0;
+            }
+
+            best_score = cmp::max(score, best_score);
+
+            if score >= beta {
+                self.ttable.set(board.zobrist, Score::Beta(score), depth);
+                return score;
+            } else if score > alpha {
+                alpha = score;
+            }
+        }
+
+        if best_score > alpha_orig {
+            self.ttable
+                .set(board.zobrist, Score::Exact(best_score), depth);
+        } else {
+            self.ttable
+                .set(board.zobrist, Score::Alpha(best_score), depth);
+        }
+        best_score
+    }
+}
+
+/// Near the horizon, a quiet move (one that doesn't capture pieces or tiles, see
+/// `UndoInfo::is_quiet`) is futile if even a generous margin on top of the static evaluation can't
+/// raise alpha. Moves that change either player's vitals are never futile, so tactical captures
+/// are always searched.
+pub(crate) fn is_futile(quiet: bool, depth: u8, static_eval: i16, alpha: i16) -> bool {
+    quiet
+        && (depth as usize) < FUTILITY_MARGINS.len()
+        && static_eval + FUTILITY_MARGINS[depth as usize] <= alpha
+}
@@ -0,0 +1,215 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::cmp;
+use std::fmt;
+
+use crate::model::bitboard::BitBoardExt;
+use crate::model::{Board, Color};
+
+/// The number of neurons in the hidden layer
+pub const HIDDEN: usize = 32;
+/// One input for each field of each color
+const FIELD_INPUTS: usize = 2 * 57;
+/// White pieces, black pieces, white captured tiles, and black captured tiles
+const VITALS_INPUTS: usize = 4;
+// Hidden neurons are clipped to 0..=ACTIVATION_MAX
+const ACTIVATION_MAX: i32 = 255;
+// The output layer's sum is divided by this to get centipieces
+const OUTPUT_DIVISOR: i32 = 256;
+// Keep network scores well away from the scores of won and lost games
+const MAX_SCORE: i32 = 0x2000;
+
+/// A small neural network evaluation in the style of NNUE. The inputs are the fields occupied by
+/// each player's pieces plus the players' vitals, which feed a single hidden layer. Because the
+/// hidden layer's sums only change by a few weight columns after each move, the search keeps them
+/// in an `Accumulator` and updates it instead of recomputing it for every position.
+///
+/// A network file is a list of whitespace-separated integers: the hidden layer size (which must
+/// be `HIDDEN`), then the hidden biases, the weights of each white field input, the weights of
+/// each black field input, the weights of each vitals input, the output weights, and finally the
+/// output bias. Lines starting with `#` are skipped. The output is the score for white.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Network {
+    hidden_bias: [i32; HIDDEN],
+    field_weights: Vec<[i32; HIDDEN]>,
+    vitals_weights: [[i32; HIDDEN]; VITALS_INPUTS],
+    output_weights: [i32; HIDDEN],
+    output_bias: i32,
+}
+
+/// The sums of the hidden layer for one position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Accumulator([i32; HIDDEN]);
+
+impl Network {
+    /// Parses a network file, or returns `None` if it is malformed.
+    pub fn parse(contents: &str) -> Option<Self> {
+        let values = contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .flat_map(str::split_whitespace)
+            // Weights fit in an i16 so that the sums can't overflow
+            .map(|value| value.parse::<i16>().map(i32::from))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+
+        let layers = 1 + FIELD_INPUTS + VITALS_INPUTS + 1;
+        if values.len() != 1 + layers * HIDDEN + 1 || values[0] != HIDDEN as i32 {
+            return None;
+        }
+        let mut layers = values[1..].chunks_exact(HIDDEN).map(|chunk| {
+            let mut layer = [0; HIDDEN];
+            layer.copy_from_slice(chunk);
+            layer
+        });
+        let mut next_layer = || layers.next().expect("The length was checked above");
+
+        let hidden_bias = next_layer();
+        let field_weights = (0..FIELD_INPUTS).map(|_| next_layer()).collect();
+        let mut vitals_weights = [[0; HIDDEN]; VITALS_INPUTS];
+        for weights in vitals_weights.iter_mut() {
+            *weights = next_layer();
+        }
+        let output_weights = next_layer();
+
+        Some(Self {
+            hidden_bias,
+            field_weights,
+            vitals_weights,
+            output_weights,
+            output_bias: values[values.len() - 1],
+        })
+    }
+    /// Computes the hidden layer's sums from scratch.
+    pub fn accumulate(&self, board: &Board) -> Accumulator {
+        let mut acc = Accumulator(self.hidden_bias);
+        for (input, scale) in self.inputs(board) {
+            acc.add(&self.input_weights(input), scale);
+        }
+        acc
+    }
+    /// Updates the hidden layer's sums after a move from `before` to `after`. Only the fields and
+    /// vitals that changed are touched.
+    pub fn update(&self, acc: &mut Accumulator, before: &Board, after: &Board) {
+        for (offset, &color) in [Color::White, Color::Black].iter().enumerate() {
+            let old = before.fields(color);
+            let new = after.fields(color);
+            for field in (old & !new).iter() {
+                acc.add(
+                    &self.field_weights[offset * 57 + field.trailing_zeros() as usize],
+                    -1,
+                );
+            }
+            for field in (new & !old).iter() {
+                acc.add(
+                    &self.field_weights[offset * 57 + field.trailing_zeros() as usize],
+                    1,
+                );
+            }
+        }
+        let (old, new) = (vitals(before), vitals(after));
+        for (i, (&old, &new)) in old.iter().zip(new.iter()).enumerate() {
+            if old != new {
+                acc.add(&self.vitals_weights[i], new - old);
+            }
+        }
+    }
+    /// Scores a position for the player to move.
+    pub fn evaluate(&self, acc: &Accumulator, board: &Board) -> i16 {
+        let mut output = self.output_bias;
+        for (&sum, &weight) in acc.0.iter().zip(self.output_weights.iter()) {
+            output += clipped_relu(sum) * weight;
+        }
+        let score = output / OUTPUT_DIVISOR;
+        let score = if score < 0 {
+            -cmp::min(-score, MAX_SCORE)
+        } else {
+            cmp::min(score, MAX_SCORE)
+        };
+        match board.turn {
+            Color::White => score as i16,
+            Color::Black => -score as i16,
+        }
+    }
+    /// Returns the active inputs of a position, along with their values.
+    fn inputs(&self, board: &Board) -> Vec<(usize, i32)> {
+        let mut inputs: Vec<_> = [Color::White, Color::Black]
+            .iter()
+            .enumerate()
+            .flat_map(|(offset, &color)| {
+                board
+                    .fields(color)
+                    .iter()
+                    .map(move |field| (offset * 57 + field.trailing_zeros() as usize, 1))
+            })
+            .collect();
+        for (i, &value) in vitals(board).iter().enumerate() {
+            inputs.push((FIELD_INPUTS + i, value));
+        }
+        inputs
+    }
+    fn input_weights(&self, input: usize) -> [i32; HIDDEN] {
+        if input < FIELD_INPUTS {
+            self.field_weights[input]
+        } else {
+            self.vitals_weights[input - FIELD_INPUTS]
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HIDDEN)?;
+        write_layer(f, &self.hidden_bias)?;
+        for weights in self.field_weights.iter().chain(self.vitals_weights.iter()) {
+            write_layer(f, weights)?;
+        }
+        write_layer(f, &self.output_weights)?;
+        writeln!(f, "{}", self.output_bias)
+    }
+}
+
+fn write_layer(f: &mut fmt::Formatter, layer: &[i32; HIDDEN]) -> fmt::Result {
+    let values: Vec<_> = layer.iter().map(i32::to_string).collect();
+    writeln!(f, "{}", values.join(" "))
+}
+
+impl Accumulator {
+    fn add(&mut self, weights: &[i32; HIDDEN], scale: i32) {
+        for (sum, &weight) in self.0.iter_mut().zip(weights.iter()) {
+            *sum += weight * scale;
+        }
+    }
+}
+
+fn vitals(board: &Board) -> [i32; VITALS_INPUTS] {
+    [
+        i32::from(board.pieces(Color::White)),
+        i32::from(board.pieces(Color::Black)),
+        i32::from(board.hexes(Color::White)),
+        i32::from(board.hexes(Color::Black)),
+    ]
+}
+
+fn clipped_relu(sum: i32) -> i32 {
+    if sum < 0 {
+        0
+    } else {
+        cmp::min(sum, ACTIVATION_MAX)
+    }
+}
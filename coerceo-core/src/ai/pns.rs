@@ -0,0 +1,251 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::cmp;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+use super::Waker;
+use crate::model::{Board, Color, Move, Outcome};
+
+/// The number of nodes the "Attempt to solve" action may create before giving up. Each node holds
+/// a board, so this also bounds the memory the solver uses.
+pub const SOLVE_NODE_LIMIT: usize = 300_000;
+// Proof and disproof numbers saturate here, which stands for a node that can't be (dis)proven
+const INFINITY: u32 = 1 << 30;
+
+/// The result of trying to solve a position, from the point of view of the player to move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Solution {
+    /// The player to move wins by playing this move
+    Win(Move),
+    Loss,
+    /// Neither player can force a win
+    Draw,
+    /// The search ran out of nodes or was stopped before it found the result
+    Unknown,
+}
+
+/// Tries to prove the result of a position with proof-number search, creating at most `node_limit`
+/// nodes for each side. Draws by repetition aren't detected, so positions where the game could go
+/// on forever stay unsolved.
+pub fn solve(board: &Board, node_limit: usize, stop_signal: &AtomicBool) -> Solution {
+    let mut search = ProofSearch::new(*board, board.turn);
+    match search.run(node_limit, stop_signal) {
+        Some(true) => {
+            return Solution::Win(
+                search
+                    .winning_move()
+                    .expect("A proven root has a proven child"),
+            )
+        }
+        None => return Solution::Unknown,
+        Some(false) => {}
+    }
+    match ProofSearch::new(*board, board.turn.switch()).run(node_limit, stop_signal) {
+        Some(true) => Solution::Loss,
+        Some(false) => Solution::Draw,
+        None => Solution::Unknown,
+    }
+}
+
+/// Returns the player who can force a win from a position, if either can be proven to within
+/// `node_limit` nodes. This is meant for adjudicating long engine matches.
+pub fn adjudicate(board: &Board, node_limit: usize) -> Option<Color> {
+    match solve(board, node_limit, &AtomicBool::new(false)) {
+        Solution::Win(_) => Some(board.turn),
+        Solution::Loss => Some(board.turn.switch()),
+        Solution::Draw | Solution::Unknown => None,
+    }
+}
+
+/// A proof-number search tree for the question "can `attacker` force a win?"
+struct ProofSearch {
+    attacker: Color,
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    board: Board,
+    // The move that led here, or `None` for the root
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // The minimum number of leaves that must be proven to prove this node
+    proof: u32,
+    // The minimum number of leaves that must be disproven to disprove this node
+    disproof: u32,
+}
+
+impl ProofSearch {
+    fn new(board: Board, attacker: Color) -> Self {
+        let mut search = Self {
+            attacker,
+            nodes: vec![],
+        };
+        search.add_node(board, None, None);
+        search
+    }
+    fn add_node(&mut self, board: Board, mv: Option<Move>, parent: Option<usize>) -> usize {
+        let (proof, disproof) = match board.outcome() {
+            Outcome::Win(color) if color == self.attacker => (0, INFINITY),
+            Outcome::InProgress => (1, 1),
+            _ => (INFINITY, 0),
+        };
+        self.nodes.push(Node {
+            board,
+            mv,
+            parent,
+            children: vec![],
+            proof,
+            disproof,
+        });
+        self.nodes.len() - 1
+    }
+    /// Grows the tree until the root is proven or disproven. Returns `None` if it runs out of
+    /// nodes or is stopped first.
+    fn run(&mut self, node_limit: usize, stop_signal: &AtomicBool) -> Option<bool> {
+        loop {
+            let root = &self.nodes[0];
+            if root.proof == 0 {
+                return Some(true);
+            } else if root.disproof == 0 {
+                return Some(false);
+            } else if self.nodes.len() >= node_limit || stop_signal.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            let index = self.most_proving_node();
+            self.expand(index);
+            self.update_ancestors(index);
+        }
+    }
+    fn is_or_node(&self, index: usize) -> bool {
+        self.nodes[index].board.turn == self.attacker
+    }
+    /// Walks down to the leaf whose result would do the most to settle the root: at the
+    /// attacker's nodes, the child closest to a proof, and at the defender's nodes, the child
+    /// closest to a disproof.
+    fn most_proving_node(&self) -> usize {
+        let mut index = 0;
+        while !self.nodes[index].children.is_empty() {
+            let children = &self.nodes[index].children;
+            index = if self.is_or_node(index) {
+                *children
+                    .iter()
+                    .min_by_key(|&&child| self.nodes[child].proof)
+                    .unwrap()
+            } else {
+                *children
+                    .iter()
+                    .min_by_key(|&&child| self.nodes[child].disproof)
+                    .unwrap()
+            };
+        }
+        index
+    }
+    fn expand(&mut self, index: usize) {
+        let board = self.nodes[index].board;
+        let children: Vec<_> = board
+            .generate_moves()
+            .map(|mv| {
+                let mut new_board = board;
+                new_board.apply_move(&mv);
+                self.add_node(new_board, Some(mv), Some(index))
+            })
+            .collect();
+        self.nodes[index].children = children;
+    }
+    fn update_ancestors(&mut self, mut index: usize) {
+        loop {
+            let (proof, disproof) = {
+                let children = self.nodes[index].children.iter().map(|&c| &self.nodes[c]);
+                if self.is_or_node(index) {
+                    let proof = children.clone().map(|c| c.proof).min();
+                    let disproof = children.fold(0, |sum, c| cmp::min(sum + c.disproof, INFINITY));
+                    (proof.unwrap_or(INFINITY), disproof)
+                } else {
+                    let proof = children
+                        .clone()
+                        .fold(0, |sum, c| cmp::min(sum + c.proof, INFINITY));
+                    let disproof = children.map(|c| c.disproof).min();
+                    (proof, disproof.unwrap_or(INFINITY))
+                }
+            };
+            let node = &mut self.nodes[index];
+            node.proof = proof;
+            node.disproof = disproof;
+            match node.parent {
+                Some(parent) => index = parent,
+                None => break,
+            }
+        }
+    }
+    fn winning_move(&self) -> Option<Move> {
+        self.nodes[0]
+            .children
+            .iter()
+            .map(|&child| &self.nodes[child])
+            .find(|child| child.proof == 0)
+            .and_then(|child| child.mv)
+    }
+}
+
+/// Runs `solve` on a background thread, so that the window stays responsive.
+#[derive(Default)]
+pub struct Solver {
+    status: Option<(Receiver<Solution>, Arc<AtomicBool>)>,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self { status: None }
+    }
+    pub fn is_running(&self) -> bool {
+        self.status.is_some()
+    }
+    /// Starts solving a position, stopping any earlier attempt.
+    pub fn start(&mut self, board: Board, waker: Waker) {
+        self.stop();
+        let (sender, recv) = mpsc::channel();
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let stop_signal_clone = stop_signal.clone();
+        thread::spawn(move || {
+            let solution = solve(&board, SOLVE_NODE_LIMIT, &stop_signal_clone);
+            // The receiver is gone if the attempt was stopped
+            if sender.send(solution).is_ok() {
+                waker.wake();
+            }
+        });
+        self.status = Some((recv, stop_signal));
+    }
+    pub fn stop(&mut self) {
+        if let Some((_, stop_signal)) = self.status.take() {
+            stop_signal.store(true, Ordering::Relaxed);
+        }
+    }
+    pub fn try_recv(&mut self) -> Option<Solution> {
+        let solution = match self.status {
+            Some((ref recv, _)) => recv.try_recv().ok()?,
+            None => return None,
+        };
+        self.status = None;
+        Some(solution)
+    }
+}
@@ -0,0 +1,70 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::cmp;
+
+use super::EvalParams;
+use crate::model::{Board, Move};
+
+// How many recaptures deep the exchange is followed
+const MAX_RECAPTURES: u8 = 6;
+
+/// Static exchange evaluation: returns how much material, in centipieces, a capture wins once
+/// the pieces that recapture on the capturing piece's destination have had their turn. Each side
+/// may stop recapturing whenever continuing would lose material, so a negative result means the
+/// capture loses material however the exchange plays out. Tiles are valued at their opening value.
+///
+/// Only captures that take the piece that just moved count as recaptures, which keeps this cheap
+/// enough to call on every capture.
+pub(crate) fn see(board: &Board, mv: &Move, params: &EvalParams) -> i16 {
+    swap(board, mv, params, MAX_RECAPTURES)
+}
+
+fn swap(board: &Board, mv: &Move, params: &EvalParams, depth: u8) -> i16 {
+    let mut new_board = *board;
+    new_board.apply_move(mv);
+    let gain = material_gain(board, &new_board, params);
+
+    let to = match *mv {
+        Move::Move(_, to, _) if depth > 0 => to,
+        // An exchange leaves no piece behind to recapture
+        _ => return gain,
+    };
+
+    let us = board.turn;
+    let best_recapture = new_board
+        .generate_captures()
+        .filter(|recapture| {
+            let mut after = new_board;
+            after.apply_move(recapture);
+            after.fields(us) & to == 0
+        })
+        .map(|recapture| swap(&new_board, &recapture, params, depth - 1))
+        .max()
+        .unwrap_or(0);
+    gain - cmp::max(best_recapture, 0)
+}
+
+/// Returns the material the player to move in `board` won by moving to `new_board`.
+fn material_gain(board: &Board, new_board: &Board, params: &EvalParams) -> i16 {
+    let us = board.turn;
+    let them = us.switch();
+    let piece = params.piece * i16::from(board.hexes_to_exchange);
+    let pieces = i16::from(board.pieces(them)) - i16::from(new_board.pieces(them));
+    let tiles = i16::from(new_board.hexes(us)) - i16::from(board.hexes(us));
+    pieces * piece + tiles * params.opening.tile
+}
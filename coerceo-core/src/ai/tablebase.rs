@@ -0,0 +1,419 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Endgame tablebases: the win/draw/loss result of every position with few pieces and tiles.
+//!
+//! Only the shape of the board matters in these endgames, not where it sits or which way it
+//! faces, so positions are rotated, reflected and translated to a canonical spot before they are
+//! indexed. Removing a tile never splits the board,
+//! so only connected sets of tiles are generated.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use crate::model::bitboard::{BitBoard, BitBoardExt};
+use crate::model::{Board, Color, ColorMap, GameType, HexCoord, Outcome, Symmetry, EXCHANGE_COSTS};
+
+/// The most pieces, counting both players, in a tablebase position
+pub const MAX_PIECES: u32 = 4;
+/// The most tiles in a tablebase position
+pub const MAX_TILES: u32 = 3;
+
+const MAGIC: &[u8; 4] = b"CTB3";
+// The lowest bit of each hex in a bitboard
+const HEX_LOW_BITS: BitBoard = 0x49_249_249_249_249;
+const HEX_DIRECTIONS: [(i8, i8); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+/// The result of a position for the player to move, with perfect play from both sides.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wdl {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// A tablebase for one exchange cost. Only decisive positions are stored, so every position in
+/// range that is missing from the table is a draw.
+pub struct Tablebase {
+    hexes_to_exchange: u8,
+    // Sorted by index
+    entries: Vec<(u64, Wdl)>,
+}
+
+impl Tablebase {
+    /// Solves every position with at most `MAX_PIECES` pieces on at most `MAX_TILES` tiles.
+    ///
+    /// Each position starts out unknown. Positions where the player to move can capture the last
+    /// enemy piece are wins. Then, until nothing changes, a position becomes a win if a move leads
+    /// to a loss for the opponent, and a loss if every move leads to a win for the opponent. The
+    /// positions left over can't be forced either way, so they are draws.
+    pub fn generate(hexes_to_exchange: u8) -> Self {
+        // Keep one position for each index, since the others are its rotations and reflections
+        let mut positions = vec![];
+        let mut indices = vec![];
+        let mut slots: HashMap<u64, u32> = HashMap::new();
+        for board in enumerate_positions(hexes_to_exchange) {
+            if let Entry::Vacant(slot) = slots.entry(index(&board)) {
+                indices.push(*slot.key());
+                slot.insert(positions.len() as u32);
+                positions.push(board);
+            }
+        }
+
+        // The positions each position can move to, stored back to back
+        let mut values = vec![None; positions.len()];
+        let mut child_starts = Vec::with_capacity(positions.len() + 1);
+        let mut children = vec![];
+        for (slot, board) in positions.iter().enumerate() {
+            child_starts.push(children.len());
+            match board.outcome() {
+                Outcome::InProgress => {}
+                _ => {
+                    values[slot] = Some(Wdl::Draw);
+                    continue;
+                }
+            }
+
+            let first_child = children.len();
+            for mv in board.generate_moves() {
+                let mut child = *board;
+                child.apply_move(&mv);
+                if let Outcome::Win(_) = child.outcome() {
+                    values[slot] = Some(Wdl::Win);
+                    break;
+                }
+                children.push(slots[&index(&child)]);
+            }
+            if values[slot].is_some() {
+                children.truncate(first_child);
+            } else if children.len() == first_child {
+                // Pieces that are stuck without an empty field to move to can't lose
+                values[slot] = Some(Wdl::Draw);
+            }
+        }
+        child_starts.push(children.len());
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for slot in 0..positions.len() {
+                if values[slot].is_some() {
+                    continue;
+                }
+                let mut result = Some(Wdl::Loss);
+                for &child in &children[child_starts[slot]..child_starts[slot + 1]] {
+                    match values[child as usize] {
+                        Some(Wdl::Loss) => {
+                            result = Some(Wdl::Win);
+                            break;
+                        }
+                        Some(Wdl::Win) => {}
+                        _ => result = None,
+                    }
+                }
+                if result.is_some() {
+                    values[slot] = result;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut entries: Vec<_> = indices
+            .into_iter()
+            .zip(values)
+            .filter_map(|(index, value)| match value {
+                Some(Wdl::Win) => Some((index, Wdl::Win)),
+                Some(Wdl::Loss) => Some((index, Wdl::Loss)),
+                _ => None,
+            })
+            .collect();
+        entries.sort_by_key(|&(index, _)| index);
+
+        Self {
+            hexes_to_exchange,
+            entries,
+        }
+    }
+    /// Returns the result of a position, or `None` if the position has too many pieces or tiles,
+    /// a different exchange cost from this tablebase, or a ply limit, which the tablebase doesn't
+    /// take into account.
+    pub fn probe(&self, board: &Board) -> Option<Wdl> {
+        let pieces =
+            board.fields(Color::White).count_ones() + board.fields(Color::Black).count_ones();
+        if board.hexes_to_exchange != self.hexes_to_exchange
+            || board.draw_after_plies.is_some()
+            || pieces > MAX_PIECES
+            || tile_count(board.hex_bitboard()) > MAX_TILES
+        {
+            return None;
+        }
+        match board.outcome() {
+            Outcome::Win(color) if color == board.turn => return Some(Wdl::Win),
+            Outcome::Win(_) => return Some(Wdl::Loss),
+            Outcome::InProgress => {}
+            _ => return Some(Wdl::Draw),
+        }
+
+        let index = index(board);
+        Some(
+            match self
+                .entries
+                .binary_search_by_key(&index, |&(index, _)| index)
+            {
+                Ok(i) => self.entries[i].1,
+                Err(_) => Wdl::Draw,
+            },
+        )
+    }
+    pub fn hexes_to_exchange(&self) -> u8 {
+        self.hexes_to_exchange
+    }
+    /// The number of decisive positions in the tablebase
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Encodes the tablebase as a WDL file: a magic number, the exchange cost, and then a 9-byte
+    /// record for each decisive position (its little-endian index, then 1 for a win or 0 for a
+    /// loss), sorted by index.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(5 + self.entries.len() * 9);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(self.hexes_to_exchange);
+        for &(index, value) in &self.entries {
+            bytes.extend_from_slice(&index.to_le_bytes());
+            bytes.push(match value {
+                Wdl::Win => 1,
+                _ => 0,
+            });
+        }
+        bytes
+    }
+    /// Decodes a WDL file written by `to_bytes`, or returns `None` if it is malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 5 || &bytes[..4] != MAGIC {
+            return None;
+        }
+        let hexes_to_exchange = bytes[4];
+        if !EXCHANGE_COSTS.contains(&hexes_to_exchange) {
+            return None;
+        }
+
+        let records = bytes[5..].chunks_exact(9);
+        if !records.remainder().is_empty() {
+            return None;
+        }
+        let mut entries = Vec::with_capacity(records.len());
+        for record in records {
+            let mut index = [0; 8];
+            index.copy_from_slice(&record[..8]);
+            let value = match record[8] {
+                1 => Wdl::Win,
+                0 => Wdl::Loss,
+                _ => return None,
+            };
+            entries.push((u64::from_le_bytes(index), value));
+        }
+        if entries.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return None;
+        }
+
+        Some(Self {
+            hexes_to_exchange,
+            entries,
+        })
+    }
+}
+
+/// Returns the canonical index of a position in range of the tablebase: the smallest index of any
+/// of its images under the board's symmetries.
+fn index(board: &Board) -> u64 {
+    Symmetry::all()
+        .map(|symmetry| translated_index(&board.transformed(symmetry)))
+        .min()
+        .expect("There is always the identity")
+}
+
+/// Returns the index of a position once it is translated to its canonical spot. From the least
+/// significant bit up, the index holds:
+///
+/// * The hexes of the translated board (19 bits)
+/// * The fields of the white and then the black pieces (6 bits each, 24 bits in all), in order
+/// * The number of white and black pieces (2 bits each)
+/// * The captured tiles of white and black (4 bits each), capped at the most that could be used
+/// * Whether white is to move (1 bit)
+fn translated_index(board: &Board) -> u64 {
+    let (hexes, fields) = canonicalize(
+        board.hex_bitboard(),
+        board.fields(Color::White),
+        board.fields(Color::Black),
+    );
+
+    let mut index = 0;
+    for hex in (hexes & HEX_LOW_BITS).iter() {
+        index |= 1 << hex.to_index();
+    }
+
+    let mut shift = 19;
+    for &color in &[Color::White, Color::Black] {
+        for field in fields.get(color).iter() {
+            index |= u64::from(field.trailing_zeros()) << shift;
+            shift += 6;
+        }
+    }
+
+    let counts = capped_hex_counts(board);
+    index |= u64::from(board.pieces(Color::White)) << 43;
+    index |= u64::from(board.pieces(Color::Black)) << 45;
+    index |= u64::from(counts.white) << 47;
+    index |= u64::from(counts.black) << 51;
+    if board.turn == Color::White {
+        index |= 1 << 55;
+    }
+    index
+}
+
+/// Captured tiles beyond what it takes to exchange for every enemy piece are never useful.
+fn capped_hex_counts(board: &Board) -> ColorMap<u8> {
+    let cap = |color: Color| {
+        let most_useful = board.hexes_to_exchange * board.pieces(color.switch());
+        board.hexes(color).min(most_useful)
+    };
+    ColorMap::new(cap(Color::White), cap(Color::Black))
+}
+
+/// Translates the hexes and fields so that the hex bitboard is as small as possible.
+fn canonicalize(
+    hexes: BitBoard,
+    white: BitBoard,
+    black: BitBoard,
+) -> (BitBoard, ColorMap<BitBoard>) {
+    // Translating one bit per hex is enough to compare the hex bitboards
+    let tiles = hexes & HEX_LOW_BITS;
+    let (tiles, dx, dy) = (-4..=4)
+        .flat_map(|dx| (-4..=4).map(move |dy| (dx, dy)))
+        .filter_map(|(dx, dy)| translate(tiles, dx, dy).map(|tiles| (tiles, dx, dy)))
+        .min_by_key(|&(tiles, ..)| tiles)
+        .expect("The board can always stay where it is");
+
+    let shift = |bb| translate(bb, dx, dy).expect("Pieces are always on extant hexes");
+    (tiles * 0b111, ColorMap::new(shift(white), shift(black)))
+}
+
+/// Moves every set bit of a hex or field bitboard by an offset, or returns `None` if a bit would
+/// leave the board.
+fn translate(bb: BitBoard, dx: i8, dy: i8) -> Option<BitBoard> {
+    let mut translated = 0;
+    for bit in bb.iter() {
+        let hex = HexCoord::from_index(bit.to_index() as u8);
+        let new_hex = HexCoord::try_new(hex.x() + dx, hex.y() + dy)?;
+        let slot = bit.trailing_zeros() % 3;
+        translated |= 1 << (new_hex.to_index() as u32 * 3 + slot);
+    }
+    Some(translated)
+}
+
+fn tile_count(hexes: BitBoard) -> u32 {
+    hexes.count_ones() / 3
+}
+
+/// Returns the hex bitboards of every connected set of at most `MAX_TILES` tiles, in their
+/// canonical spot.
+fn tile_sets() -> Vec<BitBoard> {
+    let mut sets = vec![canonicalize(0b111, 0, 0).0];
+    let mut frontier = sets.clone();
+
+    for _ in 1..MAX_TILES {
+        let mut next = HashSet::new();
+        for &set in &frontier {
+            // Grow the set everywhere it fits on the board, so that no shape is missed because
+            // the set is too close to an edge
+            for (dx, dy) in (-4..=4).flat_map(|dx| (-4..=4).map(move |dy| (dx, dy))) {
+                let set = match translate(set, dx, dy) {
+                    Some(set) => set,
+                    None => continue,
+                };
+                for hex in (set & HEX_LOW_BITS).iter() {
+                    let hex = HexCoord::from_index(hex.to_index() as u8);
+                    for &(dx, dy) in &HEX_DIRECTIONS {
+                        if let Some(neighbor) = HexCoord::try_new(hex.x() + dx, hex.y() + dy) {
+                            let grown = set | 0b111 << (neighbor.to_index() * 3);
+                            if grown != set {
+                                next.insert(canonicalize(grown, 0, 0).0);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        frontier = next.into_iter().collect();
+        sets.extend(frontier.iter().cloned());
+    }
+    sets
+}
+
+fn enumerate_positions(hexes_to_exchange: u8) -> Vec<Board> {
+    let mut positions = vec![];
+    for hexes in tile_sets() {
+        for white_count in 1..MAX_PIECES {
+            for black_count in 1..=MAX_PIECES - white_count {
+                // Each player has one field for each of the three bits of a hex
+                for white in combinations(hexes, white_count) {
+                    for black in combinations(hexes, black_count) {
+                        let fields = ColorMap::new(white, black);
+                        let white_cap = hexes_to_exchange * black_count as u8;
+                        let black_cap = hexes_to_exchange * white_count as u8;
+                        for white_hexes in 0..=white_cap {
+                            for black_hexes in 0..=black_cap {
+                                for &turn in &[Color::White, Color::Black] {
+                                    positions.push(Board::from_parts(
+                                        GameType::Laurentius,
+                                        hexes_to_exchange,
+                                        hexes,
+                                        fields,
+                                        ColorMap::new(white_hexes, black_hexes),
+                                        turn,
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    positions
+}
+
+/// Returns every bitboard made of `count` of the set bits of `bb`.
+fn combinations(bb: BitBoard, count: u32) -> Vec<BitBoard> {
+    if count == 0 {
+        return vec![0];
+    }
+    let mut result = vec![];
+    let mut rest = bb;
+    for bit in bb.iter() {
+        rest ^= bit;
+        if rest.count_ones() + 1 < count {
+            break;
+        }
+        result.extend(combinations(rest, count - 1).into_iter().map(|c| c | bit));
+    }
+    result
+}
@@ -0,0 +1,55 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Lets whoever started a search know that it has news, such as a new iteration or a move. The
+/// GUI wakes its event loop with this, so that the AI's threads don't need to know about windows.
+#[derive(Clone)]
+pub struct Waker(Arc<Mutex<Box<dyn Fn() + Send>>>);
+
+impl Waker {
+    pub fn new<F: Fn() + Send + 'static>(wake: F) -> Self {
+        Waker(Arc::new(Mutex::new(Box::new(wake))))
+    }
+    /// A waker for when nobody is waiting, such as in tests and headless tools that poll instead.
+    pub fn none() -> Self {
+        Self::new(|| ())
+    }
+    pub fn wake(&self) {
+        if let Ok(wake) = self.0.lock() {
+            wake();
+        }
+    }
+    /// Wakes after `delay` has passed, without blocking the caller.
+    pub fn wake_after(&self, delay: Duration) {
+        let waker = self.clone();
+        thread::spawn(move || {
+            thread::sleep(delay);
+            waker.wake();
+        });
+    }
+}
+
+impl fmt::Debug for Waker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Waker")
+    }
+}
@@ -0,0 +1,108 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The `key = value` format shared by the app's settings file and evaluation parameter files, and
+//! the directory the game keeps its files in, which tools such as the tablebase generator share
+//! with the app.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::ai::Tablebase;
+
+/// The app's private storage on Android, which needs no permissions to write to. The package name
+/// is the one in `[package.metadata.android]` of the app's manifest.
+const ANDROID_DATA_DIR: &str = "/data/data/io.github.npn.coerceo/files";
+
+/// Splits `key = value` lines into a map. Blank lines and lines starting with `#` are skipped.
+pub fn parse_values(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next()?.trim();
+            let value = parts.next()?.trim();
+            Some((key.to_owned(), value.to_owned()))
+        })
+        .collect()
+}
+
+/// Returns the directory the game stores its files in, or `None` if there is nowhere to store them.
+pub fn dir() -> Option<PathBuf> {
+    if cfg!(target_os = "android") {
+        return Some(PathBuf::from(ANDROID_DATA_DIR));
+    }
+    let dir = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    }?;
+    Some(dir.join("coerceo"))
+}
+
+/// Returns the path of the endgame tablebase for an exchange cost, or `None` if there is nowhere
+/// to store it.
+pub fn tablebase_path(hexes_to_exchange: u8) -> Option<PathBuf> {
+    dir().map(|dir| dir.join(format!("tablebase-{}.wdl", hexes_to_exchange)))
+}
+
+/// Saves a tablebase to `tablebase_path` for its exchange cost, replacing any saved before.
+pub fn save_tablebase(tablebase: &Tablebase) -> io::Result<()> {
+    let path = tablebase_path(tablebase.hexes_to_exchange()).ok_or_else(no_dir_error)?;
+    write_atomic(&path, &tablebase.to_bytes())
+}
+
+/// The error for when there is nowhere to store a file.
+pub fn no_dir_error() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "No directory to store files in")
+}
+
+/// Replaces the file at `path` with `contents`. The data is written to a temporary file in the
+/// same directory, flushed to disk, and then renamed over the old file, so readers see either the
+/// old or the new contents and never a partial write.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dir)?;
+
+    let mut tmp_name = path.file_name().unwrap_or_default().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = dir.join(tmp_name);
+
+    let result = File::create(&tmp_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    if let Err(e) = result.and_then(|_| fs::rename(&tmp_path, path)) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    // Make the rename itself durable. Directories can't be opened as files on Windows.
+    if cfg!(unix) {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,146 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Messages from the app and the computer player, kept so they can be read in the log window.
+//! GUI builds have no terminal to print them to, and on Android there is no way to see one.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The most messages kept. The oldest are dropped to make room for new ones.
+const CAPACITY: usize = 1000;
+
+/// How much a message matters, most important first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warning,
+    Info,
+    /// Details of each of the computer's searches
+    Debug,
+}
+
+impl Default for Level {
+    /// Shows every message.
+    fn default() -> Self {
+        Level::Debug
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub level: Level,
+    /// How long after the app started the message was logged
+    pub time: Duration,
+    pub message: String,
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let millis = self.time.as_millis();
+        write!(
+            f,
+            "[{}.{:03}] {}: {}",
+            millis / 1000,
+            millis % 1000,
+            self.level,
+            self.message
+        )
+    }
+}
+
+/// A list of log messages. Clones share the same messages, so the computer player's threads can
+/// log to the same place as the rest of the app.
+#[derive(Clone)]
+pub struct Log {
+    start: Instant,
+    entries: Arc<Mutex<VecDeque<Entry>>>,
+}
+
+impl Log {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            entries: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+    /// Adds a message. Errors and warnings are also printed to stderr, as they were before there
+    /// was a log window.
+    pub fn add<S: Into<String>>(&self, level: Level, message: S) {
+        let entry = Entry {
+            level,
+            time: self.start.elapsed(),
+            message: message.into(),
+        };
+        if level <= Level::Warning {
+            eprintln!("{}", entry.message);
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() == CAPACITY {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+    }
+    pub fn error<S: Into<String>>(&self, message: S) {
+        self.add(Level::Error, message);
+    }
+    pub fn warn<S: Into<String>>(&self, message: S) {
+        self.add(Level::Warning, message);
+    }
+    pub fn info<S: Into<String>>(&self, message: S) {
+        self.add(Level::Info, message);
+    }
+    pub fn debug<S: Into<String>>(&self, message: S) {
+        self.add(Level::Debug, message);
+    }
+    /// Returns the messages at `level` or more important, oldest first.
+    pub fn entries(&self, level: Level) -> Vec<Entry> {
+        match self.entries.lock() {
+            Ok(entries) => entries
+                .iter()
+                .filter(|entry| entry.level <= level)
+                .cloned()
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Self::new()
+    }
+}
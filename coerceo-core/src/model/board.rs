@@ -0,0 +1,1179 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::error::Error;
+use std::fmt;
+
+use crate::model::bitboard::*;
+use crate::model::constants::*;
+use crate::model::rng::Rng;
+use crate::model::zobrist::{self, ZobristExt, ZobristHash};
+use crate::model::{
+    Color, ColorMap, FieldCoord, GameType, HexCoord, Move, MoveAnnotated, Outcome, Symmetry,
+    EXCHANGE_COSTS,
+};
+
+#[derive(Clone, Copy)]
+pub struct Board {
+    /*
+    Board layout:
+    The hex board uses an axial coordinate system with (0, 0) at the center. The x-axis slopes
+    up and to the right, and the y-axis goes up and down.
+    See http://www.redblobgames.com/grids/hexagons/#coordinates-axial for more info.
+                                             ____
+                                            /    \
+                                       ____/  +y  \____
+                                      /    \      /    \
+                                     /      \____/  +x  \
+                                     \      /    \      /
+                                      \____/      \____/
+                                      /    \      /    \
+                                     /  -x  \____/      \
+                                     \      /    \      /
+                                      \____/  -y  \____/
+                                           \      /
+                                            \____/
+
+    Field layout:
+    Fields are numbered clockwise from the top. Even indicies are black, odd indicies are white.
+                                          _________
+                                         / \     / \
+                                        /   \ 0 /   \
+                                       /  5  \ /  1  \
+                                      (-------*-------)
+                                       \  4  / \  2  /
+                                        \   / 3 \   /
+                                         \_/_____\_/
+    u64 Bitboard layout:
+
+     MSB                                                                              LSB
+
+     7 bits                            57 bits (19 groups of 3)
+     +-----+  +-------------------------------------------------------------------------+
+    [0000000][000 111 000 000 000 000 000 000 000 000 000 000 000 000 000 000 000 010 000]
+     Unused   -+- -+-                 ||+-- Field 0           ||+-- Field 1       |+-- Piece on field
+               |   +-- Extant hex     |+--- Field 2           |+--- Field 3       +--- No piece on field
+               +------ Removed hex    +---- Field 4           +---- Field 5            (Field bitboards)
+                      (Hex bitboard)  (Black field bitboard)  (White field bitboard)
+    */
+    fields: ColorMap<BitBoard>,
+    hexes: BitBoard,
+    pub turn: Color,
+    pub vitals: ColorMap<PlayerVitals>,
+    pub zobrist: ZobristHash,
+    pub hexes_to_exchange: u8,
+    /// If set, the game is drawn once this many plies in a row have been played without a
+    /// capture, an exchange or a tile being removed
+    pub draw_after_plies: Option<u16>,
+    /// The number of plies played since the last capture, exchange or tile removal
+    pub reversible_plies: u16,
+    game_type: GameType,
+}
+
+// Boards are the same position whatever their reversible ply counts, so that repetitions are
+// detected by comparing them
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.fields == other.fields
+            && self.hexes == other.hexes
+            && self.turn == other.turn
+            && self.vitals == other.vitals
+            && self.zobrist == other.zobrist
+            && self.hexes_to_exchange == other.hexes_to_exchange
+            && self.draw_after_plies == other.draw_after_plies
+            && self.game_type == other.game_type
+    }
+}
+
+/// Draws the board as text, two lines per hex, with the players' vitals and the player to move
+/// underneath. Each hex shows its fields in the same places as the field layout above, and removed
+/// hexes are left blank. The starting position of Ocius looks like this:
+///
+/// ```text
+///         /...\
+///     /..W\B.B/W..\
+///     \.W./.B.\.W./
+///     /.B.\.W./.B.\
+///     \..B/W.W\B../
+///         \.../
+///   a   b   c   d   e
+/// White: 7 pieces, 0 tiles
+/// Black: 7 pieces, 0 tiles
+/// White to move
+/// ```
+///
+/// The alternate form (`{:#}`) draws the pieces with Unicode circles instead of letters.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Hexes in neighboring columns share the characters of the sides between them
+        const COLUMN_WIDTH: usize = 4;
+        let (white, black, empty) = if f.alternate() {
+            ('\u{25cb}', '\u{25cf}', '\u{b7}')
+        } else {
+            ('W', 'B', '.')
+        };
+
+        // Each column is offset from the ones next to it by half a hex, that is, one line
+        let mut lines = vec![vec![' '; COLUMN_WIDTH * 5 + 1]; 10];
+        for hex in self.hexes_iter() {
+            let column = (hex.x() + 2) as usize * COLUMN_WIDTH;
+            let line = (4 - 2 * hex.y() - hex.x()) as usize;
+
+            let field = |f| {
+                let coord = hex.to_field(f);
+                match (self.is_piece_on_field(coord), coord.color()) {
+                    (true, Color::White) => white,
+                    (true, Color::Black) => black,
+                    (false, _) => empty,
+                }
+            };
+            let top = ['/', field(5), field(0), field(1), '\\'];
+            let bottom = ['\\', field(4), field(3), field(2), '/'];
+            lines[line][column..column + 5].copy_from_slice(&top);
+            lines[line + 1][column..column + 5].copy_from_slice(&bottom);
+        }
+
+        let lines: Vec<String> = lines
+            .iter()
+            .map(|line| line.iter().collect::<String>().trim_end().to_string())
+            .collect();
+        // Leave out the lines above and below the hexes that are left
+        let first = lines.iter().position(|line| !line.is_empty()).unwrap_or(0);
+        let last = lines.iter().rposition(|line| !line.is_empty()).unwrap_or(0);
+        for line in &lines[first..=last] {
+            writeln!(f, "{}", line)?;
+        }
+        writeln!(f, "  a   b   c   d   e")?;
+        let plural = |count: u8, noun: &str| match count {
+            1 => format!("1 {}", noun),
+            _ => format!("{} {}s", count, noun),
+        };
+        for &color in &[Color::White, Color::Black] {
+            writeln!(
+                f,
+                "{:?}: {}, {}",
+                color,
+                plural(self.pieces(color), "piece"),
+                plural(self.hexes(color), "tile"),
+            )?;
+        }
+        write!(f, "{:?} to move", self.turn)
+    }
+}
+
+/// What a move changed, returned by `Board::make_move` so that the move can be taken back.
+#[derive(Clone, Copy)]
+pub struct UndoInfo {
+    mv: Move,
+    // The opponent's pieces the move captured, including an exchanged piece
+    captured: BitBoard,
+    removed_hexes: BitBoard,
+    vitals: ColorMap<PlayerVitals>,
+    zobrist: ZobristHash,
+    reversible_plies: u16,
+}
+
+impl UndoInfo {
+    /// Returns whether the move left both players' vitals alone, that is, it didn't capture any
+    /// pieces or tiles and wasn't an exchange.
+    pub fn is_quiet(&self) -> bool {
+        match self.mv {
+            Move::Move(..) => self.captured == 0 && self.removed_hexes == 0,
+            Move::Exchange(..) => false,
+        }
+    }
+}
+
+/// Why `Board::from_setup` rejected a position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SetupError {
+    /// The exchange cost isn't one of `EXCHANGE_COSTS`
+    InvalidExchangeCost(u8),
+    /// The hex bitboard sets bits past the last hex or only some of a hex's three bits
+    MalformedHexes,
+    PieceOnRemovedHex(FieldCoord),
+    /// A player has more pieces than they start the game with
+    TooManyPieces(Color),
+    /// A player's piece count doesn't match the pieces on the board
+    PieceCountMismatch(Color),
+    /// The players hold more tiles than have been removed from the board
+    TooManyTiles,
+    /// An empty hex is attached by three or fewer sides, so it should have been removed
+    RemovableHex(HexCoord),
+    /// The hexes don't all join up. Only hexes attached by one to three neighboring sides are ever
+    /// removed, so a board in play never comes apart.
+    DisconnectedHexes,
+}
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SetupError::InvalidExchangeCost(cost) => {
+                write!(f, "Cannot exchange {} tiles for a piece", cost)
+            }
+            SetupError::MalformedHexes => write!(f, "The hex bitboard is malformed"),
+            SetupError::PieceOnRemovedHex(coord) => {
+                write!(f, "There is a piece on removed hex {}", coord.to_notation())
+            }
+            SetupError::TooManyPieces(color) => write!(f, "{:?} has too many pieces", color),
+            SetupError::PieceCountMismatch(color) => write!(
+                f,
+                "{:?}'s piece count doesn't match the pieces on the board",
+                color
+            ),
+            SetupError::TooManyTiles => write!(
+                f,
+                "The players hold more tiles than have been removed from the board"
+            ),
+            SetupError::RemovableHex(coord) => write!(
+                f,
+                "Hex {} is empty and should have been removed",
+                coord.to_notation()
+            ),
+            SetupError::DisconnectedHexes => write!(f, "The hexes are not all joined together"),
+        }
+    }
+}
+
+impl Error for SetupError {}
+
+/// Why `Board::check_move` rejected a move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IllegalMove {
+    /// The piece to move belongs to the player who isn't moving
+    NotYourTurn,
+    /// A player can only exchange for their opponent's pieces
+    OwnPiece,
+    /// The move uses a field on a hex that has been removed
+    RemovedHex(FieldCoord),
+    /// There is no piece to move or exchange for on this field
+    NoPiece(FieldCoord),
+    /// Pieces can only move to a field that shares a vertex with the one they're on
+    NotAVertexNeighbor,
+    DestinationOccupied,
+    /// The player doesn't hold enough tiles to exchange
+    CannotExchange,
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IllegalMove::NotYourTurn => write!(f, "It isn't that piece's turn to move"),
+            IllegalMove::OwnPiece => write!(f, "You can only exchange for an opponent's piece"),
+            IllegalMove::RemovedHex(coord) => {
+                write!(f, "The hex of {} has been removed", coord.to_notation())
+            }
+            IllegalMove::NoPiece(coord) => {
+                write!(f, "There is no piece on {}", coord.to_notation())
+            }
+            IllegalMove::NotAVertexNeighbor => {
+                write!(f, "Pieces can only move to a field that shares a corner")
+            }
+            IllegalMove::DestinationOccupied => write!(f, "That field is already occupied"),
+            IllegalMove::CannotExchange => write!(f, "You don't have enough tiles to exchange"),
+        }
+    }
+}
+
+impl Error for IllegalMove {}
+
+/// A struct tracking a player's piece and captured hex count. So named because these two numbers are
+/// essential to a player's survival (i.e. vital signs).
+#[derive(Clone, Copy, PartialEq)]
+pub struct PlayerVitals {
+    pub pieces: u8,
+    pub hexes: u8,
+}
+
+// Public methods
+impl Board {
+    /// Create a new board with the starting position of a game type.
+    pub fn new(game_type: GameType, hexes_to_exchange: u8) -> Self {
+        assert!(EXCHANGE_COSTS.contains(&hexes_to_exchange));
+
+        let starting_position = match game_type {
+            GameType::Laurentius => LAURENTIUS,
+            GameType::Ocius => OCIUS,
+            GameType::Random(seed) => random_starting_position(seed),
+        };
+
+        Self {
+            fields: starting_position.fields,
+            hexes: starting_position.hexes,
+            turn: Color::White,
+            vitals: starting_position.vitals,
+            zobrist: zobrist::new(
+                starting_position.fields,
+                starting_position.hexes,
+                ColorMap::new(0, 0),
+                Color::White,
+                game_type,
+                hexes_to_exchange,
+            ),
+            hexes_to_exchange,
+            draw_after_plies: None,
+            reversible_plies: 0,
+            game_type,
+        }
+    }
+    /// Returns the board with up to `pieces` of `color`'s pieces taken off, so that a stronger
+    /// player can give a weaker one a head start. Pieces are taken in index order, skipping any
+    /// that would leave their hex empty, so a handicap always gives the same start.
+    pub fn with_handicap(&self, color: Color, pieces: u8) -> Self {
+        let mut fields = self.fields;
+        let mut removed = 0;
+        for bb in self.fields.get(color).iter() {
+            if removed == pieces {
+                break;
+            }
+            let hex = HEX_MASK[bb.to_index()];
+            if (fields.white | fields.black) & hex & !bb != 0 {
+                *fields.get_mut(color) &= !bb;
+                removed += 1;
+            }
+        }
+        Self::from_parts(
+            self.game_type,
+            self.hexes_to_exchange,
+            self.hexes,
+            fields,
+            ColorMap::new(self.hexes(Color::White), self.hexes(Color::Black)),
+            self.turn,
+        )
+    }
+    /// Create a board from its parts. Every piece must be on an extant hex.
+    pub(crate) fn from_parts(
+        game_type: GameType,
+        hexes_to_exchange: u8,
+        hexes: BitBoard,
+        fields: ColorMap<BitBoard>,
+        hex_counts: ColorMap<u8>,
+        turn: Color,
+    ) -> Self {
+        assert!(EXCHANGE_COSTS.contains(&hexes_to_exchange));
+        assert_eq!((fields.white | fields.black) & !hexes, 0);
+
+        let vitals = |color| PlayerVitals {
+            pieces: fields.get(color).count_ones() as u8,
+            hexes: hex_counts.get(color),
+        };
+        Self {
+            fields,
+            hexes,
+            turn,
+            vitals: ColorMap::new(vitals(Color::White), vitals(Color::Black)),
+            zobrist: zobrist::new(
+                fields,
+                hexes,
+                hex_counts,
+                turn,
+                game_type,
+                hexes_to_exchange,
+            ),
+            hexes_to_exchange,
+            draw_after_plies: None,
+            reversible_plies: 0,
+            game_type,
+        }
+    }
+    /// Create a board from an arbitrary position, such as one from the editor or a test. Unlike
+    /// `from_parts`, this checks that the position could come up in a game: every piece is on an
+    /// extant hex, the piece counts match the board, the players hold no more tiles than have been
+    /// removed, and no empty hex is left that should have been removed.
+    pub fn from_setup(
+        fields: ColorMap<BitBoard>,
+        hexes: BitBoard,
+        vitals: ColorMap<PlayerVitals>,
+        turn: Color,
+        game_type: GameType,
+        hexes_to_exchange: u8,
+    ) -> Result<Self, SetupError> {
+        if !EXCHANGE_COSTS.contains(&hexes_to_exchange) {
+            return Err(SetupError::InvalidExchangeCost(hexes_to_exchange));
+        }
+        if hexes != (hexes & HEX_COORD_MASK) * 0b111 {
+            return Err(SetupError::MalformedHexes);
+        }
+
+        // Random starts have the same tiles and piece counts as Laurentius
+        let starting_position = match game_type {
+            GameType::Laurentius | GameType::Random(_) => LAURENTIUS,
+            GameType::Ocius => OCIUS,
+        };
+        for &color in &[Color::White, Color::Black] {
+            let color_fields = fields.get(color);
+            if let Some(bb) = (color_fields & !hexes).iter().next() {
+                return Err(SetupError::PieceOnRemovedHex(FieldCoord::from_bitboard(
+                    bb, color,
+                )));
+            }
+            let pieces = vitals.get(color).pieces;
+            if pieces > starting_position.vitals.get(color).pieces {
+                return Err(SetupError::TooManyPieces(color));
+            }
+            if u32::from(pieces) != color_fields.count_ones() {
+                return Err(SetupError::PieceCountMismatch(color));
+            }
+        }
+
+        let removed_hexes = (starting_position.hexes & !hexes).count_ones() / 3;
+        if u32::from(vitals.white.hexes) + u32::from(vitals.black.hexes) > removed_hexes {
+            return Err(SetupError::TooManyTiles);
+        }
+
+        let board = Self {
+            fields,
+            hexes,
+            turn,
+            vitals,
+            zobrist: zobrist::new(
+                fields,
+                hexes,
+                ColorMap::new(vitals.white.hexes, vitals.black.hexes),
+                turn,
+                game_type,
+                hexes_to_exchange,
+            ),
+            hexes_to_exchange,
+            draw_after_plies: None,
+            reversible_plies: 0,
+            game_type,
+        };
+        if let Some(index) =
+            (0..19).find(|&index| board.is_hex_extant(index) && board.is_hex_removable(index))
+        {
+            return Err(SetupError::RemovableHex(HexCoord::from_index(index as u8)));
+        }
+        if !is_connected(hexes) {
+            return Err(SetupError::DisconnectedHexes);
+        }
+        Ok(board)
+    }
+    pub fn apply_move(&mut self, mv: &Move) {
+        assert!(self.can_apply_move(mv), "Cannot apply {:?}", mv);
+        self.apply_move_unchecked(mv);
+    }
+    /// Applies a legal move in place and returns what is needed to take it back with
+    /// `unmake_move`. This is faster than copying the board for each move, and skips the legality
+    /// check in release builds, so it is meant for searches over generated moves.
+    pub fn make_move(&mut self, mv: &Move) -> UndoInfo {
+        debug_assert!(self.can_apply_move(mv), "Cannot apply {:?}", mv);
+        let opp_color = self.turn.switch();
+        let old_opp_fields = self.fields.get(opp_color);
+        let old_hexes = self.hexes;
+        let vitals = self.vitals;
+        let zobrist = self.zobrist;
+        let reversible_plies = self.reversible_plies;
+
+        self.apply_move_unchecked(mv);
+
+        UndoInfo {
+            mv: *mv,
+            captured: old_opp_fields ^ self.fields.get(opp_color),
+            removed_hexes: old_hexes ^ self.hexes,
+            vitals,
+            zobrist,
+            reversible_plies,
+        }
+    }
+    /// Takes back the last move made with `make_move`.
+    pub fn unmake_move(&mut self, undo: UndoInfo) {
+        self.turn = self.turn.switch();
+        let opp_color = self.turn.switch();
+        *self.fields.get_mut(opp_color) |= undo.captured;
+        self.hexes |= undo.removed_hexes;
+        if let Move::Move(from, to, color) = undo.mv {
+            self.toggle_field(from | to, color);
+        }
+        self.vitals = undo.vitals;
+        self.zobrist = undo.zobrist;
+        self.reversible_plies = undo.reversible_plies;
+    }
+    /// Passes the turn to the opponent without moving. This isn't a legal move in Coerceo, but
+    /// searches use it to ask how good a position is if the opponent could move twice.
+    pub fn make_null_move(&mut self) {
+        self.turn = self.turn.switch();
+        self.zobrist.switch_turn();
+    }
+    /// Takes back a move made with `make_null_move`.
+    pub fn unmake_null_move(&mut self) {
+        self.make_null_move();
+    }
+    fn apply_move_unchecked(&mut self, mv: &Move) {
+        let vitals = self.vitals;
+        match *mv {
+            Move::Move(from, to, color) => {
+                self.toggle_field(from | to, color);
+
+                self.zobrist.toggle_field(from, color);
+                self.zobrist.toggle_field(to, color);
+
+                let (capture_count, mut fields_to_check) = self.check_hexes(from.to_index());
+                fields_to_check |= EDGE_NEIGHBORS.bb_get(to, color);
+                self.check_captures(fields_to_check);
+
+                if capture_count != 0 {
+                    let vitals = self.vitals.get_mut(self.turn);
+                    self.zobrist
+                        .set_hex_count(vitals.hexes, vitals.hexes + capture_count, color);
+                    vitals.hexes += capture_count;
+                }
+            }
+            Move::Exchange(bb, color) => {
+                self.remove_piece(bb, color);
+
+                let vitals = self.vitals.get_mut(self.turn);
+                self.zobrist.set_hex_count(
+                    vitals.hexes,
+                    vitals.hexes - self.hexes_to_exchange,
+                    self.turn,
+                );
+                vitals.hexes -= self.hexes_to_exchange;
+
+                // Players don't collect hexes removed due to an exchange
+                let (_, fields_to_check) = self.check_hexes(bb.to_index());
+                self.check_captures(fields_to_check);
+            }
+        }
+        // Captures, exchanges and tile removals all change the players' vitals
+        self.reversible_plies = if self.vitals == vitals {
+            self.reversible_plies.saturating_add(1)
+        } else {
+            0
+        };
+        self.turn = self.turn.switch();
+        self.zobrist.switch_turn();
+    }
+    /// Applies a `Move` and returns it as a `MoveAnnotated`, that is, holding `Vec`s of the pieces
+    /// and hexes removed by playing the move and the number of tiles the mover collected.
+    pub fn annotated_apply_move(&mut self, mv: &Move) -> MoveAnnotated {
+        let opp_color = self.turn.switch();
+        let old_opp_fields = self.fields.get(opp_color);
+        let old_hexes = self.hexes;
+        let mover = self.turn;
+        let old_tiles = self.vitals.get(mover).hexes;
+
+        self.apply_move(mv);
+
+        let captured_pieces = (old_opp_fields ^ self.fields.get(opp_color))
+            .iter()
+            .map(|bb| FieldCoord::from_bitboard(bb, opp_color))
+            .collect();
+
+        let removed_hexes = (HEX_COORD_MASK & (old_hexes ^ self.hexes))
+            .iter()
+            .map(|bb| HexCoord::from_index(bb.trailing_zeros() as u8 / 3))
+            .collect();
+
+        // An exchange spends tiles, and the mover collects none of the tiles it removes
+        let tiles_gained = self.vitals.get(mover).hexes.saturating_sub(old_tiles);
+
+        mv.annotate(captured_pieces, removed_hexes, tiles_gained)
+    }
+    pub fn can_apply_move(&self, mv: &Move) -> bool {
+        self.check_move(mv).is_ok()
+    }
+    /// Returns why a move can't be played in this position, if it can't.
+    pub fn check_move(&self, mv: &Move) -> Result<(), IllegalMove> {
+        let check_field = |bb: BitBoard, color| {
+            if bb & self.hexes == 0 {
+                Err(IllegalMove::RemovedHex(FieldCoord::from_bitboard(
+                    bb, color,
+                )))
+            } else {
+                Ok(bb & self.fields.get(color) != 0)
+            }
+        };
+        match *mv {
+            Move::Move(from, to, color) => {
+                if color != self.turn {
+                    return Err(IllegalMove::NotYourTurn);
+                }
+                if !check_field(from, color)? {
+                    return Err(IllegalMove::NoPiece(FieldCoord::from_bitboard(from, color)));
+                }
+                if to & VERTEX_NEIGHBORS.bb_get(from, color) == 0 {
+                    return Err(IllegalMove::NotAVertexNeighbor);
+                }
+                if check_field(to, color)? {
+                    return Err(IllegalMove::DestinationOccupied);
+                }
+            }
+            Move::Exchange(bb, color) => {
+                if color == self.turn {
+                    return Err(IllegalMove::OwnPiece);
+                }
+                if !self.can_exchange() {
+                    return Err(IllegalMove::CannotExchange);
+                }
+                if !check_field(bb, color)? {
+                    return Err(IllegalMove::NoPiece(FieldCoord::from_bitboard(bb, color)));
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn generate_moves(&self) -> impl Iterator<Item = Move> {
+        assert_ne!(self.fields.get(self.turn), 0);
+
+        self.generate_moves_for(self.turn)
+    }
+    /// Generates the moves `turn` could play if it were their turn to move, which may not be the
+    /// case. Useful for reasoning about the player who isn't moving, such as their mobility.
+    pub fn generate_moves_for(&self, turn: Color) -> impl Iterator<Item = Move> {
+        let fields = self.fields.get(turn);
+        let hexes = self.hexes;
+        let opp_color = turn.switch();
+        let opp_fields = if self.vitals.get(turn).hexes >= self.hexes_to_exchange {
+            self.fields.get(opp_color)
+        } else {
+            // impl Trait requires that we return a single, concrete type. So, if there are no
+            // fields to exchange, we create an empty BitBoardIter and chain it on anyways. This
+            // way, the type of the resulting iterator is always Chain.
+            0
+        };
+
+        fields
+            .iter()
+            .flat_map(move |origin| {
+                let empty_vertex_neighbors =
+                    VERTEX_NEIGHBORS.bb_get(origin, turn) & (!fields & hexes);
+                empty_vertex_neighbors
+                    .iter()
+                    .map(move |dest| Move::Move(origin, dest, turn))
+            })
+            .chain(
+                opp_fields
+                    .iter()
+                    .map(move |exchanged| Move::Exchange(exchanged, opp_color)),
+            )
+    }
+    /// Counts the positions reachable in exactly `depth` plies, for checking move generation
+    /// against known results.
+    pub fn perft(&self, depth: u8) -> u64 {
+        let mut board = *self;
+        board.perft_in_place(depth)
+    }
+    /// Returns each move along with the number of positions reachable in `depth` plies that start
+    /// with it. When a perft count is off, comparing the counts for each move narrows down where
+    /// move generation went wrong.
+    pub fn perft_divide(&self, depth: u8) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return vec![];
+        }
+        let mut board = *self;
+        self.generate_moves()
+            .map(|mv| {
+                let undo = board.make_move(&mv);
+                let count = board.perft_in_place(depth - 1);
+                board.unmake_move(undo);
+                (mv, count)
+            })
+            .collect()
+    }
+    /// Returns how many moves `generate_moves` would generate, without generating them.
+    pub fn count_moves(&self) -> u32 {
+        let turn = self.turn;
+        let fields = self.fields.get(turn);
+        let empty_fields = !fields & self.hexes;
+
+        let moves: u32 = fields
+            .iter()
+            .map(|origin| (VERTEX_NEIGHBORS.bb_get(origin, turn) & empty_fields).count_ones())
+            .sum();
+        if self.can_exchange() {
+            moves + self.fields.get(turn.switch()).count_ones()
+        } else {
+            moves
+        }
+    }
+    pub fn generate_captures(&self) -> impl Iterator<Item = Move> {
+        let hexes = self.hexes;
+        let can_exchange = self.can_exchange();
+
+        let our_color = self.turn;
+        let our_fields = self.fields.get(our_color);
+
+        let opp_color = self.turn.switch();
+        let opp_fields = self.fields.get(opp_color);
+
+        // A bitboard of pieces that can be moved to capture a hex.
+        let mut hex_capture_pieces = 0;
+
+        // By exchanging these pieces, we collapse their hex, which also captures more opponent
+        // pieces. This includes "hex capture chains" where removing the first hex doesn't capture
+        // a piece, but causes another hex to be removed, which does. Only hexes holding a lone
+        // opponent piece can collapse this way, and there are few of them, so it's cheap enough to
+        // play each exchange and count the pieces.
+        let mut exchange_captures = 0;
+
+        for (i, hex) in HEX_MASK.iter().enumerate() {
+            if self.is_hex_extant(i) && self.is_hex_maybe_removable(i) {
+                let opp_piece = opp_fields & hex;
+                let our_piece = our_fields & hex;
+
+                if can_exchange && our_piece == 0 && opp_piece.is_one_bit_set() {
+                    let mut after = *self;
+                    after.apply_move(&Move::Exchange(opp_piece, opp_color));
+                    if after.pieces(opp_color) + 1 < self.pieces(opp_color) {
+                        exchange_captures |= opp_piece;
+                    }
+                }
+
+                if opp_piece == 0 && our_piece.is_one_bit_set() {
+                    hex_capture_pieces |= our_piece;
+                }
+            }
+        }
+
+        exchange_captures
+            .iter()
+            .map(move |opp_piece| Move::Exchange(opp_piece, opp_color))
+            .chain(opp_fields.iter().flat_map(move |opp_piece| {
+                let edge_neighbors = EDGE_NEIGHBORS.bb_get(opp_piece, opp_color) & hexes;
+                let empty_neighbor = edge_neighbors & !our_fields;
+
+                let origins = if empty_neighbor.is_one_bit_set() {
+                    VERTEX_NEIGHBORS.bb_get(empty_neighbor, our_color)
+                        & our_fields
+                        & !edge_neighbors
+                } else {
+                    // Again, we need to return a single, concrete iterator type
+                    0
+                };
+
+                origins
+                    .iter()
+                    .map(move |our_piece| Move::Move(our_piece, empty_neighbor, our_color))
+            }))
+            .chain(hex_capture_pieces.iter().flat_map(move |origin| {
+                let hex = HEX_MASK[origin.to_index()];
+                let vertex_neighbors =
+                    VERTEX_NEIGHBORS.bb_get(origin, our_color) & (!hex & !our_fields & hexes);
+                vertex_neighbors
+                    .iter()
+                    .map(move |dest| Move::Move(origin, dest, our_color))
+            }))
+    }
+    pub fn available_moves_for_piece(&self, field: FieldCoord) -> Vec<FieldCoord> {
+        if self.is_piece_on_field(field) {
+            let color = field.color();
+            let vertex_neighbors = self.hexes & VERTEX_NEIGHBORS.bb_get(field.to_bitboard(), color);
+            let mut moves = Vec::with_capacity(3);
+
+            for dest in vertex_neighbors.iter() {
+                moves.push(FieldCoord::from_bitboard(dest, color));
+            }
+            moves
+        } else {
+            vec![]
+        }
+    }
+    pub fn can_exchange(&self) -> bool {
+        self.vitals.get(self.turn).hexes >= self.hexes_to_exchange
+    }
+    pub fn is_piece_on_field(&self, coord: FieldCoord) -> bool {
+        self.is_piece_on_bitboard(coord.to_bitboard(), coord.color())
+    }
+    pub fn is_piece_on_bitboard(&self, bb: BitBoard, color: Color) -> bool {
+        assert!(
+            bb & self.hexes != 0,
+            "Cannot cannot check if piece is on {:?}. Hex was removed.",
+            FieldCoord::from_bitboard(bb, color),
+        );
+
+        bb & self.fields.get(color) != 0
+    }
+    /// Iterates over the coordinates of the extant hexes, that is, the hexes that have not been
+    /// removed yet, in index order.
+    pub fn hexes_iter(&self) -> impl Iterator<Item = HexCoord> {
+        (self.hexes & HEX_COORD_MASK)
+            .iter()
+            .map(|bb| HexCoord::from_index(bb.to_index() as u8))
+    }
+    /// Iterates over the fields holding `color`'s pieces.
+    pub fn pieces_iter(&self, color: Color) -> impl Iterator<Item = FieldCoord> {
+        self.fields
+            .get(color)
+            .iter()
+            .map(move |bb| FieldCoord::from_bitboard(bb, color))
+    }
+    /// > extant (adj.): Still in existence; not destroyed, lost, or extinct (The Free Dictionary)
+    ///
+    /// Returns true if a hex has not been removed yet.
+    pub fn is_hex_extant(&self, index: usize) -> bool {
+        self.hexes & HEX_MASK[index] != 0
+    }
+    /// Returns how many more neighboring hexes must be removed before this hex is removable once it
+    /// is empty: `Some(0)` if it already is, `Some(1)` if losing one more neighbor could make it
+    /// removable, and `None` otherwise.
+    pub fn hex_fragility(&self, index: usize) -> Option<u8> {
+        debug_assert!(self.is_hex_extant(index));
+
+        let neighbors = self.hexes
+            & (HEX_FIELD_NEIGHBORS.index_get(index, Color::White)
+                | HEX_FIELD_NEIGHBORS.index_get(index, Color::Black));
+        let combs = &REMOVABLE_HEX_COMBS[index * 18..index * 18 + 18];
+
+        if combs.contains(&neighbors) {
+            Some(0)
+        } else if neighbors.count_ones() <= 4
+            && neighbors
+                .iter()
+                .any(|hex| combs.contains(&(neighbors ^ hex)))
+        {
+            Some(1)
+        } else {
+            None
+        }
+    }
+    /// Returns a bitboard of `color`'s pieces that the other player could capture by surrounding
+    /// them with their next move. Captures by exchanging, or by removing a tile next to a piece,
+    /// are not counted.
+    pub fn pieces_en_prise(&self, color: Color) -> BitBoard {
+        let their_color = color.switch();
+        let their_fields = self.fields.get(their_color);
+
+        self.fields
+            .get(color)
+            .iter()
+            .filter(|&piece| {
+                let edge_neighbors = EDGE_NEIGHBORS.bb_get(piece, color) & self.hexes;
+                let empty_neighbor = edge_neighbors & !their_fields;
+
+                empty_neighbor.is_one_bit_set()
+                    && VERTEX_NEIGHBORS.bb_get(empty_neighbor, their_color)
+                        & their_fields
+                        & !edge_neighbors
+                        != 0
+            })
+            .fold(0, |en_prise, piece| en_prise | piece)
+    }
+    /// Returns a bitboard of `color`'s pieces that the other player could capture with their next
+    /// move, whether by surrounding them, by removing a tile next to them or as the knock-on
+    /// effect of an exchange. Unlike `pieces_en_prise`, this tries every move, so it is too slow
+    /// for the search. The piece taken by an exchange itself isn't counted, since any piece can
+    /// be taken that way once the other player has enough tiles.
+    pub fn threatened_pieces(&self, color: Color) -> BitBoard {
+        let mut board = *self;
+        if board.turn == color {
+            board.make_null_move();
+        }
+        if board.fields.get(board.turn) == 0 {
+            return 0;
+        }
+
+        let pieces = board.fields.get(color);
+        let moves: Vec<_> = board.generate_moves().collect();
+        moves.iter().fold(0, |threatened, mv| {
+            let undo = board.make_move(mv);
+            let mut lost = pieces & !board.fields.get(color);
+            board.unmake_move(undo);
+            if let Move::Exchange(exchanged, _) = *mv {
+                lost &= !exchanged;
+            }
+            threatened | lost
+        })
+    }
+    /// Returns a bitboard with all three bits set for each hex that will be removed as soon as the
+    /// pieces on it leave, that is, each hex with a fragility of 0.
+    pub fn tiles_about_to_fall(&self) -> BitBoard {
+        self.hexes_iter()
+            .map(|hex| hex.to_index())
+            .filter(|&index| self.hex_fragility(index) == Some(0))
+            .fold(0, |tiles, index| tiles | HEX_MASK[index])
+    }
+    /// Returns a bitboard with all three bits set for each extant hex.
+    pub fn hex_bitboard(&self) -> BitBoard {
+        self.hexes
+    }
+    pub fn game_type(&self) -> GameType {
+        self.game_type
+    }
+    /// Returns a bitboard of the fields occupied by a player's pieces.
+    pub fn fields(&self, color: Color) -> BitBoard {
+        self.fields.get(color)
+    }
+    pub fn pieces(&self, color: Color) -> u8 {
+        self.vitals.get(color).pieces
+    }
+    pub fn hexes(&self, color: Color) -> u8 {
+        self.vitals.get(color).hexes
+    }
+    /// Returns the image of this position under a symmetry. If the symmetry swaps colors, the
+    /// players swap pieces, captured tiles and turns as well.
+    pub fn transformed(&self, symmetry: Symmetry) -> Self {
+        let mut fields = ColorMap::new(0, 0);
+        let mut vitals = self.vitals;
+        for &color in &[Color::White, Color::Black] {
+            let image = symmetry.color(color);
+            *fields.get_mut(image) = symmetry.fields(self.fields.get(color), color);
+            *vitals.get_mut(image) = self.vitals.get(color);
+        }
+        let hexes = symmetry.hexes(self.hexes);
+        let turn = symmetry.color(self.turn);
+
+        Self {
+            fields,
+            hexes,
+            turn,
+            vitals,
+            zobrist: zobrist::new(
+                fields,
+                hexes,
+                ColorMap::new(vitals.white.hexes, vitals.black.hexes),
+                turn,
+                self.game_type,
+                self.hexes_to_exchange,
+            ),
+            hexes_to_exchange: self.hexes_to_exchange,
+            draw_after_plies: self.draw_after_plies,
+            reversible_plies: self.reversible_plies,
+            game_type: self.game_type,
+        }
+    }
+    /// Returns the same representative for every position that the 12 symmetries of the board
+    /// map onto each other, along with the symmetry that maps this position to it. Use the
+    /// symmetry's inverse to map moves in the representative back to this position.
+    pub fn canonical(&self) -> (Self, Symmetry) {
+        Symmetry::all()
+            .map(|symmetry| (self.transformed(symmetry), symmetry))
+            .min_by_key(|(board, _)| {
+                (
+                    board.hexes,
+                    board.fields.white,
+                    board.fields.black,
+                    board.turn == Color::Black,
+                    board.vitals.white.hexes,
+                    board.vitals.black.hexes,
+                )
+            })
+            .expect("There is always the identity")
+    }
+    /// Returns whether `draw_after_plies` is set and that many plies have passed without a
+    /// capture, an exchange or a tile removal.
+    pub fn is_ply_limit_reached(&self) -> bool {
+        match self.draw_after_plies {
+            Some(limit) => self.reversible_plies >= limit,
+            None => false,
+        }
+    }
+    // This function does NOT consider draw by threefold repetition because move history is not the
+    // concern of Board. See Model or AI for that.
+    pub fn outcome(&self) -> Outcome {
+        let fields = self.fields.get(self.turn);
+
+        if fields == 0 {
+            // No more pieces left
+            Outcome::Win(self.turn.switch())
+        } else if fields == self.hexes && !self.can_exchange() {
+            // There are no empty fields to move to and we can't exchange. Since the board never
+            // comes apart, that means the pieces fill every field of their color.
+            Outcome::DrawStalemate
+        } else if !self.can_ever_capture(Color::White) && !self.can_ever_capture(Color::Black) {
+            Outcome::DrawInsufficientMaterial
+        } else if self.is_ply_limit_reached() {
+            Outcome::DrawNoProgress
+        } else {
+            Outcome::InProgress
+        }
+    }
+    /// Returns whether neither player can ever win from this position, however the game goes on.
+    /// This is meant for adjudicating games, such as engine matches, through the same checks that
+    /// end a game under the rules: it is equivalent to `outcome` finding a stalemate or
+    /// insufficient material. The ply limit and repetitions don't make a position dead.
+    pub fn is_dead_draw(&self) -> bool {
+        let outcome = self.outcome();
+        outcome == Outcome::DrawStalemate || outcome == Outcome::DrawInsufficientMaterial
+    }
+}
+
+// Field and piece methods
+impl Board {
+    fn perft_in_place(&mut self, depth: u8) -> u64 {
+        if depth == 0 {
+            1
+        } else if depth == 1 {
+            u64::from(self.count_moves())
+        } else {
+            let mut sum = 0;
+            for mv in self.generate_moves() {
+                let undo = self.make_move(&mv);
+                sum += self.perft_in_place(depth - 1);
+                self.unmake_move(undo);
+            }
+            sum
+        }
+    }
+    fn toggle_field(&mut self, bb: BitBoard, color: Color) {
+        *self.fields.get_mut(color) ^= bb;
+    }
+    /// Returns whether `color` could capture a piece at some point in the game. Every field has two
+    /// edge neighbors on its own hex, so it takes at least two pieces to surround one, and a lone
+    /// piece can only capture by exchanging. Each player collects at most the tiles left on the
+    /// board, except for the one under the last pieces.
+    fn can_ever_capture(&self, color: Color) -> bool {
+        let tiles_left = self.hexes.count_ones() as u8 / 3 - 1;
+        self.pieces(color) > 1 || self.hexes(color) + tiles_left >= self.hexes_to_exchange
+    }
+    fn remove_piece(&mut self, bb: BitBoard, color: Color) {
+        assert!(
+            self.is_piece_on_bitboard(bb, color),
+            "There is no piece at {:?} to remove",
+            FieldCoord::from_bitboard(bb, color)
+        );
+        self.toggle_field(bb, color);
+        self.zobrist.toggle_field(bb, color);
+        self.vitals.get_mut(color).pieces -= 1;
+    }
+    fn check_captures(&mut self, mut fields_to_check: BitBoard) {
+        // fields_to_check must be a BitBoard for the opponent player (i.e. opposite of current turn)
+        let us = self.turn;
+        let them = us.switch();
+        fields_to_check &= self.hexes & self.fields.get(them);
+        for bb in fields_to_check.iter() {
+            let neighbors = self.hexes & EDGE_NEIGHBORS.bb_get(bb, them);
+            if !self.fields.get(us) & neighbors == 0 {
+                self.remove_piece(bb, them);
+            }
+        }
+    }
+}
+
+// Hex methods
+impl Board {
+    /// A hex is removable (and must be removed) if it is empty and is "attached to the board by 3
+    /// or less adjacent sides."
+    fn is_hex_removable(&self, index: usize) -> bool {
+        debug_assert!(self.is_hex_extant(index));
+
+        if (self.fields.white | self.fields.black) & HEX_MASK[index] != 0 {
+            return false;
+        }
+        self.is_hex_maybe_removable(index)
+    }
+    /// Assuming this hex is empty, would it be removable?
+    fn is_hex_maybe_removable(&self, index: usize) -> bool {
+        // Combining colors here is okay because there won't be overlaps
+        let hex = self.hexes
+            & (HEX_FIELD_NEIGHBORS.index_get(index, Color::White)
+                | HEX_FIELD_NEIGHBORS.index_get(index, Color::Black));
+
+        // There are 18 combinations to check for each hex
+        for &comb in &REMOVABLE_HEX_COMBS[index * 18..index * 18 + 18] {
+            if hex == comb {
+                return true;
+            }
+        }
+        false
+    }
+    fn remove_hex(&mut self, index: usize) -> bool {
+        let removable = self.is_hex_removable(index);
+
+        if removable {
+            self.hexes &= !HEX_MASK[index];
+            self.zobrist.remove_hex(index);
+        }
+        removable
+    }
+    fn check_hexes(&mut self, index: usize) -> (u8, BitBoard) {
+        let mut remove_count = 0;
+        let mut fields = 0;
+
+        if self.remove_hex(index) {
+            remove_count += 1;
+
+            let our_neighbors = HEX_FIELD_NEIGHBORS.index_get(index, self.turn);
+            let their_neighbors = HEX_FIELD_NEIGHBORS.index_get(index, self.turn.switch());
+
+            for neighbor in (self.hexes & (our_neighbors | their_neighbors)).iter() {
+                let check_result = self.check_hexes(neighbor.to_index());
+                remove_count += check_result.0;
+                fields |= check_result.1;
+            }
+
+            // Add in the opponent's neighbors, excluding those on hexes that have been removed
+            fields |= their_neighbors;
+            fields &= self.hexes;
+        }
+        (remove_count, fields)
+    }
+}
+
+/// Returns whether the hexes are all joined to each other by their sides.
+fn is_connected(hexes: BitBoard) -> bool {
+    if hexes == 0 {
+        return true;
+    }
+    let mut joined = HEX_MASK[hexes.to_index()];
+    let mut frontier = joined;
+    while frontier != 0 {
+        let mut grown = 0;
+        for hex in (frontier & HEX_COORD_MASK).iter() {
+            let neighbors = hexes
+                & (HEX_FIELD_NEIGHBORS.index_get(hex.to_index(), Color::White)
+                    | HEX_FIELD_NEIGHBORS.index_get(hex.to_index(), Color::Black));
+            for field in neighbors.iter() {
+                grown |= HEX_MASK[field.to_index()];
+            }
+        }
+        frontier = grown & !joined;
+        joined |= grown;
+    }
+    joined == hexes
+}
+
+/// Generates a random starting position from a seed, in the style of Fischer random chess. Like
+/// Laurentius, each of the 18 outer tiles holds two pieces and the center tile is empty, but the
+/// pieces are placed on random fields. Black's pieces are white's turned by half a circle, so
+/// neither player is favored. Arrangements where a piece starts surrounded are skipped.
+fn random_starting_position(seed: u64) -> StartingPosition {
+    let half_turn = Symmetry::rotation(3);
+    let mut rng = Rng::new(seed);
+    loop {
+        let mut fields = ColorMap::new(0, 0);
+        // Turning the board by half a circle maps these hexes onto the other nine outer hexes
+        for hex in 0..9 {
+            let first = rng.below(6) as u8;
+            let mut second = rng.below(5) as u8;
+            if second >= first {
+                second += 1;
+            }
+            for &f in &[first, second] {
+                let field = FieldCoord::from_hex_f(hex, f);
+                let image = half_turn.field(field);
+                *fields.get_mut(field.color()) |= field.to_bitboard();
+                *fields.get_mut(image.color()) |= image.to_bitboard();
+            }
+        }
+
+        let surrounded = [Color::White, Color::Black].iter().any(|&color| {
+            let enemies = fields.get(color.switch());
+            fields.get(color).iter().any(|bb| {
+                let neighbors = LAURENTIUS.hexes & EDGE_NEIGHBORS.bb_get(bb, color);
+                neighbors & !enemies == 0
+            })
+        });
+        if !surrounded {
+            return StartingPosition {
+                fields,
+                hexes: LAURENTIUS.hexes,
+                vitals: LAURENTIUS.vitals,
+            };
+        }
+    }
+}
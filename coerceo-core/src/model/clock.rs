@@ -0,0 +1,123 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::time::{Duration, Instant};
+
+use super::{Color, ColorMap};
+
+/// The starting time for each player and the time added after each of their moves (a Fischer
+/// increment). With a delay, each player's time only starts running once they have thought for
+/// that long on a move, and the unused part of the delay isn't kept.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeControl {
+    pub time: Duration,
+    pub increment: Duration,
+    pub delay: Duration,
+}
+
+impl TimeControl {
+    pub fn new(time: Duration, increment: Duration) -> Self {
+        Self {
+            time,
+            increment,
+            delay: Duration::from_secs(0),
+        }
+    }
+    pub fn with_delay(self, delay: Duration) -> Self {
+        Self { delay, ..self }
+    }
+}
+
+/// A chess clock. At most one player's time runs at once.
+#[derive(Clone, Copy)]
+pub struct Clock {
+    remaining: ColorMap<Duration>,
+    increment: Duration,
+    delay: Duration,
+    running: Option<(Color, Instant)>,
+}
+
+impl Clock {
+    pub fn new(time_control: TimeControl) -> Self {
+        Self {
+            remaining: ColorMap::new(time_control.time, time_control.time),
+            increment: time_control.increment,
+            delay: time_control.delay,
+            running: None,
+        }
+    }
+    /// Starts the given player's time, stopping the other player's time if it was running.
+    pub fn start(&mut self, color: Color) {
+        self.stop();
+        self.running = Some((color, Instant::now()));
+    }
+    /// Stops the clock and returns the player whose time was running.
+    pub fn stop(&mut self) -> Option<Color> {
+        let (color, start) = self.running.take()?;
+        let used = self.time_used(start);
+        let remaining = self.remaining.get_mut(color);
+        *remaining = remaining.checked_sub(used).unwrap_or_default();
+        Some(color)
+    }
+    /// Stops the clock after a player moves, and gives them their increment.
+    pub fn press(&mut self) {
+        if let Some(color) = self.stop() {
+            if !self.is_flagged(color) {
+                *self.remaining.get_mut(color) += self.increment;
+            }
+        }
+    }
+    /// Returns a player's remaining time, counting the time used so far this turn.
+    pub fn remaining(&self, color: Color) -> Duration {
+        let remaining = self.remaining.get(color);
+        match self.running {
+            Some((running, start)) if running == color => remaining
+                .checked_sub(self.time_used(start))
+                .unwrap_or_default(),
+            _ => remaining,
+        }
+    }
+    pub fn increment(&self) -> Duration {
+        self.increment
+    }
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+    pub fn running(&self) -> Option<Color> {
+        self.running.map(|(color, _)| color)
+    }
+    pub fn is_flagged(&self, color: Color) -> bool {
+        self.remaining(color) == Duration::from_secs(0)
+    }
+    /// Returns how long until the running player's remaining time falls to `time`, counting the
+    /// delay left this turn, or `None` if the clock is stopped.
+    pub fn time_until(&self, time: Duration) -> Option<Duration> {
+        let (color, start) = self.running?;
+        let delay_left = self
+            .delay
+            .checked_sub(Instant::now() - start)
+            .unwrap_or_default();
+        let to_go = self.remaining(color).checked_sub(time).unwrap_or_default();
+        Some(delay_left + to_go)
+    }
+    /// Returns how much time has come off the clock since a turn started, which excludes the delay.
+    fn time_used(&self, start: Instant) -> Duration {
+        (Instant::now() - start)
+            .checked_sub(self.delay)
+            .unwrap_or_default()
+    }
+}
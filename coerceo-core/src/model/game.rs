@@ -0,0 +1,331 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::mem;
+use std::time::{Duration, Instant};
+
+use crate::model::{
+    Board, Clock, Color, GameType, IllegalMove, Move, MoveAnnotated, Outcome, RepetitionHistory,
+    TimeControl,
+};
+
+/// A head start for a weaker player: the other player starts without some of their pieces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Handicap {
+    /// The player who gives up pieces
+    pub color: Color,
+    pub pieces: u8,
+}
+
+/// The most pieces a handicap can take off the board
+pub const MAX_HANDICAP: u8 = 3;
+
+/// The rules a game is played under. They are fixed once the game starts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rules {
+    pub game_type: GameType,
+    pub hexes_to_exchange: u8,
+    pub draw_after_plies: Option<u16>,
+    pub time_control: Option<TimeControl>,
+    pub handicap: Option<Handicap>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            game_type: GameType::Laurentius,
+            hexes_to_exchange: 2,
+            draw_after_plies: None,
+            time_control: None,
+            handicap: None,
+        }
+    }
+}
+
+/// Returns the number of the move that the ply after `plies` plies belongs to. White's ply and
+/// Black's reply share a number, starting at 1.
+pub fn move_number(plies: usize) -> usize {
+    plies / 2 + 1
+}
+
+/// How long a player took over their moves.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ThinkTimes {
+    pub moves: u32,
+    pub total: Duration,
+    pub longest: Duration,
+}
+
+impl ThinkTimes {
+    /// Returns the average time per move, or `None` before the player has moved.
+    pub fn average(&self) -> Option<Duration> {
+        if self.moves == 0 {
+            None
+        } else {
+            Some(self.total / self.moves)
+        }
+    }
+}
+
+/// What undoing a ply restores: the board, the move that led to it, the outcome, how long the
+/// player took over that move, and the clock, stopped.
+type State = (
+    Board,
+    Option<MoveAnnotated>,
+    Outcome,
+    Option<Duration>,
+    Option<Clock>,
+);
+
+/// A single game: the board, the moves played so far, the clock and the outcome. Unlike `Model`,
+/// this knows nothing about the players or the GUI, so it can be driven by anything that makes
+/// moves.
+#[derive(Clone)]
+pub struct Game {
+    pub rules: Rules,
+    pub board: Board,
+    pub clock: Option<Clock>,
+    /// The number of plies played, including ones that were taken back
+    pub ply_count: u64,
+    pub last_move: Option<MoveAnnotated>,
+    pub outcome: Outcome,
+    /// How long the player who played `last_move` took over it
+    think_time: Option<Duration>,
+    /// When the player to move started thinking
+    turn_start: Instant,
+    /// The clock as it was when the player to move started thinking, so that taking back their
+    /// move gives them back the time they spent on it
+    turn_clock: Option<Clock>,
+    undo_stack: Vec<State>,
+    redo_stack: Vec<State>,
+}
+
+impl Game {
+    /// Starts a game, running White's clock if the rules have one.
+    pub fn new(rules: Rules) -> Self {
+        let board = Board::new(rules.game_type, rules.hexes_to_exchange);
+        let board = match rules.handicap {
+            Some(handicap) => board.with_handicap(handicap.color, handicap.pieces),
+            None => board,
+        };
+        Self::from_board(rules, board)
+    }
+    /// Starts a game from a position other than the usual start, such as a tutorial lesson.
+    pub fn from_board(rules: Rules, mut board: Board) -> Self {
+        board.draw_after_plies = rules.draw_after_plies;
+
+        let mut game = Self {
+            rules,
+            board,
+            clock: rules.time_control.map(Clock::new),
+            ply_count: 0,
+            last_move: None,
+            outcome: Outcome::InProgress,
+            think_time: None,
+            turn_start: Instant::now(),
+            turn_clock: None,
+            undo_stack: vec![],
+            redo_stack: vec![],
+        };
+        game.start_clock();
+        game
+    }
+    /// Plays a move for the player to move and returns what it removed from the board.
+    pub fn play(&mut self, mv: Move) -> Result<&MoveAnnotated, IllegalMove> {
+        self.board.check_move(&mv)?;
+        self.ply_count += 1;
+        self.push_undo_state(self.turn_clock);
+        if let Some(ref mut clock) = self.clock {
+            clock.press();
+        }
+        self.last_move = Some(self.board.annotated_apply_move(&mv));
+        self.think_time = Some(self.turn_start.elapsed());
+        self.update_outcome();
+        self.start_clock();
+        Ok(self.last_move.as_ref().expect("A move was just played"))
+    }
+    /// Ends the game with a win for the player who isn't moving. Resigning can be taken back like
+    /// a move.
+    pub fn resign(&mut self) {
+        assert_eq!(self.outcome, Outcome::InProgress);
+        // The position doesn't change, so neither does the time taken back with it
+        self.push_undo_state(self.stopped_clock());
+        self.outcome = Outcome::WinByResignation(self.board.turn.switch());
+        self.start_clock();
+    }
+    /// Ends the game with a win for the player who isn't moving if the player to move has run out
+    /// of time. Returns whether it did. Like resigning, this can be taken back, but the game ends
+    /// again as soon as it is checked with the clock still out of time.
+    pub fn check_flag(&mut self) -> bool {
+        let flagged = match self.clock {
+            Some(ref clock) => !self.is_game_over() && clock.is_flagged(self.board.turn),
+            None => false,
+        };
+        if flagged {
+            self.push_undo_state(self.stopped_clock());
+            self.outcome = Outcome::WinOnTime(self.board.turn.switch());
+            self.start_clock();
+        }
+        flagged
+    }
+    pub fn is_game_over(&self) -> bool {
+        self.outcome != Outcome::InProgress
+    }
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+    /// Takes back one ply, or a resignation. Returns whether there was anything to take back.
+    pub fn undo(&mut self) -> bool {
+        match self.undo_stack.pop() {
+            Some(state) => {
+                let state = self.restore(state);
+                self.redo_stack.push(state);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Plays the last ply taken back again. Returns whether there was anything to play.
+    pub fn redo(&mut self) -> bool {
+        match self.redo_stack.pop() {
+            Some(state) => {
+                let state = self.restore(state);
+                self.undo_stack.push(state);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Returns how many of the other player's pieces `color` has taken so far, by surrounding them
+    /// or by exchanging for them.
+    pub fn pieces_captured(&self, color: Color) -> u8 {
+        let start = self.undo_stack.first().map_or(self.board, |state| state.0);
+        start.pieces(color.switch()) - self.board.pieces(color.switch())
+    }
+    /// Returns each position from the start of the game up to and including the current one, with
+    /// the move that led to it. Resigning and running out of time don't add a position.
+    pub fn positions(&self) -> Vec<(Board, Option<&MoveAnnotated>)> {
+        let mut positions: Vec<_> = self
+            .undo_stack
+            .iter()
+            .map(|(board, last_move, ..)| (*board, last_move.as_ref()))
+            .chain(Some((self.board, self.last_move.as_ref())))
+            .collect();
+        // Resigning or running out of time leaves the board as it was
+        positions.dedup_by(|later, earlier| later.0 == earlier.0);
+        positions
+    }
+    /// Returns how many plies have been played to reach the current position. Unlike `ply_count`,
+    /// this leaves out plies that were taken back.
+    pub fn plies_played(&self) -> usize {
+        self.positions().len() - 1
+    }
+    /// Returns the number of the move being played, counting a ply by each player as one move.
+    pub fn move_number(&self) -> usize {
+        move_number(self.plies_played())
+    }
+    /// Returns how long `color` took over the moves that led to the current position. The time
+    /// of a move that was taken back isn't counted, and neither is the time spent on the current
+    /// turn.
+    pub fn think_times(&self, color: Color) -> ThinkTimes {
+        let mut states: Vec<_> = self
+            .undo_stack
+            .iter()
+            .map(|&(board, _, _, think_time, _)| (board, think_time))
+            .chain(Some((self.board, self.think_time)))
+            .collect();
+        // Resigning or running out of time leaves the board and the last move as they were
+        states.dedup_by(|later, earlier| later.0 == earlier.0);
+
+        let mut times = ThinkTimes::default();
+        for (board, think_time) in states {
+            // The player who moved into a position is the one who isn't to move in it
+            if let (Some(time), true) = (think_time, board.turn == color.switch()) {
+                times.moves += 1;
+                times.total += time;
+                times.longest = times.longest.max(time);
+            }
+        }
+        times
+    }
+    /// Returns the boards from the start of the game up to and including the current one.
+    pub fn board_list(&self) -> Vec<Board> {
+        let mut board_list: Vec<_> = self.undo_stack.iter().map(|t| t.0).collect();
+        board_list.push(self.board);
+        board_list
+    }
+    /// Saves the current state, with the clock to go back to when it is restored.
+    fn push_undo_state(&mut self, clock: Option<Clock>) {
+        self.undo_stack.push((
+            self.board,
+            self.last_move.clone(),
+            self.outcome,
+            self.think_time,
+            clock,
+        ));
+        self.redo_stack.clear();
+    }
+    /// Swaps in a saved state and returns the one it replaced. The replaced state keeps the clock
+    /// from the start of the current turn, so that playing it again doesn't charge anyone for the
+    /// time spent in between.
+    fn restore(&mut self, (board, last_move, outcome, think_time, clock): State) -> State {
+        let replaced = (
+            mem::replace(&mut self.board, board),
+            mem::replace(&mut self.last_move, last_move),
+            mem::replace(&mut self.outcome, outcome),
+            mem::replace(&mut self.think_time, think_time),
+            self.turn_clock,
+        );
+        self.clock = clock;
+        self.start_clock();
+        replaced
+    }
+    /// Returns the clock as it stands, stopped.
+    fn stopped_clock(&self) -> Option<Clock> {
+        self.clock.map(|mut clock| {
+            clock.stop();
+            clock
+        })
+    }
+    fn update_outcome(&mut self) {
+        if self.outcome == Outcome::InProgress {
+            let earlier: Vec<_> = self.undo_stack.iter().map(|t| t.0).collect();
+            if RepetitionHistory::from_boards(&earlier).is_repetition(&self.board) {
+                self.outcome = Outcome::DrawThreefoldRepetition;
+            } else {
+                self.outcome = self.board.outcome();
+            }
+        }
+    }
+    /// Runs the clock for the player to move, or stops it if the game is over. The player's own
+    /// think time starts over too, and the clock is remembered for taking back their move.
+    fn start_clock(&mut self) {
+        self.turn_start = Instant::now();
+        self.turn_clock = self.stopped_clock();
+        let game_over = self.is_game_over();
+        if let Some(ref mut clock) = self.clock {
+            if game_over {
+                clock.stop();
+            } else {
+                clock.start(self.board.turn);
+            }
+        }
+    }
+}
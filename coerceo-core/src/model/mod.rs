@@ -17,206 +17,58 @@
 
 pub mod bitboard;
 mod board;
+mod clock;
 mod constants;
+mod game;
+mod repetition;
+pub mod rng;
+mod symmetry;
 pub mod ttable;
+pub mod tutorial;
 mod zobrist;
 
-use std::cell::RefCell;
 use std::fmt;
-use std::mem;
 use std::ops::RangeInclusive;
 
-use glium::glutin::EventsLoopProxy;
-
 use self::bitboard::BitBoard;
-pub use self::board::Board;
-use crate::ai::AI;
-
-pub struct Model {
-    pub game_type: GameType,
-    pub board: Board,
-    pub exchange_one_hex: RefCell<bool>,
-    pub ply_count: u64,
-    pub players: ColorMap<Player>,
-    pub selected_piece: Option<FieldCoord>,
-    pub last_move: Option<MoveAnnotated>,
-    pub exchanging: bool,
-    pub ai: AI,
-    pub ai_search_depth: RefCell<i32>,
-    pub window_states: RefCell<WindowStates>,
-    pub outcome: Outcome,
-    undo_stack: Vec<(Board, Option<MoveAnnotated>, Outcome)>,
-    redo_stack: Vec<(Board, Option<MoveAnnotated>, Outcome)>,
-    pub events_proxy: EventsLoopProxy,
-}
-
-impl Model {
-    pub fn new(
-        game_type: GameType,
-        players: ColorMap<Player>,
-        events_proxy: EventsLoopProxy,
-    ) -> Self {
-        Self {
-            game_type,
-            board: Board::new(game_type, 2),
-            exchange_one_hex: RefCell::new(false),
-            ply_count: 0,
-            players,
-            selected_piece: None,
-            last_move: None,
-            exchanging: false,
-            ai: AI::new(),
-            ai_search_depth: RefCell::new(6),
-            window_states: RefCell::new(WindowStates::default()),
-            outcome: Outcome::InProgress,
-            undo_stack: vec![],
-            redo_stack: vec![],
-            events_proxy,
-        }
-    }
-    pub fn reset(&mut self, game_type: GameType, players: ColorMap<Player>) {
-        self.game_type = game_type;
-        self.players = players;
-
-        let exchange_hex_count = if *self.exchange_one_hex.borrow() {
-            1
-        } else {
-            2
-        };
-        self.board = Board::new(game_type, exchange_hex_count);
-        self.ply_count = 0;
-        self.selected_piece = None;
-        self.last_move = None;
-        self.exchanging = false;
-        self.ai = AI::new();
-        self.outcome = Outcome::InProgress;
-        self.undo_stack.clear();
-        self.redo_stack.clear();
-    }
-    pub fn try_move(&mut self, mv: Move) -> bool {
-        if self.board.can_apply_move(&mv) {
-            self.ply_count += 1;
-            self.push_undo_state();
-            self.last_move = Some(self.board.annotated_apply_move(&mv));
-            self.update_outcome();
-            true
-        } else {
-            false
-        }
-    }
-    pub fn can_undo(&self) -> bool {
-        let comp_v_comp =
-            self.players.white == Player::Computer && self.players.black == Player::Computer;
-        !comp_v_comp && !self.undo_stack.is_empty()
-    }
-    pub fn can_redo(&self) -> bool {
-        let comp_v_comp =
-            self.players.white == Player::Computer && self.players.black == Player::Computer;
-        !comp_v_comp && !self.redo_stack.is_empty()
-    }
-    pub fn push_undo_state(&mut self) {
-        self.undo_stack
-            .push((self.board, self.last_move.clone(), self.outcome));
-        self.redo_stack.clear();
-    }
-    pub fn undo_move(&mut self) {
-        while let Some((board, last_move, outcome)) = self.undo_stack.pop() {
-            self.redo_stack.push((
-                mem::replace(&mut self.board, board),
-                mem::replace(&mut self.last_move, last_move),
-                mem::replace(&mut self.outcome, outcome),
-            ));
-
-            self.clear_selection();
-            self.exchanging = false;
-
-            if Player::Human == self.players.get(board.turn) {
-                break;
-            }
-        }
-    }
-    pub fn redo_move(&mut self) {
-        while let Some((board, last_move, outcome)) = self.redo_stack.pop() {
-            self.undo_stack.push((
-                mem::replace(&mut self.board, board),
-                mem::replace(&mut self.last_move, last_move),
-                mem::replace(&mut self.outcome, outcome),
-            ));
-
-            self.clear_selection();
-            self.exchanging = false;
-
-            if Player::Human == self.players.get(board.turn) {
-                break;
-            }
-        }
-    }
-    pub fn board_list(&self) -> Vec<Board> {
-        let mut board_list: Vec<_> = self.undo_stack.iter().map(|ref t| t.0).collect();
-        board_list.push(self.board);
-        board_list
-    }
-    pub fn clear_selection(&mut self) {
-        self.selected_piece = None;
-    }
-    pub fn current_player(&self) -> Player {
-        self.players.get(self.board.turn)
-    }
-    pub fn update_outcome(&mut self) {
-        if self.outcome == Outcome::InProgress {
-            // Only take positions after the last irreversible move
-            let board_list: Vec<_> = self
-                .board_list()
-                .into_iter()
-                .rev()
-                .skip(1)
-                .take_while(|b| b.vitals == self.board.vitals)
-                .collect();
-
-            if board_list.len() >= 8 && board_list.iter().filter(|&&b| b == self.board).count() >= 2
-            {
-                self.outcome = Outcome::DrawThreefoldRepetition;
-            } else {
-                self.outcome = self.board.outcome();
-            }
-        }
-    }
-    pub fn is_game_over(&self) -> bool {
-        self.outcome != Outcome::InProgress
-    }
-    pub fn resign(&mut self) {
-        assert_eq!(self.outcome, Outcome::InProgress);
-        self.outcome = Outcome::Win(self.board.turn.switch());
-    }
-}
-
-#[derive(Default)]
-pub struct WindowStates {
-    pub about: bool,
-    pub ai_debug: bool,
-    pub how_to_play: bool,
-}
-
-#[derive(Copy, Clone)]
+pub use self::board::{Board, IllegalMove, PlayerVitals, SetupError, UndoInfo};
+pub use self::clock::{Clock, TimeControl};
+pub use self::game::{move_number, Game, Handicap, Rules, ThinkTimes, MAX_HANDICAP};
+pub use self::repetition::RepetitionHistory;
+pub use self::symmetry::Symmetry;
+pub use self::zobrist::ZobristHash;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum GameType {
     Laurentius,
     Ocius,
+    /// A Laurentius board with the pieces placed at random. The same seed always gives the same
+    /// starting position.
+    Random(u64),
 }
 
 /// The outcome of a game. This includes being in progress; a win/loss by capturing all of an
-/// opponent's pieces; and a draw by stalemate (no legal moves left), insufficient material, or
-/// threefold repetition.
+/// opponent's pieces, by the opponent resigning or by the opponent running out of time; and a draw
+/// by stalemate (no legal moves left), insufficient material, threefold repetition, or reaching
+/// `Board::draw_after_plies`.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Outcome {
     InProgress,
     DrawStalemate,
     DrawInsufficientMaterial,
     DrawThreefoldRepetition,
+    DrawNoProgress,
     Win(Color),
+    /// The other player resigned. `Board::outcome` never returns this, as resigning isn't a move.
+    WinByResignation(Color),
+    /// The other player ran out of time. Like resigning, only `Game` ends a game this way.
+    WinOnTime(Color),
 }
 
+/// Who plays a side, as chosen when starting a game. The `player::Player` that chooses the side's
+/// moves is made from this.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub enum Player {
+pub enum PlayerKind {
     Human,
     Computer,
 }
@@ -271,7 +123,7 @@ impl<T: Copy> ColorMap<T> {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Move {
     Exchange(BitBoard, Color),
     Move(BitBoard, BitBoard, Color),
@@ -284,11 +136,24 @@ impl Move {
     pub fn exchange_from_field(field: FieldCoord) -> Self {
         Move::Exchange(field.to_bitboard(), field.color())
     }
-    pub fn annotate(&self, pieces: Vec<FieldCoord>, hexes: Vec<HexCoord>) -> MoveAnnotated {
+    pub fn annotate(
+        &self,
+        pieces: Vec<FieldCoord>,
+        hexes: Vec<HexCoord>,
+        tiles_gained: u8,
+    ) -> MoveAnnotated {
         MoveAnnotated {
             mv: *self,
             removed_pieces: pieces,
             removed_hexes: hexes,
+            tiles_gained,
+        }
+    }
+    /// Returns the color of the player who plays this move.
+    pub fn mover(&self) -> Color {
+        match *self {
+            Move::Move(_, _, color) => color,
+            Move::Exchange(_, color) => color.switch(),
         }
     }
 }
@@ -316,10 +181,142 @@ impl fmt::Display for Move {
 #[derive(Clone)]
 pub struct MoveAnnotated {
     pub mv: Move,
+    /// Every piece the move removed, including an exchanged piece. Each piece's field has the
+    /// color of the side that lost it.
     pub removed_pieces: Vec<FieldCoord>,
     pub removed_hexes: Vec<HexCoord>,
+    /// The number of tiles credited to the mover. Tiles removed by an exchange aren't credited.
+    pub tiles_gained: u8,
+}
+
+impl MoveAnnotated {
+    /// Returns the pieces that `color` lost to the move.
+    pub fn pieces_lost(&self, color: Color) -> impl Iterator<Item = FieldCoord> + '_ {
+        self.removed_pieces
+            .iter()
+            .cloned()
+            .filter(move |piece| piece.color() == color)
+    }
+    /// Returns the pieces the move captured by surrounding them, leaving out an exchanged piece.
+    pub fn captured_pieces(&self) -> impl Iterator<Item = FieldCoord> + '_ {
+        let exchanged = match self.mv {
+            Move::Exchange(bb, color) => Some(FieldCoord::from_bitboard(bb, color)),
+            Move::Move(..) => None,
+        };
+        self.pieces_lost(self.mv.mover().switch())
+            .filter(move |&piece| Some(piece) != exchanged)
+    }
+    /// Describes the move in words for reading aloud, like "White moves c3a to d3b, capturing one
+    /// piece; tile b2 removed".
+    pub fn announcement(&self) -> String {
+        let count = |count: usize, noun: &str| {
+            let words = ["no", "one", "two", "three", "four", "five", "six"];
+            let number = match words.get(count) {
+                Some(word) => word.to_string(),
+                None => count.to_string(),
+            };
+            match count {
+                1 => format!("{} {}", number, noun),
+                _ => format!("{} {}s", number, noun),
+            }
+        };
+
+        let mut text = match self.mv {
+            Move::Move(from, to, color) => format!(
+                "{:?} moves {} to {}",
+                color,
+                FieldCoord::from_bitboard(from, color).to_notation(),
+                FieldCoord::from_bitboard(to, color).to_notation(),
+            ),
+            Move::Exchange(bb, color) => format!(
+                "{:?} exchanges tiles for the piece on {}",
+                color.switch(),
+                FieldCoord::from_bitboard(bb, color).to_notation(),
+            ),
+        };
+
+        let captured = self.captured_pieces().count();
+        if captured > 0 {
+            text += &format!(", capturing {}", count(captured, "piece"));
+        }
+        if self.tiles_gained > 0 {
+            text += &format!(
+                ", gaining {}",
+                count(usize::from(self.tiles_gained), "tile")
+            );
+        }
+
+        let mut hexes: Vec<_> = self
+            .removed_hexes
+            .iter()
+            .map(|hex| hex.to_notation())
+            .collect();
+        if let Some(last) = hexes.pop() {
+            let noun = if hexes.is_empty() { "tile" } else { "tiles" };
+            let list = if hexes.is_empty() {
+                last
+            } else {
+                format!("{} and {}", hexes.join(", "), last)
+            };
+            text += &format!("; {} {} removed", noun, list);
+        }
+        text
+    }
+    /// Returns whether the move was an exchange that went on to remove tiles or capture more
+    /// pieces.
+    pub fn is_exchange_chain(&self) -> bool {
+        match self.mv {
+            Move::Exchange(..) => {
+                !self.removed_hexes.is_empty() || self.captured_pieces().next().is_some()
+            }
+            Move::Move(..) => false,
+        }
+    }
 }
 
+impl fmt::Display for MoveAnnotated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let plural = |count: usize, noun: &str| match count {
+            1 => format!("1 {}", noun),
+            _ => format!("{} {}s", count, noun),
+        };
+
+        let mut effects = vec![];
+        let captured = self.captured_pieces().count();
+        if captured > 0 {
+            effects.push(format!("captures {}", plural(captured, "piece")));
+        }
+        if self.tiles_gained > 0 {
+            effects.push(format!(
+                "gains {}",
+                plural(usize::from(self.tiles_gained), "tile")
+            ));
+        }
+        let uncredited = self.removed_hexes.len() - usize::from(self.tiles_gained);
+        if uncredited > 0 {
+            effects.push(format!("removes {}", plural(uncredited, "tile")));
+        }
+
+        write!(f, "{}", self.mv)?;
+        if !effects.is_empty() {
+            write!(f, ": {}", effects.join(", "))?;
+        }
+        if !self.removed_hexes.is_empty() {
+            let hexes: Vec<_> = self
+                .removed_hexes
+                .iter()
+                .map(|hex| hex.to_notation())
+                .collect();
+            write!(f, " (removed {})", hexes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The numbers of tiles that an exchange may cost. The official rules use two, and one is a common
+/// variation.
+pub const EXCHANGE_COSTS: RangeInclusive<u8> = 1..=3;
+
 const COORD_RANGE: RangeInclusive<i8> = -2..=2;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -385,28 +382,9 @@ impl FieldCoord {
 
         1 << (hex * 3 + self.f / 2)
     }
+    /// Writes the field as its hex's notation followed by a letter for the field, such as `c3f`.
     pub fn to_notation(self) -> String {
-        let mut notation = String::with_capacity(3);
-
-        notation.push(match self.x {
-            -2 => 'a',
-            -1 => 'b',
-            0 => 'c',
-            1 => 'd',
-            2 => 'e',
-            _ => unreachable!(),
-        });
-
-        let offset = 3 + if self.x < 0 { self.x } else { 0 };
-        notation.push(match self.y + offset {
-            1 => '1',
-            2 => '2',
-            3 => '3',
-            4 => '4',
-            5 => '5',
-            _ => unreachable!(),
-        });
-
+        let mut notation = self.to_hex().to_notation();
         notation.push(match self.f {
             5 => 'a',
             4 => 'b',
@@ -418,6 +396,24 @@ impl FieldCoord {
         });
         notation
     }
+    /// Parses a field written by `to_notation`, such as `c3f`.
+    pub fn from_notation(notation: &str) -> Option<Self> {
+        let hex = HexCoord::from_notation(notation.get(..2)?)?;
+        let mut chars = notation.get(2..)?.chars();
+        let f = match chars.next()? {
+            'a' => 5,
+            'b' => 4,
+            'c' => 3,
+            'd' => 2,
+            'e' => 1,
+            'f' => 0,
+            _ => return None,
+        };
+        if chars.next().is_some() {
+            return None;
+        }
+        Some(hex.to_field(f))
+    }
     pub fn f(self) -> u8 {
         self.f
     }
@@ -481,6 +477,50 @@ impl HexCoord {
                 _ => unreachable!(),
             }
     }
+    /// Writes the hex as a file from `a` to `e`, left to right, followed by a rank from `1` up,
+    /// counted from the bottom of the file, such as `c3` for the center hex.
+    pub fn to_notation(self) -> String {
+        let mut notation = String::with_capacity(2);
+
+        notation.push(match self.x {
+            -2 => 'a',
+            -1 => 'b',
+            0 => 'c',
+            1 => 'd',
+            2 => 'e',
+            _ => unreachable!(),
+        });
+
+        let offset = 3 + if self.x < 0 { self.x } else { 0 };
+        notation.push(match self.y + offset {
+            1 => '1',
+            2 => '2',
+            3 => '3',
+            4 => '4',
+            5 => '5',
+            _ => unreachable!(),
+        });
+        notation
+    }
+    /// Parses a hex written by `to_notation`, such as `c3`.
+    pub fn from_notation(notation: &str) -> Option<Self> {
+        let mut chars = notation.chars();
+        let x = match chars.next()? {
+            'a' => -2,
+            'b' => -1,
+            'c' => 0,
+            'd' => 1,
+            'e' => 2,
+            _ => return None,
+        };
+        let rank = chars.next()?.to_digit(10)? as i8;
+        if chars.next().is_some() {
+            return None;
+        }
+
+        let offset = 3 + if x < 0 { x } else { 0 };
+        Self::try_new(x, rank - offset)
+    }
     fn is_valid_coord(x: i8, y: i8) -> bool {
         COORD_RANGE.contains(&x) && COORD_RANGE.contains(&y) && COORD_RANGE.contains(&(x + y))
     }
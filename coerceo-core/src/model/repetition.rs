@@ -0,0 +1,105 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::model::{Board, ColorMap, PlayerVitals, ZobristHash};
+
+const FILTER_SIZE: usize = 4096;
+
+/// The positions that came before the current one, for detecting draws by threefold repetition.
+///
+/// A move is irreversible if it changes either player's vitals, that is, if it captures, exchanges
+/// or removes a tile. A position is repeated if it has already come up twice since the last
+/// irreversible move, and at least 8 positions have been played since then. Both the game and the
+/// AI's searches use this, so they always agree on what counts as a repetition.
+#[derive(Clone)]
+pub struct RepetitionHistory {
+    entries: Vec<(ZobristHash, ColorMap<PlayerVitals>)>,
+    // For each entry, the index of the first entry after the last irreversible move before it
+    run_starts: Vec<usize>,
+    // How many entries fall into each slot, indexed by the low bits of their hashes. The entries
+    // only need to be scanned when a slot holds at least two of them.
+    filter: Vec<u16>,
+}
+
+impl Default for RepetitionHistory {
+    fn default() -> Self {
+        Self {
+            entries: vec![],
+            run_starts: vec![],
+            filter: vec![0; FILTER_SIZE],
+        }
+    }
+}
+
+impl RepetitionHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Creates a history from a game's boards, oldest first.
+    pub fn from_boards(boards: &[Board]) -> Self {
+        let mut history = Self::new();
+        for board in boards {
+            history.push(board.zobrist, board.vitals);
+        }
+        history
+    }
+    /// Adds a position to the end of the history. Searches pass the hash and vitals rather than the
+    /// board, as the board has usually moved on by the time its position is pushed.
+    pub fn push(&mut self, zobrist: ZobristHash, vitals: ColorMap<PlayerVitals>) {
+        let run_start = match self.entries.last() {
+            Some(&(_, last_vitals)) if last_vitals == vitals => {
+                *self.run_starts.last().expect("Every entry has a run start")
+            }
+            _ => self.entries.len(),
+        };
+        self.entries.push((zobrist, vitals));
+        self.run_starts.push(run_start);
+        self.filter[slot(zobrist)] += 1;
+    }
+    /// Removes the last position pushed.
+    pub fn pop(&mut self) {
+        let (zobrist, _) = self.entries.pop().expect("Pushed before popping");
+        self.run_starts.pop();
+        self.filter[slot(zobrist)] -= 1;
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Returns whether `board`, played right after the last position in the history, is a draw by
+    /// threefold repetition.
+    pub fn is_repetition(&self, board: &Board) -> bool {
+        let window = match (self.entries.last(), self.run_starts.last()) {
+            // The move to `board` was reversible
+            (Some(&(_, vitals)), Some(&start)) if vitals == board.vitals => &self.entries[start..],
+            _ => return false,
+        };
+        window.len() >= 8
+            && self.filter[slot(board.zobrist)] >= 2
+            && window
+                .iter()
+                .filter(|&&(zobrist, _)| zobrist == board.zobrist)
+                .count()
+                >= 2
+    }
+}
+
+fn slot(zobrist: ZobristHash) -> usize {
+    zobrist as usize & (FILTER_SIZE - 1)
+}
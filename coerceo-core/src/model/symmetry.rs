@@ -0,0 +1,121 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::model::bitboard::{BitBoard, BitBoardExt};
+use crate::model::constants::{HEX_COORD_MASK, HEX_MASK};
+use crate::model::{Color, FieldCoord, Move};
+
+/// One of the 12 ways to rotate and reflect the board onto itself: a reflection across the
+/// vertical axis, if `reflected` is set, followed by `rotation` clockwise turns of 60 degrees.
+///
+/// Turning the board by 60 degrees moves every piece onto a field of the other color, so the odd
+/// rotations also swap the players. The game is the same for both players, so a position and its
+/// image under any symmetry have the same result.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Symmetry {
+    rotation: u8,
+    reflected: bool,
+}
+
+impl Symmetry {
+    /// Returns the symmetry that turns the board clockwise by `turns` sixths of a circle.
+    pub fn rotation(turns: u8) -> Self {
+        Symmetry {
+            rotation: turns % 6,
+            reflected: false,
+        }
+    }
+    /// Returns all 12 symmetries, starting with the identity.
+    pub fn all() -> impl Iterator<Item = Self> {
+        [false, true].iter().flat_map(|&reflected| {
+            (0..6).map(move |rotation| Symmetry {
+                rotation,
+                reflected,
+            })
+        })
+    }
+    /// Returns the symmetry that undoes this one.
+    pub fn inverse(self) -> Self {
+        if self.reflected {
+            // Reflecting reverses the direction of the rotation, so this is its own inverse
+            self
+        } else {
+            Symmetry {
+                rotation: (6 - self.rotation) % 6,
+                reflected: false,
+            }
+        }
+    }
+    /// Returns whether this symmetry moves white's pieces onto black's fields and vice versa.
+    pub fn swaps_colors(self) -> bool {
+        self.rotation % 2 == 1
+    }
+    /// Returns the color a player's pieces have after applying this symmetry.
+    pub fn color(self, color: Color) -> Color {
+        if self.swaps_colors() {
+            color.switch()
+        } else {
+            color
+        }
+    }
+    pub fn field(self, coord: FieldCoord) -> FieldCoord {
+        let (mut x, mut y, mut f) = (coord.to_hex().x(), coord.to_hex().y(), coord.f());
+        if self.reflected {
+            let (rx, ry) = (-x, x + y);
+            x = rx;
+            y = ry;
+            f = (6 - f) % 6;
+        }
+        for _ in 0..self.rotation {
+            let (rx, ry) = (x + y, -x);
+            x = rx;
+            y = ry;
+            f = (f + 1) % 6;
+        }
+        FieldCoord::new(x, y, f)
+    }
+    /// Maps a bitboard of `color`'s fields. The result holds fields of `self.color(color)`.
+    pub fn fields(self, bb: BitBoard, color: Color) -> BitBoard {
+        bb.iter()
+            .map(|field| {
+                self.field(FieldCoord::from_bitboard(field, color))
+                    .to_bitboard()
+            })
+            .fold(0, |fields, field| fields | field)
+    }
+    /// Maps a hex bitboard, which has all three bits set for each hex.
+    pub fn hexes(self, hexes: BitBoard) -> BitBoard {
+        (hexes & HEX_COORD_MASK)
+            .iter()
+            .map(|hex| {
+                // Any field of a hex lands on the hex's image
+                let image = self.field(FieldCoord::from_bitboard(hex, Color::Black));
+                HEX_MASK[image.to_hex().to_index()]
+            })
+            .fold(0, |hexes, hex| hexes | hex)
+    }
+    pub fn apply_move(self, mv: &Move) -> Move {
+        match *mv {
+            Move::Move(from, to, color) => Move::Move(
+                self.fields(from, color),
+                self.fields(to, color),
+                self.color(color),
+            ),
+            Move::Exchange(bb, color) => Move::Exchange(self.fields(bb, color), self.color(color)),
+        }
+    }
+}
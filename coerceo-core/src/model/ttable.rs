@@ -0,0 +1,141 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::model::zobrist::ZobristHash;
+
+const BUCKET_SIZE: usize = 4;
+const BUCKET_COUNT: usize = 1 << 18;
+const BUCKET_MASK: u64 = BUCKET_COUNT as u64 - 1;
+
+// This could just by an array, but because arrays are allocated on the stack (even when
+// doing Box::new(array)), we need to use a Vec
+pub struct TTable {
+    table: Vec<[Entry; BUCKET_SIZE]>,
+    age: u8,
+    hits: u64,
+    misses: u64,
+}
+
+impl TTable {
+    pub fn new() -> Self {
+        Self {
+            table: vec![[Entry::default(); BUCKET_SIZE]; BUCKET_COUNT],
+            age: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+    /// Starts a new search. Entries from earlier searches are replaced first, and the hit and miss
+    /// counters are reset.
+    pub fn inc_age(&mut self) {
+        self.age = self.age.wrapping_add(1);
+        self.hits = 0;
+        self.misses = 0;
+    }
+    pub fn get(&mut self, zobrist: ZobristHash, depth: i8) -> Option<Score> {
+        let bucket = &self.table[(zobrist & BUCKET_MASK) as usize];
+        let score = bucket
+            .iter()
+            .find(|entry| entry.zobrist == zobrist)
+            .filter(|entry| entry.depth >= depth)
+            .map(|entry| entry.score);
+
+        if score.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        score
+    }
+    pub fn set(&mut self, zobrist: ZobristHash, score: Score, depth: i8) {
+        let age = self.age;
+        let bucket = &mut self.table[(zobrist & BUCKET_MASK) as usize];
+
+        let entry = match bucket.iter().position(|entry| entry.zobrist == zobrist) {
+            Some(i) => {
+                // Don't let a shallower search overwrite a deeper result from this search
+                let entry = &mut bucket[i];
+                if entry.age == age && entry.depth > depth {
+                    return;
+                }
+                entry
+            }
+            None => {
+                // Replace an empty entry if there is one. Otherwise, replace the shallowest entry,
+                // preferring entries from earlier searches. This keeps deep entries from being
+                // clobbered by the many shallow quiescence entries.
+                bucket
+                    .iter_mut()
+                    .min_by_key(|entry| {
+                        if entry.zobrist == 0 {
+                            -0x200
+                        } else if entry.age != age {
+                            i16::from(entry.depth) - 0x100
+                        } else {
+                            i16::from(entry.depth)
+                        }
+                    })
+                    .unwrap()
+            }
+        };
+
+        *entry = Entry {
+            score,
+            age,
+            depth,
+            zobrist,
+        };
+    }
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+/// A score stored in the table. `Beta` is a lower bound from a search that failed high, and
+/// `Alpha` is an upper bound from a search that failed low.
+#[derive(Clone, Copy)]
+pub enum Score {
+    Exact(i16),
+    Beta(i16),
+    Alpha(i16),
+}
+
+// TODO: Store best move for move ordering?
+// TODO: Use upper bits of ZobristHash to save space? The lower bits pick the bucket.
+#[derive(Clone, Copy)]
+pub struct Entry {
+    pub score: Score,
+    pub age: u8,
+    pub depth: i8,
+    pub zobrist: ZobristHash,
+}
+
+impl Default for Entry {
+    fn default() -> Self {
+        Self {
+            score: Score::Exact(0),
+            age: 0,
+            depth: 0,
+            // The only field that matters for determining if this is an empty entry or not.
+            // Assume (and hope) that no valid board ever hashes to 0.
+            zobrist: 0,
+        }
+    }
+}
@@ -0,0 +1,151 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::model::bitboard::BitBoardExt;
+use crate::model::{
+    Board, Color, ColorMap, FieldCoord, GameType, Move, MoveAnnotated, PlayerKind, PlayerVitals,
+};
+
+/// One step of the tutorial: a small position where White has to make a certain kind of move.
+pub struct Lesson {
+    pub title: &'static str,
+    pub instructions: &'static str,
+    /// Shown when the player makes a legal move that isn't what the lesson asks for
+    pub retry: &'static str,
+    white: &'static [&'static str],
+    black: &'static [&'static str],
+    white_tiles: u8,
+    goal: fn(&MoveAnnotated) -> bool,
+}
+
+pub const LESSONS: [Lesson; 4] = [
+    Lesson {
+        title: "Moving",
+        instructions: "Each turn, you move one of your pieces. A piece moves to a field of its \
+                       own color that shares a corner with the field it is on, even if that \
+                       field is on another tile.\n\nClick one of the white pieces to see where \
+                       it can go, then click one of the marked fields to move it there.",
+        retry: "Move one of the white pieces.",
+        white: &["c3c", "c3e"],
+        black: &["c4b", "c4d"],
+        white_tiles: 0,
+        goal: |mv| match mv.mv {
+            Move::Move(..) => true,
+            Move::Exchange(..) => false,
+        },
+    },
+    Lesson {
+        title: "Capturing",
+        instructions: "A piece is captured when every field next to it is taken by an enemy \
+                       piece. The edge of the board doesn't need to be covered, so pieces on \
+                       the edge are easier to capture.\n\nOne of the black pieces is next to \
+                       the edge, with only one empty field beside it. Move a white piece there \
+                       to capture it.",
+        retry: "That didn't capture anything. Look for the black piece with a single empty \
+                field next to it.",
+        white: &["c3c", "c3e"],
+        black: &["c3b", "c4b"],
+        white_tiles: 0,
+        goal: |mv| mv.captured_pieces().count() > 0,
+    },
+    Lesson {
+        title: "Removing tiles",
+        instructions: "When a tile is left empty and is joined to the rest of the board by no \
+                       more than three of its sides, it is removed. The player who emptied it \
+                       takes the tile. Any pieces left surrounded after it goes are captured \
+                       too.\n\nMove the white piece that is alone on its tile onto the other \
+                       tile, so that the tile it leaves is removed.",
+        retry: "No tile was removed. Move the piece off its tile entirely, so that nothing is \
+                left on it.",
+        white: &["c3e", "c4e"],
+        black: &["c4b", "c4f"],
+        white_tiles: 0,
+        goal: |mv| mv.tiles_gained > 0,
+    },
+    Lesson {
+        title: "Exchanging",
+        instructions: "Instead of moving, you can give up two of the tiles you have taken to \
+                       capture any enemy piece on the board. You don't get those tiles back.\n\n\
+                       You have two tiles. Click Exchange, then click a black piece to capture \
+                       it.",
+        retry: "Use the Exchange button instead of moving a piece.",
+        white: &["c3c", "c3e"],
+        black: &["c3b", "c4d"],
+        white_tiles: 2,
+        goal: |mv| match mv.mv {
+            Move::Exchange(..) => true,
+            Move::Move(..) => false,
+        },
+    },
+];
+
+impl Lesson {
+    /// Returns the position the lesson starts from, with White to move.
+    pub fn board(&self) -> Board {
+        let fields = |notations: &[&str]| {
+            notations
+                .iter()
+                .map(|notation| {
+                    FieldCoord::from_notation(notation)
+                        .expect("Lessons use valid fields")
+                        .to_bitboard()
+                })
+                .fold(0, |fields, field| fields | field)
+        };
+        let fields = ColorMap::new(fields(self.white), fields(self.black));
+        // Only the tiles with pieces on them are left
+        let hexes = (fields.white | fields.black)
+            .iter()
+            .map(|field| 0b111 << (field.to_index() * 3))
+            .fold(0, |hexes, hex| hexes | hex);
+        let vitals = |pieces: &[&str], hexes| PlayerVitals {
+            pieces: pieces.len() as u8,
+            hexes,
+        };
+        Board::from_setup(
+            fields,
+            hexes,
+            ColorMap::new(vitals(self.white, self.white_tiles), vitals(self.black, 0)),
+            Color::White,
+            GameType::Laurentius,
+            2,
+        )
+        .expect("Lessons start from valid positions")
+    }
+    /// Returns whether the move is the kind the lesson asks for.
+    pub fn is_goal(&self, mv: &MoveAnnotated) -> bool {
+        (self.goal)(mv)
+    }
+}
+
+/// Where the player is in the tutorial, and what to go back to when they leave it.
+#[derive(Clone, Copy)]
+pub struct Tutorial {
+    pub lesson: usize,
+    /// Whether the player's last move was turned down for not meeting the lesson's goal
+    pub wrong_move: bool,
+    pub previous_game: (GameType, ColorMap<PlayerKind>),
+}
+
+impl Tutorial {
+    pub fn lesson(&self) -> &'static Lesson {
+        &LESSONS[self.lesson]
+    }
+    pub fn is_last_lesson(&self) -> bool {
+        self.lesson + 1 == LESSONS.len()
+    }
+}
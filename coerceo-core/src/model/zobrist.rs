@@ -18,16 +18,40 @@
 #![allow(clippy::unreadable_literal)]
 
 use crate::model::bitboard::{BitBoard, BitBoardExt};
-use crate::model::{Color, ColorMap};
+use crate::model::constants::HEX_MASK;
+use crate::model::{Color, ColorMap, GameType};
 
 pub type ZobristHash = u64;
 
-pub fn new(fields: ColorMap<BitBoard>, hex_count: ColorMap<u8>, turn: Color) -> ZobristHash {
+pub fn new(
+    fields: ColorMap<BitBoard>,
+    hexes: BitBoard,
+    hex_count: ColorMap<u8>,
+    turn: Color,
+    game_type: GameType,
+    hexes_to_exchange: u8,
+) -> ZobristHash {
+    // Positions played under different rules must not share transposition table entries
     let mut hash = 0;
+    if game_type == GameType::Ocius {
+        hash ^= OCIUS;
+    }
+    match hexes_to_exchange {
+        1 => hash ^= ONE_HEX_EXCHANGE,
+        3 => hash ^= THREE_HEX_EXCHANGE,
+        _ => {}
+    }
+
+    for (index, &hex) in HEX_MASK.iter().enumerate() {
+        if hexes & hex == 0 {
+            hash.remove_hex(index);
+        }
+    }
 
-    for (w, b) in fields.white.iter().zip(fields.black.iter()) {
-        hash ^= PIECE_FIELD.white[w.trailing_zeros() as usize];
-        hash ^= PIECE_FIELD.black[b.trailing_zeros() as usize];
+    for &color in &[Color::White, Color::Black] {
+        for bb in fields.get(color).iter() {
+            hash.toggle_field(bb, color);
+        }
     }
 
     hash ^= HEX_COUNT.white[hex_count.white as usize];
@@ -43,6 +67,7 @@ pub fn new(fields: ColorMap<BitBoard>, hex_count: ColorMap<u8>, turn: Color) ->
 pub trait ZobristExt {
     fn toggle_field(&mut self, bb: BitBoard, color: Color);
     fn set_hex_count(&mut self, old: u8, new: u8, color: Color);
+    fn remove_hex(&mut self, index: usize);
     fn switch_turn(&mut self);
 }
 
@@ -57,6 +82,10 @@ impl ZobristExt for ZobristHash {
         *self ^= hex_count[new as usize];
     }
 
+    fn remove_hex(&mut self, index: usize) {
+        *self ^= REMOVED_HEX[index];
+    }
+
     fn switch_turn(&mut self) {
         *self ^= WHITE_TO_MOVE;
     }
@@ -64,6 +93,18 @@ impl ZobristExt for ZobristHash {
 
 // These constants were generated with random.org
 const WHITE_TO_MOVE: u64 = 0xb047cbc27fa474a6;
+const OCIUS: u64 = 0x6e23f58ae235413b;
+const ONE_HEX_EXCHANGE: u64 = 0x2201235c9a08da9d;
+const THREE_HEX_EXCHANGE: u64 = 0x93c1e7a05d4f28b6;
+
+// Only removed hexes are hashed, so that the starting position's hash doesn't depend on the number
+// of hexes on the board
+#[rustfmt::skip]
+const REMOVED_HEX: [u64; 19] = [
+    0x38bf84a0be32fd60, 0xc0f4b7aa11682456, 0xe905c64e70e05fa4, 0xbed4047f39034eab, 0xb92c884fdd619f3b, 0xd1b0364e50cba1e3, 0xb35d299e7f22888e,
+    0x3428877cd36337ee, 0x3cfd2f92b67b24f3, 0x518a75db1b66858c, 0x39f8b9093157671e, 0x85cec40b542bf63a, 0xf3ccd822a535270c, 0x310aa9e3ac2517b9,
+    0x6a037b4e84111ca0, 0x5749492a7a4932f9, 0x04cdd618f7498a69, 0x5e3ab689b04963d6, 0xa5fdca5db5e6fdac
+];
 
 #[rustfmt::skip]
 const HEX_COUNT: ColorMap<[u64; 18]> = ColorMap {
@@ -0,0 +1,125 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The players that choose each side's moves. Whoever runs the game asks the player to move for
+//! a move in the same way whatever kind of player it is, so new kinds of players, such as an
+//! opponent over the network or an external engine, only need to implement `Player`.
+
+use crate::ai::{AIMove, SearchInfo, SearchSettings, Waker, AI};
+use crate::model::{Board, PlayerKind};
+
+/// Everything a player may need to know to choose a move.
+pub struct MoveRequest {
+    pub board: Board,
+    /// The positions of the game so far, for spotting repetitions
+    pub board_list: Vec<Board>,
+    pub settings: SearchSettings,
+    /// Called when the move is ready, or when there is news about the search for it
+    pub waker: Waker,
+    /// Whether to wait a little before moving, so a person can see the move being made
+    pub delay: bool,
+    pub ply_count: u64,
+}
+
+/// Chooses the moves for one side of the game.
+pub trait Player {
+    fn kind(&self) -> PlayerKind;
+    /// Returns whether the player is waiting to be asked for a move.
+    fn is_idle(&self) -> bool;
+    /// Starts choosing a move for the position in `request`.
+    fn think(&mut self, request: MoveRequest);
+    /// Returns the chosen move once it is ready. The player is idle again afterwards.
+    fn try_recv(&mut self) -> Option<AIMove>;
+    /// Stops choosing a move, such as when the game changes underneath the player.
+    fn stop(&mut self);
+    /// Returns how far the player has got with the search for its latest move, if it searches.
+    fn search_info(&self) -> Option<SearchInfo> {
+        None
+    }
+}
+
+/// A person choosing moves on the board. Their moves are played as they are entered, so there is
+/// never a move to receive.
+pub struct HumanInput;
+
+impl Player for HumanInput {
+    fn kind(&self) -> PlayerKind {
+        PlayerKind::Human
+    }
+    /// A person is always working on their move, so they never need to be asked for one.
+    fn is_idle(&self) -> bool {
+        false
+    }
+    fn think(&mut self, _request: MoveRequest) {}
+    fn try_recv(&mut self) -> Option<AIMove> {
+        None
+    }
+    fn stop(&mut self) {}
+}
+
+/// The computer player, searching on this machine.
+pub struct LocalEngine {
+    ai: AI,
+}
+
+impl LocalEngine {
+    pub fn new() -> Self {
+        Self { ai: AI::new() }
+    }
+}
+
+impl Default for LocalEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Player for LocalEngine {
+    fn kind(&self) -> PlayerKind {
+        PlayerKind::Computer
+    }
+    fn is_idle(&self) -> bool {
+        self.ai.is_idle()
+    }
+    fn think(&mut self, request: MoveRequest) {
+        self.ai.think(
+            request.board,
+            request.board_list,
+            request.settings,
+            request.waker,
+            request.delay,
+            request.ply_count,
+        );
+    }
+    fn try_recv(&mut self) -> Option<AIMove> {
+        self.ai.try_recv()
+    }
+    fn stop(&mut self) {
+        self.ai.stop();
+    }
+    fn search_info(&self) -> Option<SearchInfo> {
+        self.ai.search_info.read().ok().map(|info| info.clone())
+    }
+}
+
+/// Returns the player that plays a side as `kind`.
+pub fn new_player(kind: PlayerKind) -> Box<dyn Player> {
+    match kind {
+        PlayerKind::Human => Box::new(HumanInput),
+        PlayerKind::Computer => Box::new(LocalEngine::new()),
+    }
+}
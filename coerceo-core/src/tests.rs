@@ -0,0 +1,1577 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#![cfg(test)]
+
+use std::cmp;
+use std::thread;
+use std::time::Duration;
+
+use crate::ai::{
+    self, Book, Constraint, EngineKind, EvalParams, Network, SearchSettings, Strength, Tablebase,
+    Waker, Wdl,
+};
+use crate::config;
+use crate::log::{Level, Log};
+use crate::model::bitboard::BitBoardExt;
+use crate::model::rng::Rng;
+use crate::model::tutorial::LESSONS;
+use crate::model::{
+    Board, Clock, Color, ColorMap, FieldCoord, Game, GameType, Handicap, HexCoord, IllegalMove,
+    Move, MoveAnnotated, Outcome, PlayerKind, PlayerVitals, RepetitionHistory, Rules, SetupError,
+    Symmetry, TimeControl,
+};
+use crate::player::{new_player, MoveRequest};
+
+// All of the following perft results have not been verified by an external source. They only test
+// for consistency with earlier versions of the program.
+
+#[test]
+fn laurentius_perft_4() {
+    let counts = [48, 2304, 110304, 5280654];
+    let board = Board::new(GameType::Laurentius, 2);
+
+    for (i, &count) in counts.iter().enumerate() {
+        assert_eq!(count, board.perft(i as u8 + 1));
+    }
+}
+
+#[test]
+#[ignore]
+fn laurentius_perft_5() {
+    let counts = [48, 2304, 110304, 5280654, 254945184];
+    let board = Board::new(GameType::Laurentius, 2);
+
+    for (i, &count) in counts.iter().enumerate() {
+        assert_eq!(count, board.perft(i as u8 + 1));
+    }
+}
+
+#[test]
+#[ignore]
+fn laurentius_perft_6() {
+    let counts = [48, 2304, 110304, 5280654, 254945184, 12307984056];
+    let board = Board::new(GameType::Laurentius, 2);
+
+    for (i, &count) in counts.iter().enumerate() {
+        assert_eq!(count, board.perft(i as u8 + 1));
+    }
+}
+
+#[test]
+fn ocius_perft_5() {
+    let counts = [26, 676, 17234, 435572, 10739924];
+    let board = Board::new(GameType::Ocius, 2);
+
+    for (i, &count) in counts.iter().enumerate() {
+        assert_eq!(count, board.perft(i as u8 + 1));
+    }
+}
+
+#[test]
+#[ignore]
+fn ocius_perft_6() {
+    let counts = [26, 676, 17234, 435572, 10739924, 262208752];
+    let board = Board::new(GameType::Ocius, 2);
+
+    for (i, &count) in counts.iter().enumerate() {
+        assert_eq!(count, board.perft(i as u8 + 1));
+    }
+}
+
+#[test]
+#[ignore]
+fn ocius_perft_7() {
+    let counts = [26, 676, 17234, 435572, 10739924, 262208752, 6252014770];
+    let board = Board::new(GameType::Ocius, 2);
+
+    for (i, &count) in counts.iter().enumerate() {
+        assert_eq!(count, board.perft(i as u8 + 1));
+    }
+}
+
+#[test]
+fn perft_divide() {
+    let mut rng = Rng::new(9);
+    let mut board = Board::new(GameType::Laurentius, 2);
+    for _ in 0..40 {
+        for depth in 1..4 {
+            let divided = board.perft_divide(depth);
+            assert_eq!(board.count_moves() as usize, divided.len());
+            assert_eq!(
+                board.perft(depth),
+                divided.iter().map(|&(_, count)| count).sum::<u64>()
+            );
+            for (mv, count) in divided {
+                let mut after = board;
+                after.apply_move(&mv);
+                assert_eq!(after.perft(depth - 1), count);
+            }
+        }
+        if board.outcome() != Outcome::InProgress {
+            break;
+        }
+        let moves: Vec<_> = board.generate_moves().collect();
+        board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+    }
+    assert!(board.perft_divide(0).is_empty());
+}
+
+fn assert_captures_not_futile(board: &Board, depth: u8) {
+    if depth == 0 || board.outcome() != Outcome::InProgress {
+        return;
+    }
+
+    // With alpha this high, every quiet move at depth 1 should be pruned
+    let static_eval = ai::evaluate(board, &EvalParams::default());
+    for mv in board.generate_captures() {
+        let mut new_board = *board;
+        let undo = new_board.make_move(&mv);
+        assert!(!ai::is_futile(undo.is_quiet(), 1, static_eval, i16::MAX));
+    }
+
+    for mv in board.generate_moves() {
+        let mut new_board = *board;
+        let undo = new_board.make_move(&mv);
+        assert_eq!(
+            board.vitals == new_board.vitals,
+            ai::is_futile(undo.is_quiet(), 1, static_eval, i16::MAX)
+        );
+        assert_captures_not_futile(&new_board, depth - 1);
+    }
+}
+
+#[test]
+fn futility_never_prunes_captures() {
+    assert_captures_not_futile(&Board::new(GameType::Laurentius, 2), 3);
+    assert_captures_not_futile(&Board::new(GameType::Ocius, 2), 4);
+    assert_captures_not_futile(&Board::new(GameType::Ocius, 1), 4);
+}
+
+#[test]
+fn laurentius_hex_fragility() {
+    let board = Board::new(GameType::Laurentius, 2);
+    let fragility: Vec<_> = (0..19).map(|index| board.hex_fragility(index)).collect();
+
+    // Corner tiles have three neighbors, edge tiles have four, and the rest have six
+    for &index in &[0, 2, 7, 11, 16, 18] {
+        assert_eq!(Some(0), fragility[index]);
+    }
+    for &index in &[1, 3, 6, 12, 15, 17] {
+        assert_eq!(Some(1), fragility[index]);
+    }
+    for &index in &[4, 5, 8, 9, 10, 13, 14] {
+        assert_eq!(None, fragility[index]);
+    }
+}
+
+#[test]
+fn eval_params_round_trip() {
+    let mut params = EvalParams {
+        tempo: 7,
+        ..EvalParams::default()
+    };
+    params.endgame.fragile_tile[1] = 4;
+    assert_eq!(params, EvalParams::parse(&params.to_string()));
+    assert_eq!(EvalParams::default(), EvalParams::parse("tempo = oops"));
+
+    // Weights large enough to overflow the evaluation, or negative ones, are brought into range
+    let params = EvalParams::parse("piece = 30000\ntempo = -5\nopening_tile = 100\n");
+    assert_eq!(100, params.piece);
+    assert_eq!(0, params.tempo);
+    assert_eq!(100, params.opening.tile);
+}
+
+#[test]
+fn config_write_atomic() {
+    let dir = std::env::temp_dir().join(format!("coerceo-test-{}", std::process::id()));
+    let path = dir.join("test.cfg");
+
+    config::write_atomic(&path, b"old").unwrap();
+    config::write_atomic(&path, b"new").unwrap();
+    assert_eq!("new", std::fs::read_to_string(&path).unwrap());
+    assert_eq!(1, std::fs::read_dir(&dir).unwrap().count());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn book_probe() {
+    let board = Board::new(GameType::Laurentius, 2);
+    let mv = board.generate_moves().next().unwrap();
+    let notation = match mv {
+        Move::Move(from, to, color) => format!(
+            "{} {}",
+            FieldCoord::from_bitboard(from, color).to_notation(),
+            FieldCoord::from_bitboard(to, color).to_notation()
+        ),
+        Move::Exchange(..) => unreachable!(),
+    };
+
+    // The exchange is illegal at the start, so only the move can be picked
+    let book = Book::parse(&format!(
+        "# comment\n{key:x} 1 {mv}\n{key:x} 5 c3a\n{key:x} oops\n",
+        key = board.zobrist,
+        mv = notation
+    ));
+    assert_eq!(1, book.len());
+    let mut rng = Rng::new(0);
+    for _ in 0..10 {
+        assert_eq!(Some(mv), book.probe(&board, &mut rng, |_| true));
+    }
+    assert_eq!(None, book.probe(&board, &mut rng, |_| false));
+
+    // Rotations and reflections of a book position use its moves. The starting position is
+    // symmetric, so the move may come back as any of its equivalents.
+    let book = Book::parse(&format!("{:x} 1 {}", board.zobrist, notation));
+    for symmetry in Symmetry::all() {
+        let image = board.transformed(symmetry);
+        let probed = book.probe(&image, &mut rng, |_| true).unwrap();
+        assert!(Symmetry::all()
+            .any(|back| { image.transformed(back) == board && back.apply_move(&probed) == mv }));
+    }
+}
+
+#[test]
+fn field_notation_round_trip() {
+    for index in 0..57 {
+        for &color in &[Color::White, Color::Black] {
+            let field = FieldCoord::from_index(index, color);
+            assert_eq!(Some(field), FieldCoord::from_notation(&field.to_notation()));
+        }
+    }
+    assert_eq!(None, FieldCoord::from_notation("a5a"));
+    assert_eq!(None, FieldCoord::from_notation("c3"));
+    assert_eq!(None, FieldCoord::from_notation("c3fa"));
+
+    for index in 0..19 {
+        let hex = HexCoord::from_index(index);
+        assert_eq!(Some(hex), HexCoord::from_notation(&hex.to_notation()));
+    }
+    assert_eq!("c3", HexCoord::try_new(0, 0).unwrap().to_notation());
+    assert_eq!(None, HexCoord::from_notation("a4"));
+    assert_eq!(None, HexCoord::from_notation("c3f"));
+}
+
+#[test]
+fn board_from_setup() {
+    let setup = |board: &Board| {
+        Board::from_setup(
+            ColorMap::new(board.fields(Color::White), board.fields(Color::Black)),
+            board.hex_bitboard(),
+            board.vitals,
+            board.turn,
+            board.game_type(),
+            board.hexes_to_exchange,
+        )
+    };
+    let mut rng = Rng::new(7);
+    for &game_type in &[GameType::Laurentius, GameType::Ocius] {
+        let mut board = Board::new(game_type, 2);
+        for _ in 0..60 {
+            assert!(setup(&board) == Ok(board));
+            let moves: Vec<_> = board.generate_moves().collect();
+            if moves.is_empty() || board.outcome() != Outcome::InProgress {
+                break;
+            }
+            board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+        }
+    }
+
+    let vitals = |pieces, hexes| PlayerVitals { pieces, hexes };
+    let one_each = ColorMap::new(vitals(1, 0), vitals(1, 0));
+    let single_tile = |fields, hexes, vitals| {
+        Board::from_setup(fields, hexes, vitals, Color::White, GameType::Laurentius, 2).map(|_| ())
+    };
+    let fields = ColorMap::new(1 << (9 * 3), 1 << (9 * 3 + 1));
+    let tile = 0b111 << (9 * 3);
+    assert_eq!(Ok(()), single_tile(fields, tile, one_each));
+    assert_eq!(
+        Ok(()),
+        single_tile(fields, tile, ColorMap::new(vitals(1, 10), vitals(1, 8)))
+    );
+    assert_eq!(
+        Err(SetupError::TooManyTiles),
+        single_tile(fields, tile, ColorMap::new(vitals(1, 10), vitals(1, 9)))
+    );
+    assert_eq!(
+        Err(SetupError::MalformedHexes),
+        single_tile(fields, 0b011 << (9 * 3), one_each)
+    );
+    assert_eq!(
+        Err(SetupError::PieceOnRemovedHex(FieldCoord::from_index(
+            0,
+            Color::White
+        ))),
+        single_tile(ColorMap::new(1, fields.black), tile, one_each)
+    );
+    assert_eq!(
+        Err(SetupError::PieceCountMismatch(Color::Black)),
+        single_tile(fields, tile, ColorMap::new(vitals(1, 0), vitals(2, 0)))
+    );
+    // An empty tile on its own is attached by no sides at all
+    assert_eq!(
+        Err(SetupError::RemovableHex(HexCoord::from_index(0))),
+        single_tile(fields, tile | 0b111, one_each)
+    );
+    // Removing tiles never splits the board, so hexes that don't join up can't come up in a game
+    let apart = FieldCoord::from_notation("a3a").unwrap().to_bitboard();
+    let apart_hexes = 0b111 << (apart.to_index() * 3);
+    assert_eq!(
+        Err(SetupError::DisconnectedHexes),
+        single_tile(
+            ColorMap::new(fields.white | apart, fields.black),
+            tile | apart_hexes,
+            ColorMap::new(vitals(2, 0), vitals(1, 0))
+        )
+    );
+    assert_eq!(
+        format!(
+            "Hex {} is empty and should have been removed",
+            HexCoord::from_index(0).to_notation()
+        ),
+        SetupError::RemovableHex(HexCoord::from_index(0)).to_string()
+    );
+    assert_eq!(
+        Err(SetupError::InvalidExchangeCost(4)),
+        Board::from_setup(
+            fields,
+            tile,
+            one_each,
+            Color::White,
+            GameType::Laurentius,
+            4
+        )
+        .map(|_| ())
+    );
+}
+
+#[test]
+fn hash_distinguishes_hexes_and_rules() {
+    let fields = ColorMap::new(1 << (9 * 3), 1 << (9 * 3 + 1));
+    let board = |hexes| {
+        Board::from_parts(
+            GameType::Laurentius,
+            2,
+            hexes,
+            fields,
+            ColorMap::new(0, 0),
+            Color::White,
+        )
+    };
+    // Positions that only differ in their removed hexes don't share a hash
+    let tile = 0b111 << (9 * 3);
+    assert_ne!(board(tile).zobrist, board(tile | 0b111).zobrist);
+
+    // Neither do positions played under different rules
+    let laurentius = Board::new(GameType::Laurentius, 2);
+    assert_ne!(
+        laurentius.zobrist,
+        Board::new(GameType::Laurentius, 1).zobrist
+    );
+    let rules = |game_type, hexes_to_exchange| {
+        Board::from_parts(
+            game_type,
+            hexes_to_exchange,
+            tile,
+            fields,
+            ColorMap::new(0, 0),
+            Color::White,
+        )
+        .zobrist
+    };
+    assert_ne!(rules(GameType::Laurentius, 2), rules(GameType::Ocius, 2));
+    assert_ne!(
+        rules(GameType::Laurentius, 2),
+        rules(GameType::Laurentius, 1)
+    );
+}
+
+#[test]
+fn board_symmetries() {
+    let sorted = |board: &Board| {
+        let mut moves: Vec<_> = board.generate_moves().map(|mv| mv.to_string()).collect();
+        moves.sort();
+        moves
+    };
+    let mut rng = Rng::new(3);
+    let mut board = Board::new(GameType::Laurentius, 2);
+    while board.outcome() == Outcome::InProgress {
+        let (canonical, _) = board.canonical();
+        for symmetry in Symmetry::all() {
+            let image = board.transformed(symmetry);
+            assert!(image.transformed(symmetry.inverse()) == board);
+            assert!(image.canonical().0 == canonical);
+
+            // The image is a legal position with the same moves, rotated and reflected
+            let setup = Board::from_setup(
+                ColorMap::new(image.fields(Color::White), image.fields(Color::Black)),
+                image.hex_bitboard(),
+                image.vitals,
+                image.turn,
+                image.game_type(),
+                image.hexes_to_exchange,
+            );
+            assert!(setup == Ok(image));
+            let mut mapped: Vec<_> = board
+                .generate_moves()
+                .map(|mv| symmetry.apply_move(&mv).to_string())
+                .collect();
+            mapped.sort();
+            assert_eq!(sorted(&image), mapped);
+        }
+        let moves: Vec<_> = board.generate_moves().collect();
+        board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+    }
+}
+
+#[test]
+fn random_starts() {
+    for seed in 0..50 {
+        let board = Board::new(GameType::Random(seed), 2);
+        assert!(board == Board::new(GameType::Random(seed), 2));
+        assert!(
+            board.fields(Color::White)
+                != Board::new(GameType::Random(seed + 1), 2).fields(Color::White)
+        );
+        assert_eq!(18, board.pieces(Color::White));
+        assert_eq!(18, board.pieces(Color::Black));
+        assert_eq!(Outcome::InProgress, board.outcome());
+
+        let setup = Board::from_setup(
+            ColorMap::new(board.fields(Color::White), board.fields(Color::Black)),
+            board.hex_bitboard(),
+            board.vitals,
+            board.turn,
+            board.game_type(),
+            board.hexes_to_exchange,
+        );
+        assert!(setup == Ok(board));
+
+        // Turning the board by half a circle swaps the players' pieces
+        let turned = board.transformed(Symmetry::rotation(3));
+        assert_eq!(board.fields(Color::White), turned.fields(Color::White));
+        assert_eq!(board.fields(Color::Black), turned.fields(Color::Black));
+    }
+}
+
+#[test]
+fn exchange_costs() {
+    let board = |hexes_to_exchange, white_hexes| {
+        Board::from_parts(
+            GameType::Laurentius,
+            hexes_to_exchange,
+            0b111 << (9 * 3),
+            ColorMap::new(1 << (9 * 3), 1 << (9 * 3 + 1)),
+            ColorMap::new(white_hexes, 0),
+            Color::White,
+        )
+    };
+    for hexes_to_exchange in 1..=3 {
+        for white_hexes in 0..=3 {
+            let board = board(hexes_to_exchange, white_hexes);
+            let can_exchange = white_hexes >= hexes_to_exchange;
+            assert_eq!(can_exchange, board.can_exchange());
+            assert_eq!(
+                can_exchange,
+                board.generate_moves().any(|mv| match mv {
+                    Move::Exchange(..) => true,
+                    Move::Move(..) => false,
+                })
+            );
+            // No more tiles can be captured, so whoever can't exchange now never will
+            let outcome = if can_exchange {
+                Outcome::InProgress
+            } else {
+                Outcome::DrawInsufficientMaterial
+            };
+            assert_eq!(outcome, board.outcome());
+        }
+    }
+}
+
+#[test]
+fn illegal_move_reasons() {
+    let board = Board::new(GameType::Laurentius, 2);
+    let (from, to) = match board.generate_moves().next() {
+        Some(Move::Move(from, to, _)) => (from, to),
+        _ => unreachable!(),
+    };
+    assert_eq!(
+        Ok(()),
+        board.check_move(&Move::Move(from, to, Color::White))
+    );
+    assert_eq!(
+        Err(IllegalMove::NotYourTurn),
+        board.check_move(&Move::Move(from, to, Color::Black))
+    );
+    assert_eq!(
+        Err(IllegalMove::NoPiece(FieldCoord::from_bitboard(
+            to,
+            Color::White
+        ))),
+        board.check_move(&Move::Move(to, from, Color::White))
+    );
+
+    // Moving onto another white piece is either too far or occupied
+    let white = board.fields(Color::White);
+    let mut errors = vec![];
+    for from in white.iter() {
+        for to in (white & !from).iter() {
+            errors.push(board.check_move(&Move::Move(from, to, Color::White)));
+        }
+    }
+    assert!(errors.contains(&Err(IllegalMove::NotAVertexNeighbor)));
+    assert!(errors.contains(&Err(IllegalMove::DestinationOccupied)));
+    assert!(!errors.contains(&Ok(())));
+
+    let black = board.fields(Color::Black).iter().next().unwrap();
+    assert_eq!(
+        Err(IllegalMove::CannotExchange),
+        board.check_move(&Move::Exchange(black, Color::Black))
+    );
+    assert_eq!(
+        Err(IllegalMove::OwnPiece),
+        board.check_move(&Move::Exchange(from, Color::White))
+    );
+
+    let single_tile = Board::from_parts(
+        GameType::Laurentius,
+        2,
+        0b111 << (9 * 3),
+        ColorMap::new(1 << (9 * 3), 1 << (9 * 3 + 1)),
+        ColorMap::new(0, 0),
+        Color::White,
+    );
+    let off_board = 1 << (8 * 3);
+    assert_eq!(
+        Err(IllegalMove::RemovedHex(FieldCoord::from_bitboard(
+            off_board,
+            Color::White
+        ))),
+        single_tile.check_move(&Move::Move(off_board, 1 << (9 * 3), Color::White))
+    );
+}
+
+#[test]
+fn generate_and_count_moves() {
+    let mut rng = Rng::new(11);
+    let mut board = Board::new(GameType::Ocius, 1);
+    while board.outcome() == Outcome::InProgress {
+        let moves: Vec<_> = board.generate_moves().collect();
+        assert_eq!(moves.len() as u32, board.count_moves());
+        assert_eq!(
+            moves,
+            board.generate_moves_for(board.turn).collect::<Vec<_>>()
+        );
+
+        let them = board.turn.switch();
+        let mut switched = board;
+        switched.turn = them;
+        let their_moves: Vec<_> = board.generate_moves_for(them).collect();
+        assert!(their_moves.iter().all(|mv| switched.can_apply_move(mv)));
+        if switched.pieces(them) > 0 {
+            assert_eq!(their_moves, switched.generate_moves().collect::<Vec<_>>());
+        }
+
+        board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+    }
+}
+
+#[test]
+fn network_incremental_update() {
+    let mut rng = Rng::new(1);
+    let weights: Vec<_> = (0..(1 + 2 * 57 + 4 + 1) * ai::HIDDEN + 1)
+        .map(|_| (rng.below(201) as i32 - 100).to_string())
+        .collect();
+    let contents = format!("# Random weights\n{}\n{}", ai::HIDDEN, weights.join(" "));
+    let network = Network::parse(&contents).unwrap();
+    assert_eq!(Some(network.clone()), Network::parse(&network.to_string()));
+    assert_eq!(None, Network::parse("32 1 2 3"));
+
+    // Play random moves, which include captures and exchanges
+    let mut board = Board::new(GameType::Laurentius, 1);
+    let mut acc = network.accumulate(&board);
+    for _ in 0..200 {
+        let moves: Vec<_> = board.generate_moves().collect();
+        if moves.is_empty() || board.outcome() != Outcome::InProgress {
+            break;
+        }
+        let mut new_board = board;
+        new_board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+        network.update(&mut acc, &board, &new_board);
+        board = new_board;
+        assert_eq!(network.accumulate(&board), acc);
+        assert_eq!(
+            network.evaluate(&network.accumulate(&board), &board),
+            network.evaluate(&acc, &board)
+        );
+    }
+}
+
+#[test]
+#[ignore]
+fn tablebase() {
+    let tablebase = Tablebase::generate(2);
+    let single_tile = 0b111 << (9 * 3);
+    let one_each = ColorMap::new(1 << (9 * 3), 1 << (9 * 3 + 1));
+    let board = |hex_counts, turn| {
+        Board::from_parts(
+            GameType::Laurentius,
+            2,
+            single_tile,
+            one_each,
+            hex_counts,
+            turn,
+        )
+    };
+
+    // White can exchange for black's last piece
+    let white_to_move = board(ColorMap::new(2, 0), Color::White);
+    assert_eq!(Some(Wdl::Win), tablebase.probe(&white_to_move));
+    // Black can't exchange, so white exchanges next turn whatever black does
+    assert_eq!(
+        Some(Wdl::Loss),
+        tablebase.probe(&board(ColorMap::new(2, 0), Color::Black))
+    );
+    assert_eq!(None, tablebase.probe(&Board::new(GameType::Laurentius, 2)));
+
+    // Moving the position elsewhere on the board doesn't change its result
+    let shifted = Board::from_parts(
+        GameType::Laurentius,
+        2,
+        0b111,
+        ColorMap::new(0b001, 0b010),
+        ColorMap::new(2, 0),
+        Color::White,
+    );
+    assert_eq!(Some(Wdl::Win), tablebase.probe(&shifted));
+
+    let loaded = Tablebase::from_bytes(&tablebase.to_bytes()).unwrap();
+    assert_eq!(tablebase.len(), loaded.len());
+    assert_eq!(Some(Wdl::Win), loaded.probe(&white_to_move));
+}
+
+#[test]
+fn search_info_stats() {
+    let mut info = ai::SearchInfo::default();
+    assert_eq!(0, info.nps());
+    assert_eq!(None, info.tt_hit_rate());
+    assert!(info
+        .rows()
+        .iter()
+        .any(|row| row == &("Best move", String::from("-"))));
+
+    info.nodes = 5000;
+    info.elapsed = Duration::from_millis(250);
+    info.tt_hits = 1;
+    info.tt_misses = 3;
+    info.score = Some(-150);
+    assert_eq!(20000, info.nps());
+    assert_eq!(Some(0.25), info.tt_hit_rate());
+    assert!(info.rows().contains(&("Score", String::from("-1.50"))));
+
+    assert_eq!("Starting the search", info.progress_text());
+    let board = Board::new(GameType::Laurentius, 2);
+    info.depth = 3;
+    info.pv = board.generate_moves().take(2).collect();
+    assert_eq!(
+        format!("Depth 3, 5000 nodes, best so far {}", info.pv[0]),
+        info.progress_text()
+    );
+
+    // Without a time or node limit, progress is measured in iterations
+    info.depth_limit = 6;
+    assert_eq!(0.5, info.progress());
+    info.node_limit = Some(20000);
+    assert_eq!(0.25, info.progress());
+    let ai_move = info.ai_move().unwrap();
+    assert_eq!(info.pv[0], ai_move.mv);
+    assert_eq!(-150, ai_move.score);
+
+    // The copied text has every statistic and both lists of moves
+    info.root_moves = vec![(info.pv[0], -150), (info.pv[1], -300)];
+    let text = info.to_string();
+    assert!(text.contains("Nodes: 5000\n"));
+    assert!(text.contains(&format!("PV:\n    {}\n    {}\n", info.pv[0], info.pv[1])));
+    assert!(text.ends_with(&format!(
+        "Root moves:\n    {} -150\n    {} -300\n",
+        info.pv[0], info.pv[1]
+    )));
+}
+
+#[test]
+fn captures_include_collapsing_exchanges() {
+    let mut rng = Rng::new(7);
+    let mut found = 0;
+    for _ in 0..20 {
+        let mut board = Board::new(GameType::Laurentius, 1);
+        while board.outcome() == Outcome::InProgress {
+            let them = board.turn.switch();
+            let mut expected: Vec<_> = board
+                .generate_moves()
+                .filter(|mv| match mv {
+                    Move::Exchange(..) => {
+                        let mut new_board = board;
+                        new_board.apply_move(mv);
+                        new_board.pieces(them) + 1 < board.pieces(them)
+                    }
+                    Move::Move(..) => false,
+                })
+                .collect();
+            let mut exchanges: Vec<_> = board
+                .generate_captures()
+                .filter(|mv| match mv {
+                    Move::Exchange(..) => true,
+                    Move::Move(..) => false,
+                })
+                .collect();
+            expected.sort_by_key(|mv| format!("{:?}", mv));
+            exchanges.sort_by_key(|mv| format!("{:?}", mv));
+            assert_eq!(expected, exchanges);
+            found += exchanges.len();
+
+            let moves: Vec<_> = board.generate_moves().collect();
+            board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+        }
+    }
+    assert!(found > 0);
+}
+
+#[test]
+fn see_never_exceeds_immediate_gain() {
+    let params = EvalParams::default();
+    let mut rng = Rng::new(11);
+    let mut losing = 0;
+    for _ in 0..10 {
+        let mut board = Board::new(GameType::Laurentius, 2);
+        while board.outcome() == Outcome::InProgress {
+            for mv in board.generate_captures() {
+                let mut new_board = board;
+                new_board.apply_move(&mv);
+                let them = board.turn.switch();
+                let pieces = i16::from(board.pieces(them) - new_board.pieces(them));
+                let tiles =
+                    i16::from(new_board.hexes(board.turn)) - i16::from(board.hexes(board.turn));
+                let gain = pieces * params.piece * 2 + tiles * params.opening.tile;
+
+                let see = ai::see(&board, &mv, &params);
+                match mv {
+                    Move::Exchange(..) => assert_eq!(gain, see),
+                    Move::Move(..) => assert!(see <= gain),
+                }
+                if see < 0 {
+                    losing += 1;
+                }
+            }
+            let moves: Vec<_> = board.generate_moves().collect();
+            board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+        }
+    }
+    assert!(losing > 0);
+}
+
+#[test]
+fn proof_number_search() {
+    use std::sync::atomic::AtomicBool;
+
+    let board = |hex_counts, turn| {
+        Board::from_parts(
+            GameType::Laurentius,
+            2,
+            0b111 << (9 * 3),
+            ColorMap::new(1 << (9 * 3), 1 << (9 * 3 + 1)),
+            hex_counts,
+            turn,
+        )
+    };
+    let stop_signal = AtomicBool::new(false);
+
+    // White can exchange for black's last piece
+    let white_to_move = board(ColorMap::new(2, 0), Color::White);
+    match ai::solve(&white_to_move, 1000, &stop_signal) {
+        ai::Solution::Win(mv) => {
+            let mut new_board = white_to_move;
+            new_board.apply_move(&mv);
+            assert_eq!(Outcome::Win(Color::White), new_board.outcome());
+        }
+        solution => panic!("Expected a win, got {:?}", solution),
+    }
+    assert_eq!(
+        ai::Solution::Loss,
+        ai::solve(
+            &board(ColorMap::new(2, 0), Color::Black),
+            1000,
+            &stop_signal
+        )
+    );
+    assert_eq!(
+        Some(Color::White),
+        ai::adjudicate(&board(ColorMap::new(2, 0), Color::Black), 1000)
+    );
+    assert_eq!(
+        ai::Solution::Unknown,
+        ai::solve(&Board::new(GameType::Laurentius, 2), 1000, &stop_signal)
+    );
+}
+
+#[test]
+fn make_unmake_restores_board() {
+    let mut rng = Rng::new(5);
+    for &hexes_to_exchange in &[1, 2] {
+        let mut board = Board::new(GameType::Laurentius, hexes_to_exchange);
+        while board.outcome() == Outcome::InProgress {
+            let moves: Vec<_> = board.generate_moves().collect();
+            for mv in &moves {
+                let before = board;
+                let mut applied = board;
+                applied.apply_move(mv);
+
+                let undo = board.make_move(mv);
+                assert!(board == applied);
+                assert_eq!(applied.reversible_plies, board.reversible_plies);
+                board.unmake_move(undo);
+                assert!(board == before);
+                assert_eq!(before.reversible_plies, board.reversible_plies);
+            }
+
+            let before = board;
+            board.make_null_move();
+            assert_eq!(before.turn.switch(), board.turn);
+            assert_ne!(before.zobrist, board.zobrist);
+            board.unmake_null_move();
+            assert!(board == before);
+            board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+        }
+    }
+}
+
+#[test]
+fn reversible_ply_draw() {
+    let mut rng = Rng::new(11);
+    let mut board = Board::new(GameType::Laurentius, 2);
+    board.draw_after_plies = Some(6);
+    let mut longest = 0;
+    while board.outcome() == Outcome::InProgress {
+        let moves: Vec<_> = board.generate_moves().collect();
+        let before = board;
+        board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+        if board.vitals == before.vitals {
+            assert_eq!(before.reversible_plies + 1, board.reversible_plies);
+        } else {
+            assert_eq!(0, board.reversible_plies);
+        }
+        longest = cmp::max(longest, board.reversible_plies);
+    }
+    assert_eq!(Outcome::DrawNoProgress, board.outcome());
+    assert_eq!(6, longest);
+
+    // The count isn't part of the position, and there is no limit by default
+    let mut unlimited = board;
+    unlimited.draw_after_plies = None;
+    assert_eq!(Outcome::InProgress, unlimited.outcome());
+    assert!(board != unlimited);
+    unlimited.draw_after_plies = board.draw_after_plies;
+    unlimited.reversible_plies = 0;
+    assert!(board == unlimited);
+}
+
+#[test]
+fn pieces_and_hexes_iters() {
+    let mut rng = Rng::new(3);
+    let mut board = Board::new(GameType::Laurentius, 2);
+    while board.outcome() == Outcome::InProgress {
+        let hexes: Vec<_> = board.hexes_iter().collect();
+        assert_eq!(board.hex_bitboard().count_ones() as usize / 3, hexes.len());
+        for &color in &[Color::White, Color::Black] {
+            let pieces: Vec<_> = board.pieces_iter(color).collect();
+            assert_eq!(board.pieces(color) as usize, pieces.len());
+            let expected: Vec<_> = hexes
+                .iter()
+                .flat_map(|hex| (0..6).map(move |f| hex.to_field(f)))
+                .filter(|&field| field.color() == color && board.is_piece_on_field(field))
+                .collect();
+            assert_eq!(expected.len(), pieces.len());
+            assert!(pieces.iter().all(|piece| expected.contains(piece)));
+        }
+
+        let moves: Vec<_> = board.generate_moves().collect();
+        board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+    }
+}
+
+#[test]
+fn tutorial_lessons() {
+    for (i, lesson) in LESSONS.iter().enumerate() {
+        let board = lesson.board();
+        let (goals, others): (Vec<_>, Vec<_>) = board
+            .generate_moves()
+            .map(|mv| {
+                let mut after = board;
+                after.annotated_apply_move(&mv)
+            })
+            .partition(|mv| lesson.is_goal(mv));
+        assert!(!goals.is_empty(), "Lesson {} can't be done", i);
+        // Moving is the first lesson, so any move will do there
+        if i > 0 {
+            assert!(!others.is_empty(), "Lesson {} can't be gotten wrong", i);
+        }
+    }
+}
+
+#[test]
+fn annotated_moves() {
+    let mut rng = Rng::new(9);
+    let mut chains = 0;
+    for _ in 0..20 {
+        let mut board = Board::new(GameType::Laurentius, 1);
+        while board.outcome() == Outcome::InProgress {
+            let moves: Vec<_> = board.generate_moves().collect();
+            let mv = moves[rng.below(moves.len() as u64) as usize];
+            let before = board;
+            let annotated = board.annotated_apply_move(&mv);
+            let (mover, opp) = (before.turn, before.turn.switch());
+            assert_eq!(mover, mv.mover());
+
+            assert_eq!(0, annotated.pieces_lost(mover).count());
+            assert_eq!(
+                (before.pieces(opp) - board.pieces(opp)) as usize,
+                annotated.pieces_lost(opp).count()
+            );
+            match mv {
+                Move::Move(..) => {
+                    assert_eq!(
+                        board.hexes(mover) - before.hexes(mover),
+                        annotated.tiles_gained
+                    );
+                    assert_eq!(
+                        annotated.removed_hexes.len(),
+                        annotated.tiles_gained as usize
+                    );
+                    assert!(!annotated.is_exchange_chain());
+                }
+                Move::Exchange(..) => {
+                    assert_eq!(0, annotated.tiles_gained);
+                    assert_eq!(
+                        annotated.pieces_lost(opp).count() - 1,
+                        annotated.captured_pieces().count()
+                    );
+                    if annotated.is_exchange_chain() {
+                        chains += 1;
+                    }
+                }
+            }
+            assert!(annotated.to_string().starts_with(&mv.to_string()));
+
+            let announcement = annotated.announcement();
+            assert!(announcement.starts_with(&format!("{:?} ", mover)));
+            assert_eq!(
+                annotated.captured_pieces().count() > 0,
+                announcement.contains("capturing")
+            );
+            assert_eq!(
+                !annotated.removed_hexes.is_empty(),
+                announcement.ends_with("removed")
+            );
+        }
+    }
+    assert!(chains > 0);
+
+    let field = |notation| FieldCoord::from_notation(notation).unwrap();
+    let annotated = MoveAnnotated {
+        mv: Move::move_from_field(field("c3a"), field("c3c")),
+        removed_pieces: vec![field("c2b")],
+        removed_hexes: vec![HexCoord::from_notation("b2").unwrap()],
+        tiles_gained: 1,
+    };
+    assert_eq!(
+        "White moves c3a to c3c, capturing one piece, gaining one tile; tile b2 removed",
+        annotated.announcement()
+    );
+}
+
+#[test]
+fn game_history() {
+    let mut game = Game::new(Rules {
+        time_control: Some(TimeControl::new(
+            Duration::from_secs(60),
+            Duration::from_secs(0),
+        )),
+        ..Rules::default()
+    });
+    assert_eq!(Some(Color::White), game.clock.unwrap().running());
+    assert!(!game.can_undo());
+
+    // Shuffle a piece of each side back and forth until the start position comes up three times
+    let start = game.board;
+    let shuffle = |board: &Board| {
+        board
+            .generate_moves()
+            .find(|&mv| {
+                let mut after = *board;
+                after.apply_move(&mv);
+                after.vitals == board.vitals
+            })
+            .unwrap()
+    };
+    let white = shuffle(&game.board);
+    assert!(game.play(white).is_ok());
+    assert_eq!(Some(Color::Black), game.clock.unwrap().running());
+    let black = shuffle(&game.board);
+    assert!(game.play(black).is_ok());
+    let back = |mv| match mv {
+        Move::Move(from, to, color) => Move::Move(to, from, color),
+        Move::Exchange(..) => unreachable!(),
+    };
+    for ply in 2..8 {
+        let mv = match ply % 4 {
+            0 => white,
+            1 => black,
+            2 => back(white),
+            _ => back(black),
+        };
+        assert_eq!(Outcome::InProgress, game.outcome);
+        let annotated = game.play(mv).unwrap();
+        assert!(annotated.removed_pieces.is_empty());
+    }
+    assert!(game.board == start);
+    assert_eq!(Outcome::DrawThreefoldRepetition, game.outcome);
+    assert_eq!(None, game.clock.unwrap().running());
+    assert_eq!(9, game.board_list().len());
+    assert_eq!(8, game.plies_played());
+    assert_eq!(5, game.move_number());
+    for &color in &[Color::White, Color::Black] {
+        let times = game.think_times(color);
+        assert_eq!(4, times.moves);
+        assert!(times.longest <= times.total);
+        assert!(times.average().unwrap() <= times.longest);
+    }
+
+    // Illegal moves leave the game alone
+    assert!(game.undo());
+    assert_eq!(Outcome::InProgress, game.outcome);
+    let before = game.board;
+    assert_eq!(Err(IllegalMove::NotYourTurn), game.play(white).map(|_| ()));
+    assert!(game.board == before);
+    assert!(game.can_redo());
+
+    // Taking back a move takes back its time, but resigning adds no move
+    assert_eq!(3, game.think_times(Color::Black).moves);
+    game.resign();
+    assert_eq!(Outcome::WinByResignation(Color::White), game.outcome);
+    assert!(!game.can_redo());
+    assert_eq!(3, game.think_times(Color::Black).moves);
+    assert!(game.undo());
+    assert_eq!(Outcome::InProgress, game.outcome);
+    while game.undo() {}
+    assert!(game.board == start);
+    assert!(game.last_move.is_none());
+    assert_eq!(8, game.ply_count);
+    assert_eq!(1, game.move_number());
+    assert_eq!(None, game.think_times(Color::White).average());
+    assert!(game.redo());
+    assert_eq!(Some(white), game.last_move.as_ref().map(|mv| mv.mv));
+}
+
+#[test]
+fn pieces_captured() {
+    let board = board_from_notation(&["c3c", "c3e"], &["c3b", "c4b"], (0, 0), Color::White, 2);
+    let mut game = Game::from_board(Rules::default(), board);
+    assert_eq!(0, game.pieces_captured(Color::White));
+    let capture = Move::move_from_field(
+        FieldCoord::from_notation("c3e").unwrap(),
+        FieldCoord::from_notation("c3a").unwrap(),
+    );
+    assert!(game.play(capture).is_ok());
+    assert_eq!(1, game.pieces_captured(Color::White));
+    assert_eq!(0, game.pieces_captured(Color::Black));
+
+    assert!(game.undo());
+    assert_eq!(0, game.pieces_captured(Color::White));
+}
+
+#[test]
+fn game_positions() {
+    let board = board_from_notation(&["c3c", "c3e"], &["c3b", "c4b"], (0, 0), Color::White, 2);
+    let mut game = Game::from_board(Rules::default(), board);
+    assert_eq!(1, game.positions().len());
+    let capture = Move::move_from_field(
+        FieldCoord::from_notation("c3e").unwrap(),
+        FieldCoord::from_notation("c3a").unwrap(),
+    );
+    assert!(game.play(capture).is_ok());
+    // Resigning doesn't change the board, so it isn't a position of its own
+    game.resign();
+    let positions = game.positions();
+    assert_eq!(2, positions.len());
+    assert!(board == positions[0].0);
+    assert!(game.board == positions[1].0);
+    assert_eq!(Some(capture), positions[1].1.map(|mv| mv.mv));
+}
+
+#[test]
+fn flag_fall() {
+    let mut game = Game::new(Rules {
+        time_control: Some(TimeControl::new(
+            Duration::from_millis(200),
+            Duration::from_secs(0),
+        )),
+        ..Rules::default()
+    });
+    assert!(!game.check_flag());
+    thread::sleep(Duration::from_millis(250));
+    assert!(game.check_flag());
+    assert_eq!(Outcome::WinOnTime(Color::Black), game.outcome);
+    assert_eq!(None, game.clock.unwrap().running());
+    assert!(!game.check_flag());
+
+    // Taking back the flag fall doesn't give back the time
+    assert!(game.undo());
+    assert_eq!(Outcome::InProgress, game.outcome);
+    assert!(game.check_flag());
+}
+
+#[test]
+fn undo_restores_clock() {
+    let mut game = Game::new(Rules {
+        time_control: Some(TimeControl::new(
+            Duration::from_secs(10),
+            Duration::from_secs(2),
+        )),
+        ..Rules::default()
+    });
+    thread::sleep(Duration::from_millis(100));
+    let mv = game.board.generate_moves().next().unwrap();
+    game.play(mv).unwrap();
+    let after_move = game.clock.unwrap().remaining(Color::White);
+    assert!(after_move < Duration::from_millis(11_950));
+    thread::sleep(Duration::from_millis(100));
+
+    // White gets back the time spent on the move, and loses the increment again
+    assert!(game.undo());
+    let clock = game.clock.unwrap();
+    assert_eq!(Some(Color::White), clock.running());
+    assert!(clock.remaining(Color::White) > Duration::from_millis(9_950));
+    assert!(clock.remaining(Color::White) <= Duration::from_secs(10));
+    assert_eq!(Duration::from_secs(10), clock.remaining(Color::Black));
+
+    // Playing the move again gives back the clocks as they were after it, not charging Black for
+    // the time spent looking at the earlier position
+    thread::sleep(Duration::from_millis(100));
+    assert!(game.redo());
+    let clock = game.clock.unwrap();
+    assert_eq!(after_move, clock.remaining(Color::White));
+    assert!(clock.remaining(Color::Black) > Duration::from_millis(9_950));
+}
+
+#[test]
+fn clock_delay() {
+    let mut clock = Clock::new(
+        TimeControl::new(Duration::from_secs(10), Duration::from_secs(0))
+            .with_delay(Duration::from_millis(200)),
+    );
+    clock.start(Color::White);
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(Duration::from_secs(10), clock.remaining(Color::White));
+    clock.press();
+    // The unused part of the delay isn't kept
+    assert_eq!(Duration::from_secs(10), clock.remaining(Color::White));
+
+    clock.start(Color::Black);
+    thread::sleep(Duration::from_millis(300));
+    let remaining = clock.remaining(Color::Black);
+    assert!(remaining < Duration::from_millis(9_900));
+    assert!(remaining > Duration::from_secs(9));
+
+    // The delay is waited out before the time starts falling
+    clock.start(Color::White);
+    let until = clock.time_until(Duration::from_secs(9)).unwrap();
+    assert!(until > Duration::from_millis(1_100));
+    assert!(until <= Duration::from_millis(1_200));
+    clock.stop();
+    assert_eq!(None, clock.time_until(Duration::from_secs(9)));
+}
+
+#[test]
+fn repetition_history() {
+    let board = Board::new(GameType::Laurentius, 2);
+    let mut captured = board.vitals;
+    captured.black.pieces -= 1;
+    captured.white.hexes += 1;
+
+    // The position came up twice, but only 7 positions back
+    let mut history = RepetitionHistory::new();
+    assert!(!history.is_repetition(&board));
+    for hash in &[board.zobrist, 1, 2, 3, board.zobrist, 4, 5] {
+        history.push(*hash, board.vitals);
+    }
+    assert!(!history.is_repetition(&board));
+    history.push(6, board.vitals);
+    assert!(history.is_repetition(&board));
+    history.pop();
+    assert!(!history.is_repetition(&board));
+    assert_eq!(7, history.len());
+
+    // Positions before an irreversible move don't count
+    let mut history = RepetitionHistory::new();
+    history.push(board.zobrist, captured);
+    history.push(board.zobrist, captured);
+    for hash in 1..9 {
+        history.push(hash, board.vitals);
+    }
+    assert!(!history.is_repetition(&board));
+    history.push(board.zobrist, board.vitals);
+    history.push(board.zobrist, board.vitals);
+    assert!(history.is_repetition(&board));
+
+    // Nor does anything if the last move was irreversible
+    history.push(9, captured);
+    assert!(!history.is_repetition(&board));
+    history.pop();
+    assert!(history.is_repetition(&board));
+
+    let boards = vec![board; 9];
+    assert!(RepetitionHistory::from_boards(&boards).is_repetition(&board));
+    assert!(!RepetitionHistory::from_boards(&boards[..2]).is_repetition(&board));
+}
+
+#[test]
+fn dead_draw_corpus() {
+    use self::Outcome::*;
+    let draw = DrawInsufficientMaterial;
+    const TWO_HEXES: &[&str] = &["c3a", "c3c", "c3e", "d3a", "d3c", "d3e"];
+
+    // Each position is made of the hexes under its pieces: the pieces of each player, the tiles
+    // they hold, the player to move, the exchange cost and the outcome, checked by hand
+    type Position = (
+        &'static [&'static str],
+        &'static [&'static str],
+        (u8, u8),
+        Color,
+        u8,
+        Outcome,
+    );
+    #[rustfmt::skip]
+    let corpus: &[Position] = &[
+        // Lone pieces on neighboring hexes: one tile is left to collect
+        (&["c3e"], &["d3b"], (0, 0), Color::White, 2, draw),
+        (&["c3e"], &["d3b"], (0, 0), Color::White, 1, InProgress),
+        (&["c3e"], &["d3b"], (1, 0), Color::White, 2, InProgress),
+        (&["c3e"], &["d3b"], (0, 1), Color::White, 2, InProgress),
+        (&["c3e"], &["d3b"], (1, 0), Color::White, 3, draw),
+        (&["c3e"], &["d3b"], (2, 0), Color::White, 3, InProgress),
+        // Lone pieces on the last hex: only tiles already held count
+        (&["c3e"], &["c3f"], (1, 0), Color::White, 1, InProgress),
+        (&["c3e"], &["c3f"], (1, 1), Color::Black, 2, draw),
+        // Two pieces can surround one
+        (&["c3a", "c3e"], &["d3b"], (0, 0), Color::White, 2, InProgress),
+        (&["c3e"], &["d3b", "d3d"], (0, 0), Color::White, 3, InProgress),
+        // White fills the last hex and can't move
+        (&["c3a", "c3c", "c3e"], &["c3f"], (0, 0), Color::White, 2, DrawStalemate),
+        (&["c3a", "c3c", "c3e"], &["c3f"], (2, 0), Color::White, 2, InProgress),
+        // White fills both of the last hexes, while Black still has room to move
+        (TWO_HEXES, &["d3b"], (0, 0), Color::White, 2, DrawStalemate),
+        (TWO_HEXES, &["d3b"], (1, 0), Color::White, 1, InProgress),
+        (TWO_HEXES, &["d3b"], (0, 0), Color::Black, 2, InProgress),
+    ];
+    for &(white, black, tiles, turn, hexes_to_exchange, outcome) in corpus {
+        let board = board_from_notation(white, black, tiles, turn, hexes_to_exchange);
+        assert_eq!(
+            outcome,
+            board.outcome(),
+            "{:?} vs {:?}, tiles {:?}, {:?} to move, {} to exchange",
+            white,
+            black,
+            tiles,
+            turn,
+            hexes_to_exchange
+        );
+        if outcome == DrawStalemate {
+            assert_eq!(0, board.generate_moves().count());
+        }
+    }
+}
+
+/// Sets up a Laurentius board with the given pieces on the hexes under them.
+fn board_from_notation(
+    white: &[&str],
+    black: &[&str],
+    tiles: (u8, u8),
+    turn: Color,
+    hexes_to_exchange: u8,
+) -> Board {
+    let fields = |notations: &[&str]| {
+        notations
+            .iter()
+            .map(|notation| FieldCoord::from_notation(notation).unwrap().to_bitboard())
+            .fold(0, |fields, field| fields | field)
+    };
+    let fields = ColorMap::new(fields(white), fields(black));
+    let hexes = (fields.white | fields.black)
+        .iter()
+        .map(|field| 0b111 << (field.to_index() * 3))
+        .fold(0, |hexes, hex| hexes | hex);
+    let vitals = |pieces: &[&str], hexes| PlayerVitals {
+        pieces: pieces.len() as u8,
+        hexes,
+    };
+    Board::from_setup(
+        fields,
+        hexes,
+        ColorMap::new(vitals(white, tiles.0), vitals(black, tiles.1)),
+        turn,
+        GameType::Laurentius,
+        hexes_to_exchange,
+    )
+    .unwrap()
+}
+
+#[test]
+fn dead_draws() {
+    assert!(!Board::new(GameType::Laurentius, 2).is_dead_draw());
+    assert!(!Board::new(GameType::Ocius, 1).is_dead_draw());
+
+    // Lone pieces that can never exchange
+    let board = board_from_notation(&["c3e"], &["d3b"], (0, 0), Color::White, 2);
+    assert_eq!(Outcome::DrawInsufficientMaterial, board.outcome());
+    assert!(board.is_dead_draw());
+    let board = board_from_notation(&["c3e"], &["d3b"], (0, 0), Color::White, 1);
+    assert!(!board.is_dead_draw());
+
+    // Two pieces can surround a lone piece
+    let board = board_from_notation(&["c3a", "c3e"], &["d3b"], (0, 0), Color::White, 2);
+    assert!(!board.is_dead_draw());
+
+    // White fills the last hex and can't move
+    let board = board_from_notation(&["c3a", "c3c", "c3e"], &["c3f"], (0, 0), Color::White, 2);
+    assert_eq!(Outcome::DrawStalemate, board.outcome());
+    assert!(board.is_dead_draw());
+
+    // The ply limit neither makes a position dead nor hides a dead one
+    let mut board = Board::new(GameType::Laurentius, 2);
+    board.draw_after_plies = Some(0);
+    assert_eq!(Outcome::DrawNoProgress, board.outcome());
+    assert!(!board.is_dead_draw());
+    let mut board = board_from_notation(&["c3e"], &["d3b"], (0, 0), Color::White, 2);
+    board.draw_after_plies = Some(0);
+    assert!(board.is_dead_draw());
+}
+
+#[test]
+fn threat_detection() {
+    let board = Board::new(GameType::Laurentius, 2);
+    assert_eq!(0, board.pieces_en_prise(Color::White));
+    assert_eq!(0, board.pieces_en_prise(Color::Black));
+    assert_eq!(0, board.threatened_pieces(Color::White));
+    assert_eq!(0, board.threatened_pieces(Color::Black));
+
+    let mut rng = Rng::new(5);
+    let mut board = Board::new(GameType::Laurentius, 2);
+    while board.outcome() == Outcome::InProgress {
+        let them = board.turn.switch();
+        let en_prise = board.pieces_en_prise(them);
+        let falling_tiles = board.tiles_about_to_fall();
+
+        let mut capturable = 0;
+        let mut threatened = 0;
+        let moves: Vec<_> = board.generate_moves().collect();
+        for mv in &moves {
+            let mut after = board;
+            let annotated = after.annotated_apply_move(mv);
+            // Captures don't include the piece taken by an exchange
+            threatened |= annotated
+                .captured_pieces()
+                .fold(0, |threatened, piece| threatened | piece.to_bitboard());
+
+            if let Move::Move(from, to, color) = *mv {
+                let hex = FieldCoord::from_bitboard(from, color).to_hex();
+                let hex_mask = 0b111 << (hex.to_index() * 3);
+                let vacates_hex = board.fields(color) & hex_mask == from
+                    && to & hex_mask == 0
+                    && board.fields(color.switch()) & hex_mask == 0;
+
+                let captured = annotated
+                    .removed_pieces
+                    .iter()
+                    .filter(|piece| piece.color() == them)
+                    .fold(0, |captured, piece| captured | piece.to_bitboard());
+                capturable |= captured;
+
+                // Without any tiles removed, a piece can only be captured by surrounding it
+                if annotated.removed_hexes.is_empty() {
+                    assert_eq!(captured, captured & en_prise);
+                }
+                assert_eq!(
+                    vacates_hex && falling_tiles & from != 0,
+                    annotated.removed_hexes.contains(&hex)
+                );
+            }
+        }
+        assert_eq!(en_prise, en_prise & capturable);
+        assert_eq!(threatened, board.threatened_pieces(them));
+        assert_eq!(en_prise, en_prise & threatened);
+
+        board.apply_move(&moves[rng.below(moves.len() as u64) as usize]);
+    }
+}
+
+#[test]
+fn board_display() {
+    let ocius = "        /...\\\n\
+                 \x20   /..W\\B.B/W..\\\n\
+                 \x20   \\.W./.B.\\.W./\n\
+                 \x20   /.B.\\.W./.B.\\\n\
+                 \x20   \\..B/W.W\\B../\n\
+                 \x20       \\.../\n\
+                 \x20 a   b   c   d   e\n\
+                 White: 7 pieces, 0 tiles\n\
+                 Black: 7 pieces, 0 tiles\n\
+                 White to move";
+    assert_eq!(ocius, Board::new(GameType::Ocius, 2).to_string());
+
+    let board = Board::new(GameType::Laurentius, 2);
+    let text = board.to_string();
+    let grid = &text[..text.find("  a").unwrap()];
+    assert_eq!(18, grid.matches('W').count());
+    assert_eq!(18, grid.matches('B').count());
+    assert_eq!(18, format!("{:#}", board).matches('\u{25cb}').count());
+
+    // Only the hex with the pieces on it is drawn
+    let board = board_from_notation(&["c3e"], &["c3b"], (5, 7), Color::Black, 2);
+    assert_eq!(
+        "        /..W\\\n\
+         \x20       \\B../\n\
+         \x20 a   b   c   d   e\n\
+         White: 1 piece, 5 tiles\n\
+         Black: 1 piece, 7 tiles\n\
+         Black to move",
+        board.to_string()
+    );
+}
+
+#[test]
+fn log_levels() {
+    let log = Log::new();
+    log.error("error");
+    log.warn("warning");
+    log.info("info");
+    log.debug("debug");
+    let messages = |level| -> Vec<String> {
+        log.entries(level)
+            .into_iter()
+            .map(|entry| entry.message)
+            .collect()
+    };
+    assert_eq!(vec!["error"], messages(Level::Error));
+    assert_eq!(vec!["error", "warning", "info"], messages(Level::Info));
+    assert_eq!(4, messages(Level::Debug).len());
+
+    // Clones share their messages
+    log.clone().clear();
+    assert!(log.entries(Level::Debug).is_empty());
+
+    // The oldest messages are dropped once the log is full
+    for i in 0..2000 {
+        log.debug(i.to_string());
+    }
+    let entries = log.entries(Level::Debug);
+    assert!(entries.len() < 2000);
+    assert_eq!("1999", entries.last().unwrap().message);
+}
+
+#[test]
+fn handicap() {
+    let rules = Rules {
+        handicap: Some(Handicap {
+            color: Color::Black,
+            pieces: 3,
+        }),
+        ..Rules::default()
+    };
+    let game = Game::new(rules);
+    assert_eq!(18, game.board.pieces(Color::White));
+    assert_eq!(15, game.board.pieces(Color::Black));
+    assert_eq!(Outcome::InProgress, game.outcome);
+    assert!(game.board == Game::new(rules).board);
+
+    // No hex is left empty that had a piece on it, on any start
+    let occupied_hexes = |board: &Board| {
+        board
+            .hexes_iter()
+            .filter(|&hex| (0..6).any(|i| board.is_piece_on_field(hex.to_field(i))))
+            .count()
+    };
+    for &game_type in &[GameType::Laurentius, GameType::Ocius, GameType::Random(7)] {
+        let board = Board::new(game_type, 2);
+        let handicapped = board.with_handicap(Color::White, 3);
+        assert_eq!(
+            board.pieces(Color::White) - 3,
+            handicapped.pieces(Color::White)
+        );
+        assert_eq!(occupied_hexes(&board), occupied_hexes(&handicapped));
+    }
+}
+
+#[test]
+fn players() {
+    let mut human = new_player(PlayerKind::Human);
+    assert_eq!(PlayerKind::Human, human.kind());
+    assert!(!human.is_idle());
+    assert!(human.try_recv().is_none());
+    assert!(human.search_info().is_none());
+
+    let mut computer = new_player(PlayerKind::Computer);
+    assert_eq!(PlayerKind::Computer, computer.kind());
+    assert!(computer.is_idle());
+    let board = Board::new(GameType::Laurentius, 2);
+    computer.think(MoveRequest {
+        board,
+        board_list: vec![board],
+        settings: SearchSettings {
+            depth: 1,
+            constraint: Constraint::None,
+            time_limit: None,
+            node_limit: None,
+            eval_params: EvalParams::default(),
+            strength: Strength::Full,
+            book: None,
+            engine: EngineKind::AlphaBeta,
+            network: None,
+            log: None,
+        },
+        waker: Waker::none(),
+        delay: false,
+        ply_count: 0,
+    });
+    assert!(!computer.is_idle());
+
+    let mut ai_move = None;
+    for _ in 0..500 {
+        ai_move = computer.try_recv();
+        if ai_move.is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let mv = ai_move.expect("The computer didn't move").mv;
+    assert!(board.generate_moves().any(|legal| legal == mv));
+    assert!(computer.is_idle());
+    assert_eq!(
+        Some(board.turn),
+        computer.search_info().and_then(|info| info.color)
+    );
+}
@@ -0,0 +1,743 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Persistent settings, stored as `key = value` lines. Files are written to a temporary file and
+//! renamed into place so that an interrupted write never leaves a truncated config behind.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub use coerceo_core::config::{dir, parse_values, save_tablebase, tablebase_path, write_atomic};
+
+use coerceo_core::config::no_dir_error;
+
+use crate::ai::{Book, Constraint, EngineKind, EvalParams, Network, Strength};
+use crate::model::{
+    BoardBackground, BoardSize, Color, ColorMap, ColorTheme, Handicap, HighlightPalette, Model,
+    PieceOutline, PieceStyle, ReplaySpeed, TimeControl, UndoPolicy, EXCHANGE_COSTS, MAX_HANDICAP,
+    SEARCH_DEPTHS, UI_SCALES,
+};
+
+/// The current config schema version. Bump this and add a step to `migrate` whenever a key is
+/// renamed or its meaning changes.
+pub const VERSION: u32 = 2;
+
+const FILE_NAME: &str = "coerceo.cfg";
+const EVAL_PARAMS_FILE_NAME: &str = "eval_params.cfg";
+const BOOK_FILE_NAME: &str = "book.txt";
+const NETWORK_FILE_NAME: &str = "network.txt";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub hexes_to_exchange: u8,
+    pub draw_after_plies: Option<u16>,
+    pub handicap: Option<Handicap>,
+    pub undo_policy: UndoPolicy,
+    pub time_control: Option<TimeControl>,
+    pub ai_search_depth: u8,
+    pub ai_strength: Strength,
+    pub ai_node_limit: Option<u64>,
+    pub ai_engine: EngineKind,
+    pub explain_ai_moves: bool,
+    pub warn_blunders: bool,
+    pub ai_constraint: Constraint,
+    pub use_opening_book: bool,
+    pub use_network_eval: bool,
+    pub reduce_motion: bool,
+    pub ui_scale: i32,
+    pub board_size: BoardSize,
+    pub show_threats: bool,
+    pub beginner_mode: bool,
+    pub show_eval_bar: bool,
+    pub flip_board: bool,
+    pub auto_rotate: bool,
+    pub theme: ColorTheme,
+    pub board_background: BoardBackground,
+    pub highlight_palette: HighlightPalette,
+    pub white_piece_color: Option<[u8; 3]>,
+    pub black_piece_color: Option<[u8; 3]>,
+    pub piece_style: PieceStyle,
+    pub piece_outline: PieceOutline,
+    pub announce_moves: bool,
+    pub replay_speed: ReplaySpeed,
+    pub pause_replay_on_captures: bool,
+    /// Whether the file was written by a newer version of the game. Its settings can't be read, so
+    /// it is never saved over, and going back to the newer version finds them as they were.
+    pub newer_version: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            hexes_to_exchange: 2,
+            draw_after_plies: None,
+            handicap: None,
+            undo_policy: UndoPolicy::Free,
+            time_control: None,
+            ai_search_depth: 6,
+            ai_strength: Strength::Full,
+            ai_node_limit: None,
+            ai_engine: EngineKind::AlphaBeta,
+            explain_ai_moves: false,
+            warn_blunders: false,
+            ai_constraint: Constraint::None,
+            use_opening_book: true,
+            use_network_eval: true,
+            reduce_motion: false,
+            ui_scale: 100,
+            board_size: BoardSize::Fit,
+            show_threats: false,
+            beginner_mode: false,
+            show_eval_bar: true,
+            flip_board: false,
+            auto_rotate: false,
+            theme: ColorTheme::Light,
+            board_background: BoardBackground::None,
+            highlight_palette: HighlightPalette::Standard,
+            white_piece_color: None,
+            black_piece_color: None,
+            piece_style: PieceStyle::Shaded,
+            piece_outline: PieceOutline::Normal,
+            announce_moves: false,
+            replay_speed: ReplaySpeed::Normal,
+            pause_replay_on_captures: false,
+            newer_version: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_model(model: &Model) -> Self {
+        Self {
+            hexes_to_exchange: *model.hexes_to_exchange.borrow(),
+            draw_after_plies: *model.draw_after_plies.borrow(),
+            handicap: *model.handicap.borrow(),
+            undo_policy: *model.undo_policy.borrow(),
+            time_control: *model.time_control.borrow(),
+            ai_search_depth: *model.ai_search_depth.borrow(),
+            ai_strength: *model.ai_strength.borrow(),
+            ai_node_limit: *model.ai_node_limit.borrow(),
+            ai_engine: *model.ai_engine.borrow(),
+            explain_ai_moves: *model.explain_ai_moves.borrow(),
+            warn_blunders: *model.warn_blunders.borrow(),
+            ai_constraint: *model.ai_constraint.borrow(),
+            use_opening_book: *model.use_book.borrow(),
+            use_network_eval: *model.use_network.borrow(),
+            reduce_motion: *model.reduce_motion.borrow(),
+            ui_scale: *model.ui_scale.borrow(),
+            board_size: *model.board_size.borrow(),
+            show_threats: *model.show_threats.borrow(),
+            beginner_mode: *model.beginner_mode.borrow(),
+            show_eval_bar: *model.show_eval_bar.borrow(),
+            flip_board: *model.flip_board.borrow(),
+            auto_rotate: *model.auto_rotate.borrow(),
+            theme: *model.theme.borrow(),
+            board_background: *model.board_background.borrow(),
+            highlight_palette: *model.highlight_palette.borrow(),
+            white_piece_color: model.piece_colors.borrow().white,
+            black_piece_color: model.piece_colors.borrow().black,
+            piece_style: *model.piece_style.borrow(),
+            piece_outline: *model.piece_outline.borrow(),
+            announce_moves: *model.announce_moves.borrow(),
+            replay_speed: *model.replay_speed.borrow(),
+            pause_replay_on_captures: *model.pause_replay_on_captures.borrow(),
+            newer_version: false,
+        }
+    }
+    pub fn apply(&self, model: &Model) {
+        *model.hexes_to_exchange.borrow_mut() = self.hexes_to_exchange;
+        *model.draw_after_plies.borrow_mut() = self.draw_after_plies;
+        *model.handicap.borrow_mut() = self.handicap;
+        *model.undo_policy.borrow_mut() = self.undo_policy;
+        *model.time_control.borrow_mut() = self.time_control;
+        *model.ai_search_depth.borrow_mut() = self.ai_search_depth;
+        *model.ai_strength.borrow_mut() = self.ai_strength;
+        *model.ai_node_limit.borrow_mut() = self.ai_node_limit;
+        *model.ai_engine.borrow_mut() = self.ai_engine;
+        *model.explain_ai_moves.borrow_mut() = self.explain_ai_moves;
+        *model.warn_blunders.borrow_mut() = self.warn_blunders;
+        *model.ai_constraint.borrow_mut() = self.ai_constraint;
+        *model.use_book.borrow_mut() = self.use_opening_book;
+        *model.use_network.borrow_mut() = self.use_network_eval;
+        *model.reduce_motion.borrow_mut() = self.reduce_motion;
+        *model.ui_scale.borrow_mut() = self.ui_scale;
+        *model.board_size.borrow_mut() = self.board_size;
+        *model.show_threats.borrow_mut() = self.show_threats;
+        *model.beginner_mode.borrow_mut() = self.beginner_mode;
+        *model.show_eval_bar.borrow_mut() = self.show_eval_bar;
+        *model.flip_board.borrow_mut() = self.flip_board;
+        *model.auto_rotate.borrow_mut() = self.auto_rotate;
+        *model.theme.borrow_mut() = self.theme;
+        *model.board_background.borrow_mut() = self.board_background;
+        *model.highlight_palette.borrow_mut() = self.highlight_palette;
+        *model.piece_colors.borrow_mut() =
+            ColorMap::new(self.white_piece_color, self.black_piece_color);
+        *model.piece_style.borrow_mut() = self.piece_style;
+        *model.piece_outline.borrow_mut() = self.piece_outline;
+        *model.announce_moves.borrow_mut() = self.announce_moves;
+        *model.replay_speed.borrow_mut() = self.replay_speed;
+        *model.pause_replay_on_captures.borrow_mut() = self.pause_replay_on_captures;
+    }
+    /// Loads the config file, falling back to the defaults if it is missing or unreadable.
+    pub fn load() -> Self {
+        path().map_or_else(Self::default, |path| Self::load_from(&path))
+    }
+    /// Loads a config file from `path`, falling back to the defaults if it is missing or
+    /// unreadable.
+    pub fn load_from(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or_default()
+    }
+    pub fn save(&self) -> io::Result<()> {
+        match path() {
+            Some(path) => self.save_to(&path),
+            None => Ok(()),
+        }
+    }
+    /// Writes the config to `path`, unless it was loaded from a file written by a newer version of
+    /// the game.
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        if self.newer_version {
+            return Ok(());
+        }
+        write_atomic(path, self.to_string().as_bytes())
+    }
+    /// Parses a config file. Missing or invalid values keep their defaults, and files from a newer
+    /// version of the game are ignored entirely, other than being marked as newer.
+    pub fn parse(contents: &str) -> Self {
+        let mut config = Self::default();
+        let mut values = parse_values(contents);
+
+        let version = match values.get("version").and_then(|v| v.parse().ok()) {
+            Some(version) if version > VERSION => {
+                config.newer_version = true;
+                return config;
+            }
+            Some(version) => version,
+            None => return config,
+        };
+        migrate(version, &mut values);
+
+        let get = |key: &str| values.get(key).map(String::as_str);
+        if let Some(v) = get("hexes_to_exchange").and_then(parse_hexes_to_exchange) {
+            config.hexes_to_exchange = v;
+        }
+        if let Some(v) = get("draw_after_plies").and_then(parse_draw_after_plies) {
+            config.draw_after_plies = v;
+        }
+        if let Some(v) = get("handicap").and_then(parse_handicap) {
+            config.handicap = v;
+        }
+        if let Some(v) = get("undo_policy").and_then(parse_undo_policy) {
+            config.undo_policy = v;
+        }
+        if let Some(v) = get("time_control").and_then(parse_time_control) {
+            config.time_control = v;
+        }
+        if let Some(v) = get("ai_search_depth").and_then(parse_search_depth) {
+            config.ai_search_depth = v;
+        }
+        if let Some(v) = get("ai_strength").and_then(parse_strength) {
+            config.ai_strength = v;
+        }
+        if let Some(v) = get("ai_node_limit").and_then(parse_node_limit) {
+            config.ai_node_limit = v;
+        }
+        if let Some(v) = get("ai_engine").and_then(parse_engine) {
+            config.ai_engine = v;
+        }
+        if let Some(v) = get("explain_ai_moves").and_then(parse_bool) {
+            config.explain_ai_moves = v;
+        }
+        if let Some(v) = get("warn_blunders").and_then(parse_bool) {
+            config.warn_blunders = v;
+        }
+        if let Some(v) = get("ai_constraint").and_then(parse_constraint) {
+            config.ai_constraint = v;
+        }
+        if let Some(v) = get("use_opening_book").and_then(parse_bool) {
+            config.use_opening_book = v;
+        }
+        if let Some(v) = get("use_network_eval").and_then(parse_bool) {
+            config.use_network_eval = v;
+        }
+        if let Some(v) = get("reduce_motion").and_then(parse_bool) {
+            config.reduce_motion = v;
+        }
+        if let Some(v) = get("ui_scale").and_then(parse_ui_scale) {
+            config.ui_scale = v;
+        }
+        if let Some(v) = get("board_size").and_then(parse_board_size) {
+            config.board_size = v;
+        }
+        if let Some(v) = get("show_threats").and_then(parse_bool) {
+            config.show_threats = v;
+        }
+        if let Some(v) = get("beginner_mode").and_then(parse_bool) {
+            config.beginner_mode = v;
+        }
+        if let Some(v) = get("show_eval_bar").and_then(parse_bool) {
+            config.show_eval_bar = v;
+        }
+        if let Some(v) = get("flip_board").and_then(parse_bool) {
+            config.flip_board = v;
+        }
+        if let Some(v) = get("auto_rotate").and_then(parse_bool) {
+            config.auto_rotate = v;
+        }
+        if let Some(v) = get("theme").and_then(parse_theme) {
+            config.theme = v;
+        }
+        if let Some(v) = get("board_background").and_then(parse_board_background) {
+            config.board_background = v;
+        }
+        if let Some(v) = get("highlight_palette").and_then(parse_highlight_palette) {
+            config.highlight_palette = v;
+        }
+        if let Some(v) = get("white_piece_color").and_then(parse_piece_color) {
+            config.white_piece_color = v;
+        }
+        if let Some(v) = get("black_piece_color").and_then(parse_piece_color) {
+            config.black_piece_color = v;
+        }
+        if let Some(v) = get("piece_style").and_then(parse_piece_style) {
+            config.piece_style = v;
+        }
+        if let Some(v) = get("piece_outline").and_then(parse_piece_outline) {
+            config.piece_outline = v;
+        }
+        if let Some(v) = get("announce_moves").and_then(parse_bool) {
+            config.announce_moves = v;
+        }
+        if let Some(v) = get("replay_speed").and_then(parse_replay_speed) {
+            config.replay_speed = v;
+        }
+        if let Some(v) = get("pause_replay_on_captures").and_then(parse_bool) {
+            config.pause_replay_on_captures = v;
+        }
+        config
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let theme = match self.theme {
+            ColorTheme::Light => "light",
+            ColorTheme::Dark => "dark",
+        };
+        let board_background = match self.board_background {
+            BoardBackground::None => "none",
+            BoardBackground::Plain => "plain",
+            BoardBackground::Textured => "textured",
+        };
+        let piece_style = match self.piece_style {
+            PieceStyle::Shaded => "shaded",
+            PieceStyle::Flat => "flat",
+            PieceStyle::HighVisibility => "high_visibility",
+        };
+        let piece_outline = match self.piece_outline {
+            PieceOutline::Thin => "thin",
+            PieceOutline::Normal => "normal",
+            PieceOutline::Thick => "thick",
+        };
+        let highlight_palette = match self.highlight_palette {
+            HighlightPalette::Standard => "standard",
+            HighlightPalette::Deuteranopia => "deuteranopia",
+            HighlightPalette::Protanopia => "protanopia",
+        };
+        let board_size = match self.board_size {
+            BoardSize::Fit => "fit",
+            BoardSize::Small => "small",
+            BoardSize::Medium => "medium",
+            BoardSize::Large => "large",
+        };
+        let replay_speed = match self.replay_speed {
+            ReplaySpeed::Slow => "slow",
+            ReplaySpeed::Normal => "normal",
+            ReplaySpeed::Fast => "fast",
+        };
+        let undo_policy = match self.undo_policy {
+            UndoPolicy::Free => "free",
+            UndoPolicy::ApprovalRequired => "approval_required",
+            UndoPolicy::Disabled => "disabled",
+        };
+        let time_control = match self.time_control {
+            Some(tc) if tc.delay > Duration::from_secs(0) => format!(
+                "{}+{}d{}",
+                tc.time.as_secs(),
+                tc.increment.as_secs(),
+                tc.delay.as_secs()
+            ),
+            Some(tc) => format!("{}+{}", tc.time.as_secs(), tc.increment.as_secs()),
+            None => String::from("none"),
+        };
+        let ai_strength = match self.ai_strength {
+            Strength::Beginner => "beginner",
+            Strength::Novice => "novice",
+            Strength::Intermediate => "intermediate",
+            Strength::Full => "full",
+        };
+        let draw_after_plies = match self.draw_after_plies {
+            Some(limit) => limit.to_string(),
+            None => String::from("none"),
+        };
+        let handicap = match self.handicap {
+            Some(handicap) => format!(
+                "{} {}",
+                format!("{:?}", handicap.color).to_lowercase(),
+                handicap.pieces
+            ),
+            None => String::from("none"),
+        };
+        let ai_node_limit = match self.ai_node_limit {
+            Some(limit) => limit.to_string(),
+            None => String::from("none"),
+        };
+        let ai_engine = match self.ai_engine {
+            EngineKind::AlphaBeta => "alpha_beta",
+            EngineKind::Mcts => "mcts",
+        };
+        let ai_constraint = match self.ai_constraint {
+            Constraint::None => "none",
+            Constraint::NoExchanges => "no_exchanges",
+            Constraint::NoCaptures => "no_captures",
+        };
+
+        write!(
+            f,
+            "version = {}\n\
+             hexes_to_exchange = {}\n\
+             draw_after_plies = {}\n\
+             handicap = {}\n\
+             undo_policy = {}\n\
+             time_control = {}\n\
+             ai_search_depth = {}\n\
+             ai_strength = {}\n\
+             ai_node_limit = {}\n\
+             ai_engine = {}\n\
+             explain_ai_moves = {}\n\
+             warn_blunders = {}\n\
+             ai_constraint = {}\n\
+             use_opening_book = {}\n\
+             use_network_eval = {}\n\
+             reduce_motion = {}\n\
+             ui_scale = {}\n\
+             board_size = {}\n\
+             show_threats = {}\n\
+             beginner_mode = {}\n\
+             show_eval_bar = {}\n\
+             flip_board = {}\n\
+             auto_rotate = {}\n\
+             theme = {}\n\
+             board_background = {}\n\
+             highlight_palette = {}\n\
+             white_piece_color = {}\n\
+             black_piece_color = {}\n\
+             piece_style = {}\n\
+             piece_outline = {}\n\
+             announce_moves = {}\n\
+             replay_speed = {}\n\
+             pause_replay_on_captures = {}\n",
+            VERSION,
+            self.hexes_to_exchange,
+            draw_after_plies,
+            handicap,
+            undo_policy,
+            time_control,
+            self.ai_search_depth,
+            ai_strength,
+            ai_node_limit,
+            ai_engine,
+            self.explain_ai_moves,
+            self.warn_blunders,
+            ai_constraint,
+            self.use_opening_book,
+            self.use_network_eval,
+            self.reduce_motion,
+            self.ui_scale,
+            board_size,
+            self.show_threats,
+            self.beginner_mode,
+            self.show_eval_bar,
+            self.flip_board,
+            self.auto_rotate,
+            theme,
+            board_background,
+            highlight_palette,
+            format_piece_color(self.white_piece_color),
+            format_piece_color(self.black_piece_color),
+            piece_style,
+            piece_outline,
+            self.announce_moves,
+            replay_speed,
+            self.pause_replay_on_captures,
+        )
+    }
+}
+
+/// Upgrades the values of a config file written with an older schema version.
+fn migrate(version: u32, values: &mut HashMap<String, String>) {
+    // Each step upgrades the values from the version before it, in order
+    debug_assert!(version <= VERSION);
+    if version < 2 {
+        // The exchange cost was a switch between one and two tiles
+        if let Some(one) = values.remove("exchange_one_hex") {
+            let count = if parse_bool(&one) == Some(true) { 1 } else { 2 };
+            values.insert(String::from("hexes_to_exchange"), count.to_string());
+        }
+    }
+    values.insert(String::from("version"), VERSION.to_string());
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    value.parse().ok()
+}
+
+fn parse_hexes_to_exchange(value: &str) -> Option<u8> {
+    value
+        .parse()
+        .ok()
+        .filter(|count| EXCHANGE_COSTS.contains(count))
+}
+
+fn parse_draw_after_plies(value: &str) -> Option<Option<u16>> {
+    match value {
+        "none" => Some(None),
+        _ => value.parse().ok().filter(|&limit| limit > 0).map(Some),
+    }
+}
+
+/// Parses a handicap written as the color giving it and a number of pieces, like `black 2`.
+fn parse_handicap(value: &str) -> Option<Option<Handicap>> {
+    if value == "none" {
+        return Some(None);
+    }
+    let mut parts = value.split_whitespace();
+    let color = match parts.next()? {
+        "white" => Color::White,
+        "black" => Color::Black,
+        _ => return None,
+    };
+    let pieces = parts
+        .next()?
+        .parse()
+        .ok()
+        .filter(|pieces| (1..=MAX_HANDICAP).contains(pieces))?;
+    Some(Some(Handicap { color, pieces }))
+}
+
+fn parse_undo_policy(value: &str) -> Option<UndoPolicy> {
+    match value {
+        "free" => Some(UndoPolicy::Free),
+        "approval_required" => Some(UndoPolicy::ApprovalRequired),
+        "disabled" => Some(UndoPolicy::Disabled),
+        _ => None,
+    }
+}
+
+fn parse_search_depth(value: &str) -> Option<u8> {
+    value
+        .parse()
+        .ok()
+        .filter(|depth| SEARCH_DEPTHS.contains(depth))
+}
+
+fn parse_ui_scale(value: &str) -> Option<i32> {
+    value.parse().ok().filter(|scale| UI_SCALES.contains(scale))
+}
+
+fn parse_board_size(value: &str) -> Option<BoardSize> {
+    match value {
+        "fit" => Some(BoardSize::Fit),
+        "small" => Some(BoardSize::Small),
+        "medium" => Some(BoardSize::Medium),
+        "large" => Some(BoardSize::Large),
+        _ => None,
+    }
+}
+
+fn parse_theme(value: &str) -> Option<ColorTheme> {
+    match value {
+        "light" => Some(ColorTheme::Light),
+        "dark" => Some(ColorTheme::Dark),
+        _ => None,
+    }
+}
+
+fn parse_piece_style(value: &str) -> Option<PieceStyle> {
+    match value {
+        "shaded" => Some(PieceStyle::Shaded),
+        "flat" => Some(PieceStyle::Flat),
+        "high_visibility" => Some(PieceStyle::HighVisibility),
+        _ => None,
+    }
+}
+
+fn parse_piece_outline(value: &str) -> Option<PieceOutline> {
+    match value {
+        "thin" => Some(PieceOutline::Thin),
+        "normal" => Some(PieceOutline::Normal),
+        "thick" => Some(PieceOutline::Thick),
+        _ => None,
+    }
+}
+
+fn parse_board_background(value: &str) -> Option<BoardBackground> {
+    match value {
+        "none" => Some(BoardBackground::None),
+        "plain" => Some(BoardBackground::Plain),
+        "textured" => Some(BoardBackground::Textured),
+        _ => None,
+    }
+}
+
+fn parse_replay_speed(value: &str) -> Option<ReplaySpeed> {
+    match value {
+        "slow" => Some(ReplaySpeed::Slow),
+        "normal" => Some(ReplaySpeed::Normal),
+        "fast" => Some(ReplaySpeed::Fast),
+        _ => None,
+    }
+}
+
+fn parse_highlight_palette(value: &str) -> Option<HighlightPalette> {
+    match value {
+        "standard" => Some(HighlightPalette::Standard),
+        "deuteranopia" => Some(HighlightPalette::Deuteranopia),
+        "protanopia" => Some(HighlightPalette::Protanopia),
+        _ => None,
+    }
+}
+
+/// Parses a piece color written as `#rrggbb`, or `theme` for the theme's colors.
+fn parse_piece_color(value: &str) -> Option<Option<[u8; 3]>> {
+    if value == "theme" {
+        return Some(None);
+    }
+    if value.len() != 7 || !value.starts_with('#') {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(value.get(i..i + 2)?, 16).ok();
+    Some(Some([channel(1)?, channel(3)?, channel(5)?]))
+}
+
+fn format_piece_color(color: Option<[u8; 3]>) -> String {
+    match color {
+        Some([r, g, b]) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        None => String::from("theme"),
+    }
+}
+
+/// Parses a time control written as `seconds+increment`, optionally followed by `d` and a delay in
+/// seconds, or `none` for no clock.
+fn parse_time_control(value: &str) -> Option<Option<TimeControl>> {
+    if value == "none" {
+        return Some(None);
+    }
+    let mut parts = value.splitn(2, '+');
+    let time = parts.next()?.trim().parse().ok()?;
+    let mut parts = parts.next()?.splitn(2, 'd');
+    let increment = parts.next()?.trim().parse().ok()?;
+    let delay = match parts.next() {
+        Some(delay) => delay.trim().parse().ok()?,
+        None => 0,
+    };
+    Some(Some(
+        TimeControl::new(Duration::from_secs(time), Duration::from_secs(increment))
+            .with_delay(Duration::from_secs(delay)),
+    ))
+}
+
+fn parse_strength(value: &str) -> Option<Strength> {
+    match value {
+        "beginner" => Some(Strength::Beginner),
+        "novice" => Some(Strength::Novice),
+        "intermediate" => Some(Strength::Intermediate),
+        "full" => Some(Strength::Full),
+        _ => None,
+    }
+}
+
+fn parse_node_limit(value: &str) -> Option<Option<u64>> {
+    match value {
+        "none" => Some(None),
+        _ => value.parse().ok().filter(|&limit| limit > 0).map(Some),
+    }
+}
+
+fn parse_engine(value: &str) -> Option<EngineKind> {
+    match value {
+        "alpha_beta" => Some(EngineKind::AlphaBeta),
+        "mcts" => Some(EngineKind::Mcts),
+        _ => None,
+    }
+}
+
+fn parse_constraint(value: &str) -> Option<Constraint> {
+    match value {
+        "none" => Some(Constraint::None),
+        "no_exchanges" => Some(Constraint::NoExchanges),
+        "no_captures" => Some(Constraint::NoCaptures),
+        _ => None,
+    }
+}
+
+/// Returns the path of the config file, or `None` if there is nowhere to store it.
+pub fn path() -> Option<PathBuf> {
+    dir().map(|dir| dir.join(FILE_NAME))
+}
+
+/// Returns the path of the evaluation parameter file, or `None` if there is nowhere to store it.
+pub fn eval_params_path() -> Option<PathBuf> {
+    dir().map(|dir| dir.join(EVAL_PARAMS_FILE_NAME))
+}
+
+pub fn load_eval_params() -> io::Result<EvalParams> {
+    let path = eval_params_path().ok_or_else(no_dir_error)?;
+    Ok(EvalParams::parse(&fs::read_to_string(path)?))
+}
+
+pub fn save_eval_params(params: &EvalParams) -> io::Result<()> {
+    let path = eval_params_path().ok_or_else(no_dir_error)?;
+    write_atomic(&path, params.to_string().as_bytes())
+}
+
+/// Returns the path of the opening book, or `None` if there is nowhere to store it.
+pub fn book_path() -> Option<PathBuf> {
+    dir().map(|dir| dir.join(BOOK_FILE_NAME))
+}
+
+pub fn load_book() -> io::Result<Book> {
+    let path = book_path().ok_or_else(no_dir_error)?;
+    Ok(Book::parse(&fs::read_to_string(path)?))
+}
+
+/// Returns the path of the evaluation network, or `None` if there is nowhere to store it.
+pub fn network_path() -> Option<PathBuf> {
+    dir().map(|dir| dir.join(NETWORK_FILE_NAME))
+}
+
+pub fn load_network() -> io::Result<Network> {
+    let path = network_path().ok_or_else(no_dir_error)?;
+    Network::parse(&fs::read_to_string(path)?)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid network file"))
+}
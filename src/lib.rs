@@ -18,8 +18,11 @@
 #[macro_use]
 extern crate imgui;
 
-pub mod ai;
+pub use coerceo_core::{ai, log, player};
+
+pub mod config;
 pub mod model;
+pub mod speech;
 pub mod tests;
 pub mod update;
 pub mod view;
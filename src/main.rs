@@ -15,30 +15,61 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::io;
+use std::sync::Arc;
+
 use glium::glutin::EventsLoop;
 use imgui::Ui;
 
 use coerceo::{
-    model::{ColorMap, GameType, Model, Player},
+    config::{self, Config},
+    model::{ColorMap, GameType, Model, PlayerKind},
     update, view,
 };
 
 fn main() {
     let events_loop = EventsLoop::new();
-    let events_proxy = events_loop.create_proxy();
 
-    let model = Model::new(
-        GameType::Laurentius,
-        ColorMap::new(Player::Human, Player::Human),
-        events_proxy,
-    );
+    let game_type = GameType::Laurentius;
+    let players = ColorMap::new(PlayerKind::Human, PlayerKind::Human);
+    let mut model = Model::new(game_type, players, view::waker(&events_loop));
+
+    // Restart the game so that saved game rules take effect
+    let mut config = Config::load();
+    config.apply(&model);
+    if config.newer_version {
+        model.log.warn(
+            "The settings file is from a newer version of the game, so changes to the settings \
+             won't be saved",
+        );
+    }
+    model.reset(game_type, players);
+
+    match config::load_book() {
+        Ok(book) => model.book = Arc::new(book),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => model
+            .log
+            .error(format!("Failed to load opening book: {}", e)),
+    }
+    match config::load_network() {
+        Ok(network) => model.network = Some(Arc::new(network)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => model
+            .log
+            .error(format!("Failed to load evaluation network: {}", e)),
+    }
 
     view::run(
         String::from("Coerceo"),
         (800, 800),
         events_loop,
         model,
-        |model, ui, size| game_loop(model, ui, size),
+        move |model, ui, size| {
+            let keep_running = game_loop(model, ui, size);
+            save_config(model, &mut config);
+            keep_running
+        },
     );
 }
 
@@ -46,3 +77,16 @@ fn game_loop(model: &mut Model, ui: &Ui, size: [f32; 2]) -> bool {
     let event = view::draw(ui, size, model);
     update::update(model, event)
 }
+
+/// Saves the settings whenever they change, since the app may be killed without warning on
+/// Android. A file from a newer version of the game stays as it is.
+fn save_config(model: &Model, config: &mut Config) {
+    let mut new_config = Config::from_model(model);
+    new_config.newer_version = config.newer_version;
+    if new_config != *config {
+        if let Err(e) = new_config.save() {
+            model.log.error(format!("Failed to save settings: {}", e));
+        }
+        *config = new_config;
+    }
+}
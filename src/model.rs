@@ -0,0 +1,929 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::cell::RefCell;
+use std::cmp;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// The rules of the game live in the core library. The app's state is built on top of them.
+pub use coerceo_core::model::*;
+
+use self::rng::Rng;
+use self::tutorial::{Tutorial, LESSONS};
+use crate::ai::{
+    self, AIMove, Book, Constraint, EngineKind, EvalParams, Network, SearchInfo, Solution, Solver,
+    Strength, Waker, AI,
+};
+use crate::log::{Level, Log};
+use crate::player::{new_player, Player};
+use crate::speech::Speaker;
+
+pub struct Model {
+    pub game: Game,
+    /// When the last move was played, so the board can animate it. Taking back or replaying a
+    /// move clears this, as those are shown instantly.
+    pub last_move_time: Option<Instant>,
+    pub hexes_to_exchange: RefCell<u8>,
+    pub draw_after_plies: RefCell<Option<u16>>,
+    pub handicap: RefCell<Option<Handicap>>,
+    pub undo_policy: RefCell<UndoPolicy>,
+    pub active_undo_policy: UndoPolicy,
+    pub undo_request: Option<UndoRequest>,
+    /// An action that would throw away the game, waiting for the player to confirm it
+    pub confirmation: Option<Confirmation>,
+    /// The position being looked back at, as an index into `Game::positions`, or `None` to show
+    /// the game as it stands
+    pub replay_ply: Option<usize>,
+    /// Fields and tiles the player has marked to plan ahead, with the index of each one's color
+    pub marks: Vec<(Mark, usize)>,
+    /// Arrows the player has drawn between fields to plan ahead
+    pub arrows: Vec<(FieldCoord, FieldCoord)>,
+    /// The field the right mouse button was pressed on, while it is held to draw an arrow
+    pub arrow_start: RefCell<Option<FieldCoord>>,
+    /// The field a finger or the left mouse button was put down on and when, to tell a long press
+    /// from a tap
+    pub press_start: RefCell<Option<(FieldCoord, Instant)>>,
+    /// When the last position was stepped to while the moves are being played back on their own
+    pub auto_replay: Option<Instant>,
+    pub replay_speed: RefCell<ReplaySpeed>,
+    /// Whether playing the moves back stops at each move that captures a piece
+    pub pause_replay_on_captures: RefCell<bool>,
+    /// Whether the player has closed the summary shown when the game ended
+    pub game_over_dismissed: bool,
+    pub time_control: RefCell<Option<TimeControl>>,
+    pub players: ColorMap<PlayerKind>,
+    pub selected_piece: Option<FieldCoord>,
+    /// A move the player entered while the computer was thinking, to be played as soon as the
+    /// computer has moved
+    pub premove: Option<Move>,
+    pub exchanging: bool,
+    /// Why the player's last attempted move was rejected, if it was
+    pub illegal_move: Option<IllegalMove>,
+    /// What chooses each side's moves, made from `players` whenever a game starts
+    pub seats: ColorMap<Box<dyn Player>>,
+    /// Searches for hints
+    pub hint_ai: AI,
+    pub ai_search_depth: RefCell<u8>,
+    pub ai_strength: RefCell<Strength>,
+    pub ai_node_limit: RefCell<Option<u64>>,
+    pub ai_engine: RefCell<EngineKind>,
+    pub explain_ai_moves: RefCell<bool>,
+    pub warn_blunders: RefCell<bool>,
+    pub ai_constraint: RefCell<Constraint>,
+    pub eval_params: RefCell<EvalParams>,
+    pub book: Arc<Book>,
+    pub use_book: RefCell<bool>,
+    pub network: Option<Arc<Network>>,
+    pub use_network: RefCell<bool>,
+    pub ai_explanation: Option<String>,
+    pub hint: Option<Hint>,
+    pub solver: Solver,
+    pub solve_status: Option<SolveStatus>,
+    /// Checks whether a person's last move gave away material
+    pub blunder_check_ai: AI,
+    pub blunder_check: Option<BlunderCheck>,
+    /// Searches the position for the eval bar and analysis mode while no computer player is
+    /// searching it
+    pub analysis_ai: AI,
+    /// The position `analysis_ai` was last asked to search, and how deep
+    pub analysis_search: Option<(Board, u8)>,
+    pub show_eval_bar: RefCell<bool>,
+    /// While set, the best moves in the current position are drawn on the board
+    pub analysis_mode: RefCell<bool>,
+    pub window_states: RefCell<WindowStates>,
+    pub reduce_motion: RefCell<bool>,
+    /// How large text, buttons and spacing are drawn, as a percentage on top of the system's own
+    /// scaling for high-resolution screens
+    pub ui_scale: RefCell<i32>,
+    pub board_size: RefCell<BoardSize>,
+    pub board_zoom: RefCell<BoardZoom>,
+    pub show_threats: RefCell<bool>,
+    /// Whether selecting a piece also shows every other legal move, for players learning the game
+    pub beginner_mode: RefCell<bool>,
+    pub flip_board: RefCell<bool>,
+    pub auto_rotate: RefCell<bool>,
+    pub theme: RefCell<ColorTheme>,
+    pub board_background: RefCell<BoardBackground>,
+    pub highlight_palette: RefCell<HighlightPalette>,
+    /// The medium color of each player's pieces as RGB, or `None` to use the theme's colors
+    pub piece_colors: RefCell<ColorMap<Option<[u8; 3]>>>,
+    pub piece_style: RefCell<PieceStyle>,
+    pub piece_outline: RefCell<PieceOutline>,
+    /// Whether each move is read aloud for players using a screen reader
+    pub announce_moves: RefCell<bool>,
+    pub speaker: Speaker,
+    /// Messages from the app and the computer player, shown in the log window
+    pub log: Log,
+    /// The lesson being played, if the player is going through the tutorial
+    pub tutorial: Option<Tutorial>,
+    /// Lets the view know that there is something new to draw, such as the computer's move
+    pub waker: Waker,
+}
+
+impl Model {
+    /// Creates the app's state. `waker` is called from other threads when the view should draw
+    /// again; `Waker::none()` is enough when there is no view, such as in tests.
+    pub fn new(game_type: GameType, players: ColorMap<PlayerKind>, waker: Waker) -> Self {
+        let log = Log::new();
+        Self {
+            game: Game::new(Rules {
+                game_type,
+                ..Rules::default()
+            }),
+            last_move_time: None,
+            hexes_to_exchange: RefCell::new(2),
+            draw_after_plies: RefCell::new(None),
+            handicap: RefCell::new(None),
+            undo_policy: RefCell::new(UndoPolicy::Free),
+            active_undo_policy: UndoPolicy::Free,
+            undo_request: None,
+            confirmation: None,
+            replay_ply: None,
+            marks: vec![],
+            arrows: vec![],
+            arrow_start: RefCell::new(None),
+            press_start: RefCell::new(None),
+            auto_replay: None,
+            replay_speed: RefCell::new(ReplaySpeed::Normal),
+            pause_replay_on_captures: RefCell::new(false),
+            game_over_dismissed: false,
+            time_control: RefCell::new(None),
+            players,
+            selected_piece: None,
+            premove: None,
+            exchanging: false,
+            illegal_move: None,
+            seats: new_seats(players),
+            hint_ai: AI::new(),
+            ai_search_depth: RefCell::new(6),
+            ai_strength: RefCell::new(Strength::Full),
+            ai_node_limit: RefCell::new(None),
+            ai_engine: RefCell::new(EngineKind::AlphaBeta),
+            explain_ai_moves: RefCell::new(false),
+            warn_blunders: RefCell::new(false),
+            ai_constraint: RefCell::new(Constraint::None),
+            eval_params: RefCell::new(EvalParams::default()),
+            book: Arc::new(Book::default()),
+            use_book: RefCell::new(true),
+            network: None,
+            use_network: RefCell::new(true),
+            ai_explanation: None,
+            hint: None,
+            solver: Solver::new(),
+            solve_status: None,
+            blunder_check_ai: AI::new(),
+            blunder_check: None,
+            analysis_ai: AI::new(),
+            analysis_search: None,
+            show_eval_bar: RefCell::new(true),
+            analysis_mode: RefCell::new(false),
+            window_states: RefCell::new(WindowStates::default()),
+            reduce_motion: RefCell::new(false),
+            ui_scale: RefCell::new(100),
+            board_size: RefCell::new(BoardSize::Fit),
+            board_zoom: RefCell::new(BoardZoom::default()),
+            show_threats: RefCell::new(false),
+            beginner_mode: RefCell::new(false),
+            flip_board: RefCell::new(false),
+            auto_rotate: RefCell::new(false),
+            theme: RefCell::new(ColorTheme::Light),
+            board_background: RefCell::new(BoardBackground::None),
+            highlight_palette: RefCell::new(HighlightPalette::Standard),
+            piece_colors: RefCell::new(ColorMap::new(None, None)),
+            piece_style: RefCell::new(PieceStyle::Shaded),
+            piece_outline: RefCell::new(PieceOutline::Normal),
+            announce_moves: RefCell::new(false),
+            speaker: Speaker::new(log.clone()),
+            log,
+            tutorial: None,
+            waker,
+        }
+    }
+    pub fn reset(&mut self, game_type: GameType, players: ColorMap<PlayerKind>) {
+        self.players = players;
+
+        self.game = Game::new(Rules {
+            game_type,
+            hexes_to_exchange: *self.hexes_to_exchange.borrow(),
+            draw_after_plies: *self.draw_after_plies.borrow(),
+            time_control: *self.time_control.borrow(),
+            handicap: *self.handicap.borrow(),
+        });
+        self.last_move_time = None;
+        // Takebacks are only limited in timed games
+        self.active_undo_policy = if self.game.clock.is_some() {
+            *self.undo_policy.borrow()
+        } else {
+            UndoPolicy::Free
+        };
+        self.undo_request = None;
+        self.confirmation = None;
+        self.game_over_dismissed = false;
+        self.replay_ply = None;
+        self.premove = None;
+        self.clear_drawings();
+        self.auto_replay = None;
+        self.selected_piece = None;
+        self.exchanging = false;
+        self.illegal_move = None;
+        self.clear_hint();
+        self.clear_solution();
+        self.seats = new_seats(players);
+        self.hint_ai = AI::new();
+        self.ai_explanation = None;
+        self.clear_blunder_check();
+        self.stop_analysis();
+        self.tutorial = None;
+    }
+    /// Returns the setup for a new game like the current one, with the rules and computer strength
+    /// the next game would be played with.
+    pub fn game_setup(&self) -> GameSetup {
+        GameSetup {
+            game_type: self.game.rules.game_type,
+            players: self.players,
+            hexes_to_exchange: *self.hexes_to_exchange.borrow(),
+            handicap: *self.handicap.borrow(),
+            time_control: *self.time_control.borrow(),
+            ai_strength: *self.ai_strength.borrow(),
+        }
+    }
+    /// Starts a new game. Its rules and computer strength are kept for later games too.
+    pub fn start_game(&mut self, setup: GameSetup) {
+        *self.hexes_to_exchange.borrow_mut() = setup.hexes_to_exchange;
+        *self.handicap.borrow_mut() = setup.handicap;
+        *self.time_control.borrow_mut() = setup.time_control;
+        *self.ai_strength.borrow_mut() = setup.ai_strength;
+        self.reset(setup.game_type, setup.players);
+    }
+    /// Sets up a tutorial lesson for the player to work through as White. The game that was being
+    /// played before the tutorial started is remembered so that leaving goes back to one like it.
+    pub fn start_lesson(&mut self, lesson: usize) {
+        let previous_game = match self.tutorial {
+            Some(tutorial) => tutorial.previous_game,
+            None => (self.game.rules.game_type, self.players),
+        };
+        self.reset(
+            GameType::Laurentius,
+            ColorMap::new(PlayerKind::Human, PlayerKind::Human),
+        );
+        let board = LESSONS[lesson].board();
+        self.game = Game::from_board(
+            Rules {
+                hexes_to_exchange: board.hexes_to_exchange,
+                ..Rules::default()
+            },
+            board,
+        );
+        self.tutorial = Some(Tutorial {
+            lesson,
+            wrong_move: false,
+            previous_game,
+        });
+    }
+    /// Leaves the tutorial for a new game like the one played before it.
+    pub fn exit_tutorial(&mut self) {
+        if let Some(tutorial) = self.tutorial {
+            let (game_type, players) = tutorial.previous_game;
+            self.reset(game_type, players);
+        }
+    }
+    /// Returns whether the player has made the move the current lesson asks for. Only moves that
+    /// do are played, so any move on the board means the lesson is done.
+    pub fn is_lesson_complete(&self) -> bool {
+        self.tutorial.is_some() && self.game.last_move.is_some()
+    }
+    pub fn try_move(&mut self, mv: Move) -> Result<(), IllegalMove> {
+        self.game.play(mv)?;
+        self.last_move_time = Some(Instant::now());
+        self.clear_drawings();
+        self.undo_request = None;
+        self.confirmation = None;
+        self.ai_explanation = None;
+        self.clear_hint();
+        self.clear_solution();
+        if *self.announce_moves.borrow() {
+            self.announce_last_move();
+        }
+        Ok(())
+    }
+    /// Reads the last move aloud, along with the result if it ended the game.
+    fn announce_last_move(&self) {
+        if let Some(ref mv) = self.game.last_move {
+            let mut text = mv.announcement();
+            match self.game.outcome {
+                Outcome::InProgress => {}
+                Outcome::Win(color)
+                | Outcome::WinByResignation(color)
+                | Outcome::WinOnTime(color) => text += &format!(". {:?} wins", color),
+                _ => text += ". The game is a draw",
+            }
+            self.speaker.speak(text);
+        }
+    }
+    /// Returns what the computer player has found so far, if it is their turn and their search
+    /// has started.
+    pub fn computer_search_info(&self) -> Option<SearchInfo> {
+        let info = self.seats.get_ref(self.game.board.turn).search_info()?;
+        // Until the search starts, the info is still about the last one
+        if info.color == Some(self.game.board.turn) && info.turn == self.game.ply_count / 2 + 1 {
+            Some(info)
+        } else {
+            None
+        }
+    }
+    /// Returns the computer player's latest search: its search for this move if it is their turn,
+    /// and otherwise the one for their last move.
+    pub fn latest_search_info(&self) -> Option<SearchInfo> {
+        let turn = self.game.board.turn;
+        self.seats
+            .get_ref(turn)
+            .search_info()
+            .or_else(|| self.seats.get_ref(turn.switch()).search_info())
+    }
+    /// Stops the player to move from choosing their move.
+    pub fn stop_player(&mut self) {
+        self.seats.get_mut(self.game.board.turn).stop();
+    }
+    /// Stops the computer player's search and plays the best move it has found so far. Does
+    /// nothing if it hasn't found one yet.
+    pub fn move_now(&mut self) {
+        if let Some(ai_move) = self.computer_search_info().and_then(|info| info.ai_move()) {
+            self.stop_player();
+            self.try_ai_move(ai_move);
+        }
+    }
+    pub fn try_ai_move(&mut self, ai_move: AIMove) -> bool {
+        let board = self.game.board;
+        let moved = self.try_move(ai_move.mv).is_ok();
+        if moved && *self.explain_ai_moves.borrow() {
+            if let Some(ref mv) = self.game.last_move {
+                self.ai_explanation = Some(ai::explain_move(&board, mv, &ai_move));
+            }
+        }
+        moved
+    }
+    pub fn can_undo(&self) -> bool {
+        let comp_v_comp = self.players.white == PlayerKind::Computer
+            && self.players.black == PlayerKind::Computer;
+        !comp_v_comp
+            && self.active_undo_policy != UndoPolicy::Disabled
+            && self.undo_request.is_none()
+            && self.game.can_undo()
+    }
+    /// Asks the opponent of the player taking back a move to approve it. Against the computer, the
+    /// person is the one asking and the computer always declines. Between two people, the player
+    /// who moved last is asking.
+    pub fn request_undo(&mut self) {
+        let requester = match (self.players.white, self.players.black) {
+            (PlayerKind::Human, PlayerKind::Computer) => Color::White,
+            (PlayerKind::Computer, PlayerKind::Human) => Color::Black,
+            _ => self.game.board.turn.switch(),
+        };
+        let approver = requester.switch();
+        self.undo_request = Some(match self.players.get(approver) {
+            PlayerKind::Human => UndoRequest::Pending(approver),
+            PlayerKind::Computer => UndoRequest::Declined,
+        });
+    }
+    pub fn answer_undo_request(&mut self, accept: bool) {
+        if let Some(UndoRequest::Pending(_)) = self.undo_request {
+            self.undo_request = None;
+            if accept {
+                self.undo_move();
+            }
+        }
+    }
+    pub fn can_redo(&self) -> bool {
+        let comp_v_comp = self.players.white == PlayerKind::Computer
+            && self.players.black == PlayerKind::Computer;
+        !comp_v_comp && self.game.can_redo()
+    }
+    pub fn undo_move(&mut self) {
+        self.last_move_time = None;
+        self.game_over_dismissed = false;
+        self.replay_ply = None;
+        self.premove = None;
+        self.clear_drawings();
+        self.auto_replay = None;
+        self.illegal_move = None;
+        self.undo_request = None;
+        self.ai_explanation = None;
+        self.clear_hint();
+        self.clear_solution();
+        self.clear_blunder_check();
+        while self.game.undo() {
+            self.clear_selection();
+            self.exchanging = false;
+
+            if PlayerKind::Human == self.current_player() {
+                break;
+            }
+        }
+    }
+    pub fn redo_move(&mut self) {
+        self.last_move_time = None;
+        self.replay_ply = None;
+        self.premove = None;
+        self.clear_drawings();
+        self.auto_replay = None;
+        self.illegal_move = None;
+        self.clear_hint();
+        self.clear_solution();
+        self.clear_blunder_check();
+        while self.game.redo() {
+            self.clear_selection();
+            self.exchanging = false;
+
+            if PlayerKind::Human == self.current_player() {
+                break;
+            }
+        }
+    }
+    /// Moves through the positions of the game without changing it, unlike undo and redo. Stepping
+    /// onto the current position goes back to showing the game as it stands.
+    pub fn step_replay(&mut self, step: ReplayStep) {
+        let last = self.game.positions().len() - 1;
+        let current = self.replay_ply.unwrap_or(last);
+        let ply = match step {
+            ReplayStep::First => 0,
+            ReplayStep::Previous => current.saturating_sub(1),
+            ReplayStep::Next => cmp::min(current + 1, last),
+            ReplayStep::Last => last,
+        };
+        self.replay_ply = if ply == last { None } else { Some(ply) };
+        self.auto_replay = None;
+        self.clear_selection();
+        self.exchanging = false;
+    }
+    /// Starts or stops playing the moves back on their own. Starting from the current position
+    /// plays the whole game from the beginning.
+    pub fn toggle_auto_replay(&mut self) {
+        if self.auto_replay.is_some() {
+            self.auto_replay = None;
+        } else if self.game.positions().len() > 1 {
+            if self.replay_ply.is_none() {
+                self.step_replay(ReplayStep::First);
+            }
+            self.auto_replay = Some(Instant::now());
+        }
+    }
+    /// Steps to the next position once it is time to, while the moves are being played back. It
+    /// stops at the current position, and at captures if the player asked to.
+    pub fn advance_auto_replay(&mut self) {
+        let interval = self.replay_speed.borrow().interval();
+        match self.auto_replay {
+            Some(stepped) if stepped.elapsed() >= interval => {}
+            _ => return,
+        }
+        self.step_replay(ReplayStep::Next);
+        let captured = match self.shown_position().1 {
+            Some(mv) => mv.captured_pieces().count() > 0,
+            None => false,
+        };
+        let pause = captured && *self.pause_replay_on_captures.borrow();
+        if self.replay_ply.is_some() && !pause {
+            self.auto_replay = Some(Instant::now());
+        }
+    }
+    /// Marks a field or tile, changes its mark to the next color, or takes the mark away after the
+    /// last color.
+    pub fn toggle_mark(&mut self, mark: Mark) {
+        match self.marks.iter().position(|&(marked, _)| marked == mark) {
+            Some(i) if self.marks[i].1 + 1 < MARK_COLORS => self.marks[i].1 += 1,
+            Some(i) => {
+                self.marks.remove(i);
+            }
+            None => self.marks.push((mark, 0)),
+        }
+    }
+    /// Draws an arrow between two fields, or takes it away if it is already there.
+    pub fn toggle_arrow(&mut self, from: FieldCoord, to: FieldCoord) {
+        match self.arrows.iter().position(|&arrow| arrow == (from, to)) {
+            Some(i) => {
+                self.arrows.remove(i);
+            }
+            None => self.arrows.push((from, to)),
+        }
+    }
+    /// Takes away the player's marks and arrows.
+    pub fn clear_drawings(&mut self) {
+        self.marks.clear();
+        self.arrows.clear();
+    }
+    /// Returns whether the step would show a different position.
+    pub fn can_step_replay(&self, step: ReplayStep) -> bool {
+        let last = self.game.positions().len() - 1;
+        let current = self.replay_ply.unwrap_or(last);
+        match step {
+            ReplayStep::First | ReplayStep::Previous => current > 0,
+            ReplayStep::Next | ReplayStep::Last => current < last,
+        }
+    }
+    /// Returns the position to draw, and the move that led to it: the one being looked back at, or
+    /// the current one.
+    pub fn shown_position(&self) -> (Board, Option<MoveAnnotated>) {
+        match self.replay_ply {
+            Some(ply) => {
+                let (board, last_move) = self.game.positions()[ply];
+                (board, last_move.cloned())
+            }
+            None => (self.game.board, self.game.last_move.clone()),
+        }
+    }
+    /// Returns the opening book, if the computer should use it.
+    pub fn opening_book(&self) -> Option<Arc<Book>> {
+        if *self.use_book.borrow() && !self.book.is_empty() {
+            Some(self.book.clone())
+        } else {
+            None
+        }
+    }
+    /// Returns the evaluation network, if the computer should use it.
+    pub fn evaluation_network(&self) -> Option<Arc<Network>> {
+        if *self.use_network.borrow() {
+            self.network.clone()
+        } else {
+            None
+        }
+    }
+    pub fn clear_selection(&mut self) {
+        self.selected_piece = None;
+    }
+    pub fn current_player(&self) -> PlayerKind {
+        self.players.get(self.game.board.turn)
+    }
+    pub fn is_game_over(&self) -> bool {
+        self.game.is_game_over()
+    }
+    /// Returns whether both players are people.
+    pub fn is_human_game(&self) -> bool {
+        self.players.white == PlayerKind::Human && self.players.black == PlayerKind::Human
+    }
+    /// Returns whether `analysis_ai` is searching, or has searched, the current position.
+    pub fn is_analyzing(&self) -> bool {
+        match self.analysis_search {
+            Some((board, _)) => board == self.game.board,
+            None => false,
+        }
+    }
+    /// Stops searching the position for the eval bar and analysis mode.
+    pub fn stop_analysis(&mut self) {
+        self.analysis_ai.stop();
+        self.analysis_search = None;
+    }
+    /// Returns the latest score of the position for White, in centipieces. This comes from the
+    /// analysis search if it is looking at the current position, and otherwise from the
+    /// computer player's last search. Returns `None` if nothing has been searched yet.
+    pub fn eval_bar_score(&self) -> Option<i16> {
+        let info = if self.is_analyzing() {
+            self.analysis_ai.search_info.read().ok()?.clone()
+        } else {
+            self.latest_search_info()?
+        };
+        match (info.color?, info.score?) {
+            (Color::White, score) => Some(score),
+            (Color::Black, score) => Some(-score),
+        }
+    }
+    /// Returns the best moves in the current position found by the analysis search so far, best
+    /// first, along with their scores for the player to move.
+    pub fn analysis_moves(&self) -> Vec<(Move, i16)> {
+        if !self.is_analyzing() {
+            return vec![];
+        }
+        match self.analysis_ai.search_info.read() {
+            Ok(info) => info.root_moves.clone(),
+            Err(_) => vec![],
+        }
+    }
+    /// Ends the game if the player to move has run out of time.
+    pub fn check_flag(&mut self) {
+        if self.game.check_flag() {
+            if let (true, Outcome::WinOnTime(color)) =
+                (*self.announce_moves.borrow(), self.game.outcome)
+            {
+                self.speaker.speak(format!(
+                    "{:?} is out of time. {:?} wins",
+                    color.switch(),
+                    color
+                ));
+            }
+            self.stop_player();
+            self.clear_hint();
+            self.clear_solution();
+            self.clear_blunder_check();
+        }
+    }
+    /// Returns whether starting a new game would throw away one that is under way. Games that
+    /// haven't had a move yet, and tutorial lessons, can be left without asking.
+    pub fn is_game_at_stake(&self) -> bool {
+        self.tutorial.is_none() && !self.is_game_over() && self.game.can_undo()
+    }
+    pub fn resign(&mut self) {
+        self.confirmation = None;
+        self.game.resign();
+        self.clear_hint();
+        self.clear_solution();
+        self.clear_blunder_check();
+    }
+    /// Removes the hint, stopping the search for it if it hasn't finished.
+    pub fn clear_hint(&mut self) {
+        if let Some(Hint::Searching) = self.hint {
+            self.hint_ai.stop();
+        }
+        self.hint = None;
+    }
+    /// Forgets the blunder warning, stopping the check for one if it hasn't finished.
+    pub fn clear_blunder_check(&mut self) {
+        self.blunder_check_ai.stop();
+        self.blunder_check = None;
+    }
+    /// Forgets the result of solving the position, stopping the solver if it hasn't finished.
+    pub fn clear_solution(&mut self) {
+        self.solver.stop();
+        self.solve_status = None;
+    }
+}
+
+fn new_seats(players: ColorMap<PlayerKind>) -> ColorMap<Box<dyn Player>> {
+    ColorMap::new(new_player(players.white), new_player(players.black))
+}
+
+/// An attempt to solve the current position with proof-number search.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SolveStatus {
+    Solving,
+    Solved(Solution),
+}
+
+/// A suggested move for a human player, found by the AI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Hint {
+    Searching,
+    Move(Move),
+}
+
+#[derive(Default)]
+pub struct WindowStates {
+    pub about: bool,
+    pub ai_debug: bool,
+    pub eval_params: bool,
+    pub how_to_play: bool,
+    pub log: bool,
+    /// The least important messages shown in the log window
+    pub log_level: Level,
+    pub piece_colors: bool,
+    /// The choices in the New Game dialog while it is open
+    pub new_game: Option<GameSetup>,
+    /// The seed typed into the New Game dialog for a random start
+    pub new_game_seed: String,
+}
+
+/// Whether a player may take back moves during a game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UndoPolicy {
+    Free,
+    ApprovalRequired,
+    Disabled,
+}
+
+/// The colors the game is drawn with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorTheme {
+    Light,
+    Dark,
+}
+
+/// What is drawn behind the board's tiles, including where tiles have been removed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoardBackground {
+    /// The window's own background
+    None,
+    /// A solid color from the theme
+    Plain,
+    /// The theme's color with faint diagonal lines
+    Textured,
+}
+
+/// How the pieces are filled in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PieceStyle {
+    /// Each side in its own shade, so the pieces look raised
+    Shaded,
+    /// One color for the whole piece
+    Flat,
+    /// Larger pieces in pure white and black with a heavier outline, ignoring the piece colors,
+    /// for small screens and recordings
+    HighVisibility,
+}
+
+/// How thick the outline around each piece is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PieceOutline {
+    Thin,
+    Normal,
+    Thick,
+}
+
+impl PieceOutline {
+    /// Returns how many times thicker than normal the outline is.
+    pub fn scale(self) -> f32 {
+        match self {
+            PieceOutline::Thin => 0.5,
+            PieceOutline::Normal => 1.0,
+            PieceOutline::Thick => 2.0,
+        }
+    }
+}
+
+/// How quickly the moves are played back when replaying a game.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplaySpeed {
+    Slow,
+    Normal,
+    Fast,
+}
+
+impl ReplaySpeed {
+    /// Returns how long each position is shown for.
+    pub fn interval(self) -> Duration {
+        match self {
+            ReplaySpeed::Slow => Duration::from_millis(2000),
+            ReplaySpeed::Normal => Duration::from_millis(1000),
+            ReplaySpeed::Fast => Duration::from_millis(400),
+        }
+    }
+}
+
+/// The colors of the highlights on the board. The alternatives stay distinguishable for players
+/// with red-green color blindness.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HighlightPalette {
+    Standard,
+    Deuteranopia,
+    Protanopia,
+}
+
+/// The progress of checking a person's move for blunders. The check compares the score of the
+/// best move in the position before the move with the score of the move that was played.
+#[derive(Clone, Copy, PartialEq)]
+pub enum BlunderCheck {
+    /// Searching the position before the move for the best score the player could have had
+    SearchingBest { mv: Move, after: Board },
+    /// Searching the position after the move, whose score is compared with `best`
+    SearchingPlayed { best: i16 },
+    /// The move scored enough worse than the best move that it may lose a piece
+    Blunder,
+}
+
+/// An action that ends the game in progress, so the player is asked to confirm it first.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Confirmation {
+    Resign,
+    NewGame(GameSetup),
+}
+
+/// Everything that is chosen when starting a new game.
+#[derive(Clone, Copy, PartialEq)]
+pub struct GameSetup {
+    pub game_type: GameType,
+    pub players: ColorMap<PlayerKind>,
+    pub hexes_to_exchange: u8,
+    pub handicap: Option<Handicap>,
+    pub time_control: Option<TimeControl>,
+    pub ai_strength: Strength,
+}
+
+/// Returns the random start for a seed typed by the player, in hexadecimal as games show it, or a
+/// new random start if nothing was typed. Returns `None` if the seed isn't a number.
+pub fn random_start(seed: &str) -> Option<GameType> {
+    let seed = seed.trim();
+    if seed.is_empty() {
+        Some(GameType::Random(Rng::from_time().next_u64()))
+    } else {
+        u64::from_str_radix(seed.trim_start_matches('#'), 16)
+            .ok()
+            .map(GameType::Random)
+    }
+}
+
+/// How large the board is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoardSize {
+    /// As large as the window allows
+    Fit,
+    Small,
+    Medium,
+    Large,
+}
+
+impl BoardSize {
+    /// Returns the most the board may take up across and down, in pixels before the UI scale is
+    /// applied, or `None` if it fills the window.
+    pub fn side(self) -> Option<f32> {
+        match self {
+            BoardSize::Fit => None,
+            BoardSize::Small => Some(360.0),
+            BoardSize::Medium => Some(540.0),
+            BoardSize::Large => Some(720.0),
+        }
+    }
+}
+
+/// How far the board can be zoomed in, as a multiple of the size that fits its space
+pub const MAX_BOARD_ZOOM: f32 = 4.0;
+
+/// How far the board is zoomed in and moved with touch gestures. The offset is in pixels, from the
+/// middle of the board's space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoardZoom {
+    pub scale: f32,
+    pub offset: [f32; 2],
+}
+
+impl Default for BoardZoom {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            offset: [0.0, 0.0],
+        }
+    }
+}
+
+impl BoardZoom {
+    /// Zooms by `factor` and moves the board by `pan` pixels. The board can't be zoomed out past
+    /// fitting its space, or in past `MAX_BOARD_ZOOM`.
+    pub fn pinch(&mut self, factor: f32, pan: [f32; 2]) {
+        let scale = self.scale * factor;
+        self.scale = if factor < 1.0 {
+            scale.max(1.0)
+        } else {
+            scale.min(MAX_BOARD_ZOOM)
+        };
+        self.offset = [self.offset[0] + pan[0], self.offset[1] + pan[1]];
+    }
+    /// Keeps the board covering the middle of a space of `size` pixels, so that it can't be moved
+    /// out of sight, and returns the result.
+    pub fn clamp_offset(&mut self, size: [f32; 2]) -> Self {
+        let scale = self.scale;
+        let clamp = |offset: f32, size: f32| {
+            let limit = (scale - 1.0) * size / 2.0;
+            offset.max(-limit).min(limit)
+        };
+        self.offset = [
+            clamp(self.offset[0], size[0]),
+            clamp(self.offset[1], size[1]),
+        ];
+        *self
+    }
+}
+
+/// The depths the computer can be set to search to, in plies
+pub const SEARCH_DEPTHS: RangeInclusive<u8> = 1..=7;
+
+/// The percentages the UI can be scaled by
+pub const UI_SCALES: RangeInclusive<i32> = 75..=200;
+
+/// How many colors fields and tiles can be marked with
+pub const MARK_COLORS: usize = 3;
+
+/// Something on the board the player has marked with the right mouse button.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mark {
+    Field(FieldCoord),
+    /// A whole tile, marked while holding Shift
+    Hex(HexCoord),
+}
+
+/// A step through the game's positions when looking back at it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReplayStep {
+    First,
+    Previous,
+    Next,
+    Last,
+}
+
+/// A takeback request under `UndoPolicy::ApprovalRequired`. `Pending` holds the color of the
+/// player who must approve it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UndoRequest {
+    Pending(Color),
+    Declined,
+}
@@ -0,0 +1,94 @@
+/*
+ * Copyright (C) 2017-2019 Ryan Huang
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published
+ * by the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Reads text aloud with the platform's speech synthesizer, so that players using a screen reader
+//! can follow the game. Speech runs on its own thread, one announcement at a time, and is
+//! skipped with a warning in the log if the platform has no synthesizer to run.
+
+use std::process::Command;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use crate::log::Log;
+
+pub struct Speaker {
+    sender: Sender<String>,
+}
+
+impl Speaker {
+    /// Starts the speech thread. Failures to speak are written to `log`, as is a warning the first
+    /// time there is no synthesizer to speak with.
+    pub fn new(log: Log) -> Self {
+        let (sender, receiver) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            let mut warned = false;
+            for text in receiver {
+                let result = match command(&text) {
+                    Some(mut command) => command.status().map(|_| ()),
+                    None => {
+                        if !warned {
+                            log.warn(
+                                "Speech isn't supported on this platform, so nothing is read aloud",
+                            );
+                            warned = true;
+                        }
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    log.error(format!("Failed to speak: {}", e));
+                }
+            }
+        });
+        Self { sender }
+    }
+    /// Queues text to be read aloud after anything already queued.
+    pub fn speak(&self, text: String) {
+        // The thread only stops if it panicked, in which case there's nothing left to do
+        let _ = self.sender.send(text);
+    }
+}
+
+/// Returns the command that reads text aloud on this platform, or `None` if there isn't one.
+fn command(text: &str) -> Option<Command> {
+    if cfg!(target_os = "android") {
+        None
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("say");
+        command.arg(text);
+        Some(command)
+    } else if cfg!(windows) {
+        // The text is passed in an environment variable so that it is never parsed as a script
+        let mut command = Command::new("powershell");
+        command
+            .arg("-NoProfile")
+            .arg("-NonInteractive")
+            .arg("-Command")
+            .arg(
+                "Add-Type -AssemblyName System.Speech; \
+                 (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:COERCEO_SPEECH)",
+            )
+            .env("COERCEO_SPEECH", text);
+        Some(command)
+    } else {
+        // Speech Dispatcher, which screen readers such as Orca speak through. Waiting for it keeps
+        // the announcements from talking over each other.
+        let mut command = Command::new("spd-say");
+        command.arg("--wait").arg(text);
+        Some(command)
+    }
+}
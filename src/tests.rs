@@ -17,85 +17,202 @@
 
 #![cfg(test)]
 
-use crate::model::{Board, GameType};
-
-fn perft(board: &Board, depth: u8) -> u64 {
-    if depth == 0 {
-        1
-    } else {
-        let mut sum = 0;
-        for mv in board.generate_moves() {
-            let mut new_board = *board;
-            new_board.apply_move(&mv);
-            sum += perft(&new_board, depth - 1);
-        }
-        sum
-    }
-}
-
-// All of the following perft results have not been verified by an external source. They only test
-// for consistency with earlier versions of the program.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::ai::{Constraint, EngineKind, Strength, Waker};
+use crate::config::{self, Config};
+use crate::model::{
+    random_start, BoardBackground, BoardSize, BoardZoom, Color, ColorMap, ColorTheme, GameType,
+    Handicap, HighlightPalette, Model, PieceOutline, PieceStyle, PlayerKind, ReplaySpeed,
+    TimeControl, UndoPolicy, UndoRequest, MAX_BOARD_ZOOM,
+};
+use crate::update;
 
 #[test]
-fn laurentius_perft_4() {
-    let counts = [48, 2304, 110304, 5280654];
-    let board = Board::new(GameType::Laurentius, 2);
-
-    for (i, &count) in counts.iter().enumerate() {
-        assert_eq!(count, perft(&board, i as u8 + 1));
+fn config_round_trip() {
+    let config = Config {
+        hexes_to_exchange: 3,
+        draw_after_plies: Some(100),
+        handicap: Some(Handicap {
+            color: Color::Black,
+            pieces: 2,
+        }),
+        undo_policy: UndoPolicy::ApprovalRequired,
+        time_control: Some(
+            TimeControl::new(Duration::from_secs(300), Duration::from_secs(5))
+                .with_delay(Duration::from_secs(2)),
+        ),
+        ai_search_depth: 4,
+        ai_strength: Strength::Novice,
+        ai_node_limit: Some(50_000),
+        ai_engine: EngineKind::Mcts,
+        explain_ai_moves: true,
+        warn_blunders: true,
+        ai_constraint: Constraint::NoCaptures,
+        use_opening_book: false,
+        use_network_eval: false,
+        reduce_motion: true,
+        ui_scale: 150,
+        board_size: BoardSize::Medium,
+        show_threats: true,
+        beginner_mode: true,
+        show_eval_bar: false,
+        flip_board: true,
+        auto_rotate: true,
+        theme: ColorTheme::Dark,
+        board_background: BoardBackground::Textured,
+        highlight_palette: HighlightPalette::Protanopia,
+        white_piece_color: Some([0xc0, 0x39, 0x2b]),
+        black_piece_color: None,
+        piece_style: PieceStyle::HighVisibility,
+        piece_outline: PieceOutline::Thick,
+        announce_moves: true,
+        replay_speed: ReplaySpeed::Fast,
+        pause_replay_on_captures: true,
+        newer_version: false,
+    };
+    assert_eq!(config, Config::parse(&config.to_string()));
+
+    let newer = config.to_string().replace(
+        &format!("version = {}", config::VERSION),
+        &format!("version = {}", config::VERSION + 1),
+    );
+    assert_eq!(
+        Config {
+            newer_version: true,
+            ..Config::default()
+        },
+        Config::parse(&newer)
+    );
+    assert_eq!(Config::default(), Config::parse("garbage"));
+
+    // Version 1 had a switch for one-tile exchanges
+    let old = Config::parse("version = 1\nexchange_one_hex = true\n");
+    assert_eq!(1, old.hexes_to_exchange);
+    assert_eq!(
+        2,
+        Config::parse("version = 1\nexchange_one_hex = false\n").hexes_to_exchange
+    );
+    assert_eq!(
+        2,
+        Config::parse("version = 2\nhexes_to_exchange = 4\n").hexes_to_exchange
+    );
+    assert_eq!(100, Config::parse("version = 2\nui_scale = 500\n").ui_scale);
+    // Depths the computer can't search to are ignored, rather than stopping it from moving
+    for depth in &["0", "8", "300", "-1"] {
+        let contents = format!("version = 2\nai_search_depth = {}\n", depth);
+        assert_eq!(6, Config::parse(&contents).ai_search_depth);
     }
 }
 
 #[test]
-#[ignore]
-fn laurentius_perft_5() {
-    let counts = [48, 2304, 110304, 5280654, 254945184];
-    let board = Board::new(GameType::Laurentius, 2);
-
-    for (i, &count) in counts.iter().enumerate() {
-        assert_eq!(count, perft(&board, i as u8 + 1));
-    }
+fn config_newer_version_kept() {
+    let dir = std::env::temp_dir().join(format!("coerceo-newer-{}", std::process::id()));
+    let path = dir.join("coerceo.cfg");
+    let newer = format!(
+        "version = {}\nsetting_from_the_future = 1\n",
+        config::VERSION + 1
+    );
+    config::write_atomic(&path, newer.as_bytes()).unwrap();
+
+    // Changing a setting doesn't write the defaults over the newer file
+    let mut config = Config::load_from(&path);
+    assert!(config.newer_version);
+    config.ui_scale = 150;
+    config.save_to(&path).unwrap();
+    assert_eq!(newer, std::fs::read_to_string(&path).unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
 }
 
 #[test]
-#[ignore]
-fn laurentius_perft_6() {
-    let counts = [48, 2304, 110304, 5280654, 254945184, 12307984056];
-    let board = Board::new(GameType::Laurentius, 2);
-
-    for (i, &count) in counts.iter().enumerate() {
-        assert_eq!(count, perft(&board, i as u8 + 1));
+fn random_start_seed() {
+    assert_eq!(Some(GameType::Random(0x1a2b)), random_start("1a2b"));
+    // As shown next to the players
+    assert_eq!(Some(GameType::Random(0x1a2b)), random_start(" #1A2B "));
+    assert_eq!(None, random_start("seed"));
+    assert_eq!(None, random_start("1a2b3c4d5e6f708192"));
+    match random_start("") {
+        Some(GameType::Random(_)) => (),
+        other => panic!("Expected a new random start, got {:?}", other),
     }
 }
 
 #[test]
-fn ocius_perft_5() {
-    let counts = [26, 676, 17234, 435572, 10739924];
-    let board = Board::new(GameType::Ocius, 2);
-
-    for (i, &count) in counts.iter().enumerate() {
-        assert_eq!(count, perft(&board, i as u8 + 1));
-    }
+fn board_zoom() {
+    let mut zoom = BoardZoom::default();
+    zoom.pinch(2.0, [500.0, -10.0]);
+    assert_eq!(2.0, zoom.scale);
+    // Zoomed to twice the size, the board can move half of its space each way
+    assert_eq!([200.0, -10.0], zoom.clamp_offset([400.0, 300.0]).offset);
+
+    zoom.pinch(10.0, [0.0, 0.0]);
+    assert_eq!(MAX_BOARD_ZOOM, zoom.scale);
+    zoom.pinch(0.01, [0.0, 0.0]);
+    assert_eq!(1.0, zoom.scale);
+    assert_eq!([0.0, 0.0], zoom.clamp_offset([400.0, 300.0]).offset);
 }
 
 #[test]
-#[ignore]
-fn ocius_perft_6() {
-    let counts = [26, 676, 17234, 435572, 10739924, 262208752];
-    let board = Board::new(GameType::Ocius, 2);
-
-    for (i, &count) in counts.iter().enumerate() {
-        assert_eq!(count, perft(&board, i as u8 + 1));
+fn headless_model() {
+    // Without a window, the computer's threads wake up whatever waker they are given
+    let wakes = Arc::new(AtomicUsize::new(0));
+    let wakes_clone = wakes.clone();
+    let waker = Waker::new(move || {
+        wakes_clone.fetch_add(1, Ordering::Relaxed);
+    });
+    let players = ColorMap::new(PlayerKind::Computer, PlayerKind::Human);
+    let mut model = Model::new(GameType::Laurentius, players, waker);
+    *model.ai_search_depth.borrow_mut() = 1;
+
+    let mut setup = model.game_setup();
+    setup.hexes_to_exchange = 3;
+    model.start_game(setup);
+    assert_eq!(3, model.game.rules.hexes_to_exchange);
+
+    for _ in 0..500 {
+        assert!(update::update(&mut model, None));
+        if model.game.plies_played() > 0 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
     }
+    assert_eq!(1, model.game.plies_played());
+    assert_eq!(PlayerKind::Human, model.current_player());
+    assert!(wakes.load(Ordering::Relaxed) > 0);
 }
 
 #[test]
-#[ignore]
-fn ocius_perft_7() {
-    let counts = [26, 676, 17234, 435572, 10739924, 262208752, 6252014770];
-    let board = Board::new(GameType::Ocius, 2);
-
-    for (i, &count) in counts.iter().enumerate() {
-        assert_eq!(count, perft(&board, i as u8 + 1));
-    }
+fn undo_approval() {
+    let time_control = TimeControl::new(Duration::from_secs(300), Duration::from_secs(0));
+    let start_game = |players: ColorMap<PlayerKind>, time_control: Option<TimeControl>| {
+        let mut model = Model::new(GameType::Laurentius, players, Waker::none());
+        *model.undo_policy.borrow_mut() = UndoPolicy::ApprovalRequired;
+        *model.time_control.borrow_mut() = time_control;
+        model.reset(GameType::Laurentius, players);
+        let mv = model.game.board.generate_moves().next().unwrap();
+        model.try_move(mv).unwrap();
+        model
+    };
+
+    // Between people, the player who moved last asks their opponent
+    let people = ColorMap::new(PlayerKind::Human, PlayerKind::Human);
+    let mut model = start_game(people, Some(time_control));
+    model.request_undo();
+    assert!(model.undo_request == Some(UndoRequest::Pending(Color::Black)));
+
+    // The computer is asked even when the person asks on their own turn, and always declines
+    let mut model = start_game(
+        ColorMap::new(PlayerKind::Computer, PlayerKind::Human),
+        Some(time_control),
+    );
+    model.request_undo();
+    assert!(model.undo_request == Some(UndoRequest::Declined));
+
+    // Without a clock, moves can always be taken back
+    let model = start_game(people, None);
+    assert_eq!(UndoPolicy::Free, model.active_undo_policy);
 }
@@ -15,17 +15,58 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use crate::model::{ColorMap, FieldCoord, GameType, Model, Move, Player};
+use std::time::Duration;
+
+use crate::ai::{Constraint, EngineKind, SearchSettings, Strength, TimeLimit};
+use crate::config;
+use crate::model::{
+    BlunderCheck, Confirmation, FieldCoord, GameSetup, Hint, IllegalMove, Mark, Model, Move,
+    PlayerKind, ReplayStep, SolveStatus, UndoPolicy,
+};
+use crate::player::MoveRequest;
 
 use self::Event::*;
 
+/// How long the AI may search for a hint.
+const HINT_TIME_LIMIT: TimeLimit = TimeLimit {
+    target: Duration::from_millis(500),
+    max: Duration::from_millis(1500),
+};
+/// How deep the eval bar searches in games between people. It only needs a rough idea of who is
+/// ahead, so it stays shallow to keep up with the moves.
+const EVAL_BAR_DEPTH: u8 = 4;
+/// How deep the blunder check searches the position before a person's move. The position after it
+/// is searched one ply less, so both searches see as far.
+const BLUNDER_CHECK_DEPTH: u8 = 4;
+
 pub enum Event {
     Click(FieldCoord),
+    /// Exchanges for the piece on the field without having to press Exchange first
+    ExchangeAt(FieldCoord),
     Exchange,
-    NewGame(GameType, ColorMap<Player>),
+    Hint,
+    Solve,
+    NewGame(GameSetup),
+    StartLesson(usize),
+    ExitTutorial,
     Resign,
+    /// Goes ahead with the action waiting for confirmation if true, or calls it off if false
+    Confirm(bool),
     Undo,
+    AnswerUndo(bool),
+    DismissBlunderWarning,
+    DismissGameOver,
+    /// Makes the computer play the best move it has found so far
+    MoveNow,
+    FlipBoard,
+    ClearSelection,
+    Replay(ReplayStep),
+    ToggleMark(Mark),
+    ToggleArrow(FieldCoord, FieldCoord),
+    ToggleAutoReplay,
     Redo,
+    LoadEvalParams,
+    SaveEvalParams,
     Quit,
 }
 
@@ -34,91 +75,428 @@ pub fn update(model: &mut Model, event: Option<Event>) -> bool {
         return false;
     }
 
-    match model.current_player() {
-        Player::Human => {
-            if let Some(event) = event {
-                handle_event(model, &event);
-            }
-        }
-        Player::Computer => {
-            if let Some(event) = event {
-                match event {
-                    Click(_) | Exchange => {}
-                    _ => {
-                        model.ai.stop();
-                        handle_event(model, &event);
-                        return true;
-                    }
-                }
-            }
+    model.check_flag();
+    model.advance_auto_replay();
 
-            if !model.is_game_over() {
-                if model.ai.is_idle() {
-                    let should_delay =
-                        model.players.get(model.board.turn.switch()) == Player::Human;
-                    let board_list = model.board_list();
-                    model.ai.think(
-                        model.board,
-                        board_list,
-                        *model.ai_search_depth.borrow() as u8,
-                        model.events_proxy.clone(),
-                        should_delay,
-                        model.ply_count,
-                    );
+    if let Some(solution) = model.solver.try_recv() {
+        model.solve_status = Some(SolveStatus::Solved(solution));
+    }
+
+    if let Some(event) = event {
+        if model.current_player() == PlayerKind::Human {
+            handle_event(model, &event);
+        } else {
+            match event {
+                // The player can enter their next move while the computer is thinking
+                Click(clicked)
+                    if model.players.get(model.game.board.turn.switch()) == PlayerKind::Human
+                        && !model.is_game_over()
+                        && model.replay_ply.is_none() =>
+                {
+                    handle_premove_click(model, clicked)
+                }
+                Click(_) | ExchangeAt(_) | Exchange | Hint => {}
+                Solve
+                | DismissBlunderWarning
+                | DismissGameOver
+                | FlipBoard
+                | ClearSelection
+                | Replay(_)
+                | ToggleAutoReplay
+                | ToggleMark(_)
+                | ToggleArrow(..)
+                | Confirm(false) => handle_event(model, &event),
+                // These only ask for confirmation, so the computer can keep thinking
+                Resign => handle_event(model, &event),
+                NewGame(..) if model.is_game_at_stake() => handle_event(model, &event),
+                // This plays the computer's best move so far and stops it afterwards, so it must
+                // not be stopped before its move is read
+                MoveNow => {
+                    handle_event(model, &event);
+                    return true;
                 }
-                if let Some(mv) = model.ai.try_recv() {
-                    model.try_move(mv);
+                _ => {
+                    model.stop_player();
+                    handle_event(model, &event);
+                    return true;
                 }
             }
         }
     }
+
+    if let Some(Hint::Searching) = model.hint {
+        if let Some(ai_move) = model.hint_ai.try_recv() {
+            model.hint = Some(Hint::Move(ai_move.mv));
+        }
+    }
+
+    // Every kind of player is asked for its move the same way. People's moves are played as they
+    // are entered, so they never have one waiting here.
+    if !model.is_game_over() {
+        let turn = model.game.board.turn;
+        if model.seats.get_ref(turn).is_idle() {
+            let request = move_request(model);
+            model.seats.get_mut(turn).think(request);
+        }
+        if let Some(ai_move) = model.seats.get_mut(turn).try_recv() {
+            if model.try_ai_move(ai_move) {
+                play_premove(model);
+            }
+        }
+    }
+    update_blunder_check(model);
+    update_analysis(model);
     true
 }
 
+/// Returns what the player to move needs to know to choose their move.
+fn move_request(model: &Model) -> MoveRequest {
+    let turn = model.game.board.turn;
+    MoveRequest {
+        board: model.game.board,
+        board_list: model.game.board_list(),
+        settings: SearchSettings {
+            depth: *model.ai_search_depth.borrow(),
+            constraint: *model.ai_constraint.borrow(),
+            time_limit: model.game.clock.as_ref().map(|clock| {
+                TimeLimit::from_clock(clock.remaining(turn), clock.increment(), clock.delay())
+            }),
+            node_limit: *model.ai_node_limit.borrow(),
+            eval_params: *model.eval_params.borrow(),
+            strength: *model.ai_strength.borrow(),
+            book: model.opening_book(),
+            engine: *model.ai_engine.borrow(),
+            network: model.evaluation_network(),
+            log: Some(model.log.clone()),
+        },
+        waker: model.waker.clone(),
+        // Moves between computers are played straight away
+        delay: model.players.get(turn.switch()) == PlayerKind::Human,
+        ply_count: model.game.ply_count,
+    }
+}
+
+/// Keeps the analysis search looking at the current position while a person is to move. It runs
+/// in analysis mode, and for the eval bar in games between people. In games against the computer,
+/// the eval bar otherwise shows the score of the computer's own searches.
+fn update_analysis(model: &mut Model) {
+    // The results are read from the search info, so the move itself isn't needed
+    model.analysis_ai.try_recv();
+
+    let depth = if *model.analysis_mode.borrow() {
+        *model.ai_search_depth.borrow()
+    } else if *model.show_eval_bar.borrow() && model.is_human_game() {
+        EVAL_BAR_DEPTH
+    } else {
+        return;
+    };
+    if model.is_game_over() || model.current_player() == PlayerKind::Computer {
+        // Don't take time away from the computer player
+        if model.analysis_search.is_some() {
+            model.stop_analysis();
+        }
+        return;
+    }
+
+    let board = model.game.board;
+    if model.analysis_search == Some((board, depth)) {
+        return;
+    }
+    let settings = SearchSettings {
+        depth,
+        constraint: Constraint::None,
+        time_limit: None,
+        node_limit: None,
+        eval_params: *model.eval_params.borrow(),
+        strength: Strength::Full,
+        book: None,
+        engine: EngineKind::AlphaBeta,
+        network: model.evaluation_network(),
+        log: None,
+    };
+    model.analysis_ai.think(
+        board,
+        model.game.board_list(),
+        settings,
+        model.waker.clone(),
+        false,
+        model.game.ply_count,
+    );
+    model.analysis_search = Some((board, depth));
+}
+
+/// Returns the settings for the blunder check's searches.
+fn blunder_check_settings(model: &Model, depth: u8) -> SearchSettings {
+    SearchSettings {
+        depth,
+        constraint: Constraint::None,
+        time_limit: None,
+        node_limit: None,
+        eval_params: *model.eval_params.borrow(),
+        strength: Strength::Full,
+        book: None,
+        engine: EngineKind::AlphaBeta,
+        network: model.evaluation_network(),
+        log: None,
+    }
+}
+
+/// Plays a person's move, then starts checking it for blunders if they asked to be warned. In the
+/// tutorial, a legal move that doesn't do what the lesson asks for isn't played.
+fn play_human_move(model: &mut Model, mv: Move) -> Result<(), IllegalMove> {
+    let before = model.game.board;
+    if let Some(ref mut tutorial) = model.tutorial {
+        tutorial.wrong_move = false;
+        before.check_move(&mv)?;
+        let mut after = before;
+        tutorial.wrong_move = !tutorial.lesson().is_goal(&after.annotated_apply_move(&mv));
+        if tutorial.wrong_move {
+            return Ok(());
+        }
+    }
+    model.try_move(mv)?;
+
+    model.clear_blunder_check();
+    if *model.warn_blunders.borrow()
+        && model.tutorial.is_none()
+        && model.active_undo_policy != UndoPolicy::Disabled
+        && !model.is_game_over()
+    {
+        // The check is only a rough guide, so it doesn't look for repetitions
+        let settings = blunder_check_settings(model, BLUNDER_CHECK_DEPTH);
+        model.blunder_check_ai.think(
+            before,
+            vec![],
+            settings,
+            model.waker.clone(),
+            false,
+            model.game.ply_count - 1,
+        );
+        model.blunder_check = Some(BlunderCheck::SearchingBest {
+            mv,
+            after: model.game.board,
+        });
+    }
+    Ok(())
+}
+
+/// Moves the blunder check on to its next step once a search finishes.
+fn update_blunder_check(model: &mut Model) {
+    let ai_move = match model.blunder_check_ai.try_recv() {
+        Some(ai_move) => ai_move,
+        None => return,
+    };
+    match model.blunder_check {
+        Some(BlunderCheck::SearchingBest { mv, after }) => {
+            if ai_move.mv == mv {
+                model.blunder_check = None;
+                return;
+            }
+            let settings = blunder_check_settings(model, BLUNDER_CHECK_DEPTH - 1);
+            model.blunder_check_ai.think(
+                after,
+                vec![],
+                settings,
+                model.waker.clone(),
+                false,
+                model.game.ply_count,
+            );
+            model.blunder_check = Some(BlunderCheck::SearchingPlayed {
+                best: ai_move.score,
+            });
+        }
+        Some(BlunderCheck::SearchingPlayed { best }) => {
+            // The search was from the other player's side
+            let played = -ai_move.score;
+            // Warn when the move gives away most of a piece
+            let piece =
+                model.eval_params.borrow().piece * i16::from(model.game.board.hexes_to_exchange);
+            model.blunder_check = if best - played >= piece * 3 / 4 {
+                Some(BlunderCheck::Blunder)
+            } else {
+                None
+            };
+        }
+        Some(BlunderCheck::Blunder) | None => {}
+    }
+}
+
 fn handle_event(model: &mut Model, event: &Event) {
     match event {
         Click(clicked) => {
-            if !model.is_game_over() {
+            if !model.is_game_over() && !model.is_lesson_complete() && model.replay_ply.is_none() {
                 handle_click(model, *clicked);
             }
         }
+        ExchangeAt(field) => {
+            if model.game.board.can_exchange()
+                && !model.is_game_over()
+                && !model.is_lesson_complete()
+                && model.replay_ply.is_none()
+            {
+                model.clear_selection();
+                model.illegal_move = None;
+                match play_human_move(model, Move::exchange_from_field(*field)) {
+                    Ok(()) => model.exchanging = false,
+                    Err(illegal) => model.illegal_move = Some(illegal),
+                }
+            }
+        }
         Exchange => {
-            if model.board.can_exchange() && !model.is_game_over() {
+            if model.game.board.can_exchange()
+                && !model.is_game_over()
+                && !model.is_lesson_complete()
+                && model.replay_ply.is_none()
+            {
                 model.exchanging = !model.exchanging;
                 model.clear_selection();
             }
         }
-        NewGame(game_type, players) => {
-            model.reset(*game_type, *players);
+        Hint => {
+            if model.hint.is_none() && !model.is_game_over() {
+                let settings = SearchSettings {
+                    depth: *model.ai_search_depth.borrow(),
+                    constraint: Constraint::None,
+                    time_limit: Some(HINT_TIME_LIMIT),
+                    node_limit: None,
+                    eval_params: *model.eval_params.borrow(),
+                    strength: Strength::Full,
+                    book: model.opening_book(),
+                    engine: *model.ai_engine.borrow(),
+                    network: model.evaluation_network(),
+                    log: None,
+                };
+                model.hint_ai.think(
+                    model.game.board,
+                    model.game.board_list(),
+                    settings,
+                    model.waker.clone(),
+                    false,
+                    model.game.ply_count,
+                );
+                model.hint = Some(Hint::Searching);
+            }
+        }
+        Solve => {
+            if model.solve_status.is_none() && !model.is_game_over() {
+                model.solver.start(model.game.board, model.waker.clone());
+                model.solve_status = Some(SolveStatus::Solving);
+            }
+        }
+        NewGame(setup) => {
+            if model.is_game_at_stake() {
+                model.confirmation = Some(Confirmation::NewGame(*setup));
+            } else {
+                model.start_game(*setup);
+            }
         }
+        StartLesson(lesson) => model.start_lesson(*lesson),
+        ExitTutorial => model.exit_tutorial(),
         Resign => {
-            model.push_undo_state();
-            model.resign();
+            if !model.is_game_over() {
+                model.confirmation = Some(Confirmation::Resign);
+            }
+        }
+        Confirm(confirmed) => match model.confirmation.take() {
+            Some(Confirmation::Resign) if *confirmed => model.resign(),
+            Some(Confirmation::NewGame(setup)) if *confirmed => model.start_game(setup),
+            _ => {}
+        },
+        Undo => match model.active_undo_policy {
+            UndoPolicy::Free => model.undo_move(),
+            UndoPolicy::ApprovalRequired => model.request_undo(),
+            UndoPolicy::Disabled => {}
+        },
+        AnswerUndo(accept) => model.answer_undo_request(*accept),
+        DismissBlunderWarning => model.blunder_check = None,
+        DismissGameOver => model.game_over_dismissed = true,
+        MoveNow => model.move_now(),
+        FlipBoard => {
+            let mut flip_board = model.flip_board.borrow_mut();
+            *flip_board = !*flip_board;
+        }
+        ClearSelection => {
+            model.clear_selection();
+            model.premove = None;
+            model.exchanging = false;
+            model.clear_drawings();
         }
-        Undo => model.undo_move(),
         Redo => model.redo_move(),
+        Replay(step) => model.step_replay(*step),
+        ToggleAutoReplay => model.toggle_auto_replay(),
+        ToggleMark(mark) => model.toggle_mark(*mark),
+        ToggleArrow(from, to) => model.toggle_arrow(*from, *to),
+        LoadEvalParams => match config::load_eval_params() {
+            Ok(params) => *model.eval_params.borrow_mut() = params,
+            Err(e) => model
+                .log
+                .error(format!("Failed to load evaluation parameters: {}", e)),
+        },
+        SaveEvalParams => {
+            if let Err(e) = config::save_eval_params(&model.eval_params.borrow()) {
+                model
+                    .log
+                    .error(format!("Failed to save evaluation parameters: {}", e));
+            }
+        }
         Quit => unreachable!(),
     }
 }
 
+/// Picks a piece and where to move it while the computer is thinking. The move is only checked
+/// once the computer has moved, since that can change what is legal.
+fn handle_premove_click(model: &mut Model, clicked: FieldCoord) {
+    model.illegal_move = None;
+    if clicked.color() == model.game.board.turn {
+        model.clear_selection();
+        model.premove = None;
+        return;
+    }
+    match model.selected_piece {
+        Some(selected) if selected == clicked => model.clear_selection(),
+        _ if model.game.board.is_piece_on_field(clicked) => {
+            model.selected_piece = Some(clicked);
+            model.premove = None;
+        }
+        Some(selected) => {
+            model.premove = Some(Move::move_from_field(selected, clicked));
+            model.clear_selection();
+        }
+        None => model.premove = None,
+    }
+}
+
+/// Plays the move the player entered while the computer was thinking, now that it is their turn.
+fn play_premove(model: &mut Model) {
+    if let Some(mv) = model.premove.take() {
+        if model.current_player() == PlayerKind::Human && !model.is_game_over() {
+            model.illegal_move = play_human_move(model, mv).err();
+        }
+    }
+}
+
 fn handle_click(model: &mut Model, clicked: FieldCoord) {
+    model.illegal_move = None;
     match model.selected_piece {
         Some(selected) => {
-            if clicked.color() != model.board.turn || selected == clicked {
+            if clicked.color() != model.game.board.turn || selected == clicked {
                 model.clear_selection();
-            } else if model.board.is_piece_on_field(clicked) {
+            } else if model.game.board.is_piece_on_field(clicked) {
                 model.selected_piece = Some(clicked);
             } else {
-                model.try_move(Move::move_from_field(selected, clicked));
+                model.illegal_move =
+                    play_human_move(model, Move::move_from_field(selected, clicked)).err();
                 model.clear_selection();
             }
         }
         None => {
-            if model.exchanging && model.try_move(Move::exchange_from_field(clicked)) {
-                model.exchanging = false;
-            } else if !model.exchanging
-                && clicked.color() == model.board.turn
-                && model.board.is_piece_on_field(clicked)
+            if model.exchanging {
+                match play_human_move(model, Move::exchange_from_field(clicked)) {
+                    Ok(()) => model.exchanging = false,
+                    Err(illegal) => model.illegal_move = Some(illegal),
+                }
+            } else if clicked.color() == model.game.board.turn
+                && model.game.board.is_piece_on_field(clicked)
             {
                 model.selected_piece = Some(clicked);
             }
@@ -15,33 +15,269 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
 use imgui::{MouseButton, Ui};
 
+use crate::ai::score_text;
 use crate::model::bitboard::BitBoardExt;
-use crate::model::{FieldCoord, GameType, Model, Move};
+use crate::model::{
+    BoardBackground, Color, FieldCoord, GameType, Hint, Mark, Model, Move, Outcome,
+};
 use crate::view::board_parts::*;
 use crate::view::vec2::Vec2;
 use crate::view::Event;
 
 const SQRT_3: f32 = 1.732_050_8;
 
-// Color format is 0xaa_bb_gg_rr
-const SELECT_HIGHLIGHT: u32 = 0xcc_35_bf_ff;
-const LAST_MOVE_HIGHLIGHT: u32 = 0xc3_49_f8_f2;
-/// The highlight for a piece capture by surrounding or exchanging.
-const CAPTURE_HIGHLIGHT: u32 = 0xcf_40_40_ff;
-
 /// The alpha used for a removed hex and any highlights on it.
 const REMOVED_HEX_ALPHA: u8 = 0x50;
 const EXTANT_HEX_ALPHA: u8 = 0xff;
 
+/// How long the last move takes to slide its piece across and fade out what it removed.
+const ANIMATION_DURATION: Duration = Duration::from_millis(150);
+/// How many centipieces ahead a player must be for the eval bar to give them about three quarters
+/// of its length.
+const EVAL_BAR_SCALE: f32 = 200.0;
+
+/// How many of the analysis search's best moves are drawn on the board in analysis mode.
+const ANALYSIS_ARROWS: usize = 3;
+/// Arrows for moves scoring this many centipieces below the best move are drawn thinnest.
+const ANALYSIS_SCORE_RANGE: f32 = 100.0;
+/// The alpha used for analysis arrows, so the pieces under them still show through.
+const ANALYSIS_ARROW_ALPHA: u8 = 0xc0;
+/// The alpha used for the pieces that can be exchanged for, other than the one under the cursor.
+const EXCHANGE_TARGET_ALPHA: u8 = 0x70;
+/// The alpha used to preview what the move under the cursor would capture.
+const PREVIEW_ALPHA: u8 = 0x80;
+/// The alpha of the copy of the selected piece drawn where the cursor would move it.
+const GHOST_PIECE_ALPHA: u8 = 0x60;
+/// How long a piece has to be held down on to exchange for it.
+const LONG_PRESS_DURATION: Duration = Duration::from_millis(500);
+/// The gap between the lines of a textured board background.
+const BACKGROUND_LINE_SPACING: f32 = 6.0;
+
+/// The gap between the captured tiles in a player's tile tray.
+const TILE_TRAY_SPACING: f32 = 3.0;
+/// The gap between the captured pieces in a player's piece tray. The pieces are narrower at the
+/// top, so they can sit closer together than the tiles.
+const PIECE_TRAY_SPACING: f32 = 1.0;
+
+/// How long an auto-rotating board takes to turn to the next player, once the move has finished
+/// animating.
+const ROTATION_DURATION: Duration = Duration::from_millis(400);
+
+/// Returns how long ago the last move was played, or `None` if it shouldn't be animated.
+fn time_since_last_move(model: &Model) -> Option<Duration> {
+    if *model.reduce_motion.borrow() {
+        None
+    } else {
+        model.last_move_time.map(|time| Instant::now() - time)
+    }
+}
+
+/// Returns how far along the last move's animation is, from 0 to 1, or `None` if there is nothing
+/// to animate.
+fn animation_progress(model: &Model) -> Option<f32> {
+    let elapsed = time_since_last_move(model)?;
+    if elapsed < ANIMATION_DURATION {
+        Some(elapsed.as_millis() as f32 / ANIMATION_DURATION.as_millis() as f32)
+    } else {
+        None
+    }
+}
+
+/// Returns whether the board turns to face the player to move, which it only does when two people
+/// are playing each other.
+fn is_auto_rotating(model: &Model) -> bool {
+    *model.auto_rotate.borrow() && model.is_human_game()
+}
+
+/// Returns how far along the board is in turning to face the player to move, from 0 to 1, or
+/// `None` if it isn't turning.
+fn rotation_progress(model: &Model) -> Option<f32> {
+    if !is_auto_rotating(model) {
+        return None;
+    }
+    let elapsed = time_since_last_move(model)?;
+    if elapsed < ANIMATION_DURATION {
+        Some(0.0)
+    } else if elapsed < ANIMATION_DURATION + ROTATION_DURATION {
+        let elapsed = elapsed - ANIMATION_DURATION;
+        Some(elapsed.as_millis() as f32 / ROTATION_DURATION.as_millis() as f32)
+    } else {
+        None
+    }
+}
+
+/// Returns how far the board is turned, in radians.
+fn board_rotation(model: &Model) -> f32 {
+    let mut rotation = if *model.flip_board.borrow() { PI } else { 0.0 };
+    if is_auto_rotating(model) && model.game.board.turn == Color::Black {
+        rotation += PI;
+    }
+    match rotation_progress(model) {
+        // Ease in and out of the half turn from the last player's side
+        Some(progress) => rotation - PI * (1.0 - progress * progress * (3.0 - 2.0 * progress)),
+        None => rotation,
+    }
+}
+
+/// Returns whether White's side of the board is at the top of the screen. The board is drawn with
+/// White at the bottom until it is turned.
+pub fn is_white_on_top(model: &Model) -> bool {
+    board_rotation(model).cos() < 0.0
+}
+
+/// Returns whether the board is in the middle of an animation, so more frames are needed to
+/// finish it.
+pub fn is_animating(model: &Model) -> bool {
+    animation_progress(model).is_some() || rotation_progress(model).is_some()
+}
+
+/// Blends two alphas, giving `from` at a progress of 0 and `to` at 1.
+fn blend_alpha(from: u8, to: u8, progress: f32) -> u8 {
+    (f32::from(from) + (f32::from(to) - f32::from(from)) * progress) as u8
+}
+
+/// Returns how much of the eval bar goes to White, from 0 to 1.
+fn eval_bar_share(model: &Model) -> f32 {
+    match model.game.outcome {
+        Outcome::InProgress => match model.eval_bar_score() {
+            // A logistic curve, so that winning by more pieces fills the bar by less each time
+            Some(score) => 1.0 / (1.0 + (-f32::from(score) / EVAL_BAR_SCALE).exp()),
+            None => 0.5,
+        },
+        Outcome::Win(color) | Outcome::WinByResignation(color) | Outcome::WinOnTime(color) => {
+            match color {
+                Color::White => 1.0,
+                Color::Black => 0.0,
+            }
+        }
+        _ => 0.5,
+    }
+}
+
+/// Draws the tiles a player has captured as a row of small hexes. The row shrinks again as the
+/// tiles are spent on exchanges.
+pub fn tile_tray(ui: &Ui, model: &Model, color: Color, height: f32) {
+    let theme = Theme::get(*model.theme.borrow());
+    let count = model.game.board.hexes(color);
+    let size = height / SQRT_3;
+    let step = 2.0 * size + TILE_TRAY_SPACING;
+
+    let top_left = Vec2::from(ui.cursor_screen_pos());
+    for i in 0..count {
+        let center = top_left + Vec2::new(size + f32::from(i) * step, height / 2.0);
+        draw_loose_hex(ui, center, size, theme);
+    }
+
+    ui.dummy([f32::from(count) * step, height]);
+    if count > 0 && ui.is_item_hovered() {
+        ui.tooltip_text(match count {
+            1 => format!("{:?} has captured 1 tile", color),
+            _ => format!("{:?} has captured {} tiles", color, count),
+        });
+    }
+}
+
+/// Draws the pieces a player has captured as a row of small triangles in the other player's
+/// color.
+pub fn piece_tray(ui: &Ui, model: &Model, color: Color, height: f32) {
+    let theme = Theme::get(*model.theme.borrow());
+    let piece_colors = theme.piece_colors(*model.piece_colors.borrow());
+    let count = model.game.pieces_captured(color);
+    let size = 2.0 * height / SQRT_3;
+    let step = size + PIECE_TRAY_SPACING;
+
+    let top_left = Vec2::from(ui.cursor_screen_pos());
+    for i in 0..count {
+        // A triangle's center is a third of the way up from its base
+        let center = top_left + Vec2::new(size / 2.0 + f32::from(i) * step, height * 2.0 / 3.0);
+        draw_loose_piece(
+            ui,
+            center,
+            size,
+            piece_colors.get(color.switch())[0],
+            theme.piece_outline,
+        );
+    }
+
+    ui.dummy([f32::from(count) * step, height]);
+    if count > 0 && ui.is_item_hovered() {
+        ui.tooltip_text(match count {
+            1 => format!("{:?} has captured 1 piece", color),
+            _ => format!("{:?} has captured {} pieces", color, count),
+        });
+    }
+}
+
+/// Draws a bar showing who is ahead, split between the colors of the two players' pieces. White's
+/// share starts from whichever end is on White's side of the board.
+pub fn eval_bar(ui: &Ui, model: &Model, size: Vec2) {
+    let theme = Theme::get(*model.theme.borrow());
+    let piece_colors = theme.piece_colors(*model.piece_colors.borrow());
+    let share = eval_bar_share(model);
+
+    let top_left = Vec2::from(ui.cursor_screen_pos());
+    let bottom_right = top_left + size;
+    let white_on_top = is_white_on_top(model);
+    let split = if white_on_top {
+        top_left.y + size.y * share
+    } else {
+        bottom_right.y - size.y * share
+    };
+    let (top_color, bottom_color) = if white_on_top {
+        (Color::White, Color::Black)
+    } else {
+        (Color::Black, Color::White)
+    };
+
+    let draw_list = ui.get_window_draw_list();
+    draw_list
+        .add_rect(
+            top_left.into(),
+            [bottom_right.x, split],
+            piece_colors.get(top_color)[0],
+        )
+        .filled(true)
+        .build();
+    draw_list
+        .add_rect(
+            [top_left.x, split],
+            bottom_right.into(),
+            piece_colors.get(bottom_color)[0],
+        )
+        .filled(true)
+        .build();
+    let middle = top_left.y + size.y / 2.0;
+    draw_list
+        .add_line(
+            [top_left.x, middle],
+            [bottom_right.x, middle],
+            theme.piece_outline,
+        )
+        .build();
+    draw_list
+        .add_rect(top_left.into(), bottom_right.into(), theme.piece_outline)
+        .build();
+
+    ui.dummy(size.into());
+    if ui.is_item_hovered() {
+        if let (Outcome::InProgress, Some(score)) = (model.game.outcome, model.eval_bar_score()) {
+            ui.tooltip_text(format!("{} for White", score_text(score)));
+        }
+    }
+}
+
 pub fn board(ui: &Ui, model: &Model, size: Vec2) -> Option<Event> {
     let mouse_click = ui.is_mouse_clicked(MouseButton::Left);
     let mouse_pos = Vec2::from(ui.io().mouse_pos);
     let cursor_pos = Vec2::from(ui.cursor_screen_pos());
 
-    let side_len = match model.game_type {
-        GameType::Laurentius => {
+    let side_len = match model.game.rules.game_type {
+        GameType::Laurentius | GameType::Random(_) => {
             // hex_spacing  =          m * side_len + b
             // board_width  =          8 * side_len + 6 * SQRT_3 * hex_spacing
             // board_height = 5 * SQRT_3 * side_len +          4 * hex_spacing
@@ -62,72 +298,392 @@ pub fn board(ui: &Ui, model: &Model, size: Vec2) -> Option<Event> {
             size_width.min(size_height)
         }
     };
-    let origin = cursor_pos + size / 2.0;
+    let zoom = model.board_zoom.borrow_mut().clamp_offset(size.into());
+    let theme = Theme::get(*model.theme.borrow());
+    let layout = Layout {
+        origin: cursor_pos + size / 2.0 + Vec2::from(zoom.offset),
+        size: side_len * zoom.scale,
+        rotation: board_rotation(model),
+        theme,
+        piece_colors: theme.piece_colors(*model.piece_colors.borrow()),
+        piece_style: *model.piece_style.borrow(),
+        piece_outline: *model.piece_outline.borrow(),
+        highlights: Highlights::get(*model.highlight_palette.borrow()),
+    };
+    // Fields zoomed out of the board's space can't be clicked
+    let area_end = cursor_pos + size;
+    let mouse_pos = Some(mouse_pos).filter(|pos| {
+        pos.x >= cursor_pos.x && pos.y >= cursor_pos.y && pos.x < area_end.x && pos.y < area_end.y
+    });
+
+    // Zoomed in, the board is cut off at the edges of its space
+    let mut event = None;
+    ui.get_window_draw_list()
+        .with_clip_rect_intersect(cursor_pos.into(), area_end.into(), || {
+            draw_background(ui, model, cursor_pos, area_end, theme);
+            event = draw_board(ui, model, layout, mouse_pos, mouse_click);
+        });
+    ui.dummy(size.into());
+    event
+}
+
+/// Fills the board's space with the background the player picked, if any. It stays put while the
+/// board is zoomed or moved.
+fn draw_background(ui: &Ui, model: &Model, top_left: Vec2, bottom_right: Vec2, theme: &Theme) {
+    let background = *model.board_background.borrow();
+    if background == BoardBackground::None {
+        return;
+    }
+    let draw_list = ui.get_window_draw_list();
+    draw_list
+        .add_rect(top_left.into(), bottom_right.into(), theme.board_background)
+        .filled(true)
+        .build();
+    if background == BoardBackground::Textured {
+        // Lines running down to the left, far enough apart to cover the space once clipped
+        let size = bottom_right - top_left;
+        let mut x = top_left.x;
+        while x < bottom_right.x + size.y {
+            draw_list
+                .add_line(
+                    [x, top_left.y],
+                    [x - size.y, bottom_right.y],
+                    theme.board_texture,
+                )
+                .build();
+            x += BACKGROUND_LINE_SPACING;
+        }
+    }
+}
 
-    let extant_hexes = model.board.extant_hexes();
+/// Draws the board with everything on it, and returns the event for clicking it, if any.
+fn draw_board(
+    ui: &Ui,
+    model: &Model,
+    layout: Layout,
+    mouse_pos: Option<Vec2>,
+    mouse_click: bool,
+) -> Option<Event> {
+    let highlights = layout.highlights;
+    // When looking back at an earlier position, only that position and the move that led to it
+    // are drawn, with nothing to play
+    let reviewing = model.replay_ply.is_some();
+    let (shown_board, shown_move) = model.shown_position();
+    let progress = if reviewing {
+        None
+    } else {
+        animation_progress(model)
+    };
 
-    for &hex in &extant_hexes {
-        draw_hex(ui, EXTANT_HEX_ALPHA, hex, origin, side_len);
+    for hex in shown_board.hexes_iter() {
+        draw_hex(ui, EXTANT_HEX_ALPHA, hex, &layout);
     }
 
-    if let Some(ref mv) = model.last_move {
+    if let Some(ref mv) = shown_move {
+        let removed_hex_alpha = match progress {
+            Some(progress) => blend_alpha(EXTANT_HEX_ALPHA, REMOVED_HEX_ALPHA, progress),
+            None => REMOVED_HEX_ALPHA,
+        };
         for &hex in &mv.removed_hexes {
-            draw_hex(ui, REMOVED_HEX_ALPHA, hex, origin, side_len);
+            draw_hex(ui, removed_hex_alpha, hex, &layout);
         }
 
         for &piece in &mv.removed_pieces {
-            let color = if model.board.is_hex_extant(piece.to_hex().to_index()) {
-                CAPTURE_HIGHLIGHT
+            let color = if shown_board.is_hex_extant(piece.to_hex().to_index()) {
+                highlights.capture
             } else {
-                set_alpha(CAPTURE_HIGHLIGHT, REMOVED_HEX_ALPHA)
+                set_alpha(highlights.capture, REMOVED_HEX_ALPHA)
             };
-            draw_field(ui, color, piece, origin, side_len);
+            draw_field(ui, color, piece, &layout);
         }
 
         if let Move::Move(from, to, color) = mv.mv {
-            let from_color = if model.board.is_hex_extant(from.to_index()) {
-                LAST_MOVE_HIGHLIGHT
+            let from_color = if shown_board.is_hex_extant(from.to_index()) {
+                highlights.last_move
             } else {
-                set_alpha(LAST_MOVE_HIGHLIGHT, REMOVED_HEX_ALPHA)
+                set_alpha(highlights.last_move, REMOVED_HEX_ALPHA)
             };
 
             let from = FieldCoord::from_bitboard(from, color);
-            draw_field(ui, from_color, from, origin, side_len);
+            draw_field(ui, from_color, from, &layout);
 
             let to = FieldCoord::from_bitboard(to, color);
-            draw_field(ui, LAST_MOVE_HIGHLIGHT, to, origin, side_len);
+            draw_field(ui, highlights.last_move, to, &layout);
+        }
+    }
+
+    for &(mark, color) in &model.marks {
+        let color = highlights.marks[color];
+        match mark {
+            Mark::Field(field) => draw_field(ui, color, field, &layout),
+            Mark::Hex(hex) => {
+                for i in 0..6 {
+                    draw_field(ui, color, hex.to_field(i), &layout);
+                }
+            }
+        }
+    }
+
+    if *model.show_threats.borrow() && !model.game.is_game_over() && !reviewing {
+        let board = &model.game.board;
+        let falling_tiles = board.tiles_about_to_fall();
+        for hex in board.hexes_iter() {
+            if falling_tiles & (0b111 << (hex.to_index() * 3)) != 0 {
+                for i in 0..6 {
+                    draw_field(ui, highlights.falling_tile, hex.to_field(i), &layout);
+                }
+            }
+        }
+        let turn = board.turn;
+        for piece in board.threatened_pieces(turn).iter() {
+            let coord = FieldCoord::from_bitboard(piece, turn);
+            draw_field(ui, highlights.threat, coord, &layout);
         }
     }
 
+    if let Some(Hint::Move(mv)) = model.hint.filter(|_| !reviewing) {
+        match mv {
+            Move::Move(from, to, color) => {
+                let from = FieldCoord::from_bitboard(from, color);
+                draw_field(ui, highlights.hint, from, &layout);
+
+                let to = FieldCoord::from_bitboard(to, color);
+                draw_field_dot(ui, highlights.hint, to, &layout);
+            }
+            Move::Exchange(field, color) => {
+                let field = FieldCoord::from_bitboard(field, color);
+                draw_field(ui, highlights.hint, field, &layout);
+            }
+        }
+    }
+
+    // A move entered while the computer is thinking is shown faintly until it is played
+    if let Some(Move::Move(from, to, color)) = model.premove.filter(|_| !reviewing) {
+        let premove_color = set_alpha(highlights.select, PREVIEW_ALPHA);
+        draw_field(
+            ui,
+            premove_color,
+            FieldCoord::from_bitboard(from, color),
+            &layout,
+        );
+        draw_field(
+            ui,
+            premove_color,
+            FieldCoord::from_bitboard(to, color),
+            &layout,
+        );
+    }
+
     if let Some(coord) = model.selected_piece {
-        draw_field(ui, SELECT_HIGHLIGHT, coord, origin, side_len);
-        for coord in model.board.available_moves_for_piece(coord) {
-            draw_field_dot(ui, SELECT_HIGHLIGHT, coord, origin, side_len);
+        draw_field(ui, highlights.select, coord, &layout);
+        for coord in model.game.board.available_moves_for_piece(coord) {
+            draw_field_dot(ui, highlights.select, coord, &layout);
+        }
+    }
+
+    // In beginner mode, selecting a piece also shows the moves of all the others, and which of
+    // them are stuck
+    let mut stuck_pieces = vec![];
+    if *model.beginner_mode.borrow() && model.selected_piece.is_some() && !reviewing {
+        let board = &model.game.board;
+        let other_move_color = set_alpha(highlights.select, PREVIEW_ALPHA);
+        for piece in board.pieces_iter(board.turn) {
+            let moves: Vec<_> = board
+                .available_moves_for_piece(piece)
+                .into_iter()
+                .filter(|&to| board.can_apply_move(&Move::move_from_field(piece, to)))
+                .collect();
+            if moves.is_empty() {
+                stuck_pieces.push(piece);
+            } else if Some(piece) != model.selected_piece {
+                for to in moves {
+                    draw_field_dot(ui, other_move_color, to, &layout);
+                }
+            }
+        }
+    }
+
+    let field_under_cursor = mouse_pos
+        .and_then(|pos| pixel_to_field(pos, &layout))
+        .filter(|field| shown_board.is_hex_extant(field.to_hex().to_index()));
+    let hover_field = field_under_cursor.filter(|_| !reviewing);
+
+    // Preview the tiles and pieces that moving the selected piece to the field under the cursor
+    // would remove, by playing the move on a copy of the board
+    let mut ghost_piece = None;
+    if let (Some(from), Some(to)) = (model.selected_piece, hover_field) {
+        let mv = Move::move_from_field(from, to);
+        if model.game.board.can_apply_move(&mv) {
+            ghost_piece = Some(to);
+            let mut preview = model.game.board;
+            let annotated = preview.annotated_apply_move(&mv);
+            for &hex in &annotated.removed_hexes {
+                for i in 0..6 {
+                    draw_field(ui, highlights.falling_tile, hex.to_field(i), &layout);
+                }
+            }
+            for piece in annotated.captured_pieces() {
+                draw_field(
+                    ui,
+                    set_alpha(highlights.capture, PREVIEW_ALPHA),
+                    piece,
+                    &layout,
+                );
+            }
+        }
+    }
+
+    // Any of the other player's pieces can be exchanged for, so they are all marked, and the one
+    // under the cursor more strongly
+    let is_exchange_target = |field: FieldCoord| {
+        field.color() != model.game.board.turn && model.game.board.is_piece_on_field(field)
+    };
+    if model.exchanging {
+        for coord in model.game.board.pieces_iter(model.game.board.turn.switch()) {
+            let color = if hover_field == Some(coord) {
+                highlights.capture
+            } else {
+                set_alpha(highlights.capture, EXCHANGE_TARGET_ALPHA)
+            };
+            draw_field(ui, color, coord, &layout);
         }
     }
 
-    let hover_field = pixel_to_field(mouse_pos, origin, side_len)
-        .filter(|field| model.board.is_hex_extant(field.to_hex().to_index()));
+    // While the last move is animated, its piece slides over from where it came from and the
+    // pieces it removed fade out
+    let sliding = match (progress, &shown_move) {
+        (Some(progress), Some(mv)) => {
+            for &piece in &mv.removed_pieces {
+                let alpha = blend_alpha(0xff, 0, progress);
+                draw_faded_piece(ui, piece, alpha, &layout);
+            }
+            match mv.mv {
+                Move::Move(from, to, color) => Some((
+                    FieldCoord::from_bitboard(from, color),
+                    FieldCoord::from_bitboard(to, color),
+                    progress,
+                )),
+                Move::Exchange(..) => None,
+            }
+        }
+        _ => None,
+    };
 
-    if let Some(coord) = hover_field {
-        if model.exchanging
-            && coord.color() != model.board.turn
-            && model.board.is_piece_on_field(coord)
-        {
-            draw_field(ui, CAPTURE_HIGHLIGHT, coord, origin, side_len);
+    for &color in &[Color::White, Color::Black] {
+        for coord in shown_board.pieces_iter(color) {
+            match sliding {
+                Some((_, to, _)) if to == coord => {}
+                _ => draw_piece(ui, coord, &layout),
+            }
         }
     }
+    if let Some((from, to, progress)) = sliding {
+        draw_sliding_piece(ui, from, to, progress, &layout);
+    }
+    if let Some(to) = ghost_piece {
+        draw_faded_piece(ui, to, GHOST_PIECE_ALPHA, &layout);
+    }
 
-    for hex in &extant_hexes {
-        for f in 0..6 {
-            let coord = hex.to_field(f);
-            if model.board.is_piece_on_field(coord) {
-                draw_piece(ui, coord, origin, side_len);
+    for &piece in &stuck_pieces {
+        draw_field(ui, layout.theme.dim, piece, &layout);
+    }
+
+    // Grey out everything that can't be exchanged for, so it's clear the next click exchanges
+    if model.exchanging {
+        for hex in model.game.board.hexes_iter() {
+            for i in 0..6 {
+                let field = hex.to_field(i);
+                if !is_exchange_target(field) {
+                    draw_field(ui, layout.theme.dim, field, &layout);
+                }
             }
         }
     }
 
-    ui.dummy(size.into());
+    if *model.analysis_mode.borrow() && !model.game.is_game_over() && !reviewing {
+        let moves = model.analysis_moves();
+        let color = set_alpha(highlights.hint, ANALYSIS_ARROW_ALPHA);
+        if let Some(&(_, best_score)) = moves.first() {
+            // Draw the best move last, so it is on top
+            for &(mv, score) in moves.iter().take(ANALYSIS_ARROWS).rev() {
+                let behind = f32::from(best_score - score) / ANALYSIS_SCORE_RANGE;
+                let weight = (1.0 - behind).max(0.25);
+                match mv {
+                    Move::Move(from, to, color_moved) => draw_arrow(
+                        ui,
+                        color,
+                        FieldCoord::from_bitboard(from, color_moved),
+                        FieldCoord::from_bitboard(to, color_moved),
+                        weight,
+                        &layout,
+                    ),
+                    Move::Exchange(field, color_taken) => draw_field_ring(
+                        ui,
+                        color,
+                        FieldCoord::from_bitboard(field, color_taken),
+                        weight,
+                        &layout,
+                    ),
+                }
+            }
+        }
+    }
+
+    // The player's arrows go above everything else, along with the one being drawn
+    let arrow_color = highlights.marks[0];
+    for &(from, to) in &model.arrows {
+        draw_arrow(ui, arrow_color, from, to, 1.0, &layout);
+    }
+    if let (Some(from), Some(to)) = (*model.arrow_start.borrow(), field_under_cursor) {
+        if ui.is_mouse_down(MouseButton::Right) {
+            draw_arrow(
+                ui,
+                set_alpha(arrow_color, PREVIEW_ALPHA),
+                from,
+                to,
+                1.0,
+                &layout,
+            );
+        }
+    }
 
+    // Holding a finger or the mouse button down on one of the other player's pieces exchanges for
+    // it, so that touch screens don't need the Exchange button
+    let mut press_start = model.press_start.borrow_mut();
+    if mouse_click {
+        *press_start = hover_field.map(|field| (field, Instant::now()));
+    }
+    if ui.is_mouse_released(MouseButton::Left) {
+        if let (Some((pressed, at)), Some(field)) = (press_start.take(), hover_field) {
+            if pressed == field
+                && at.elapsed() >= LONG_PRESS_DURATION
+                && is_exchange_target(field)
+                && model.game.board.can_exchange()
+            {
+                return Some(Event::ExchangeAt(field));
+            }
+        }
+    }
+
+    // Right-clicking marks a field, or its whole tile with Shift held, and dragging to another
+    // field draws an arrow. Both work even while looking back.
+    let mut arrow_start = model.arrow_start.borrow_mut();
+    if ui.is_mouse_clicked(MouseButton::Right) {
+        *arrow_start = field_under_cursor;
+    }
+    if ui.is_mouse_released(MouseButton::Right) {
+        match (arrow_start.take(), field_under_cursor) {
+            (Some(from), Some(to)) if from != to => return Some(Event::ToggleArrow(from, to)),
+            (Some(field), Some(_)) => {
+                let mark = if ui.io().key_shift {
+                    Mark::Hex(field.to_hex())
+                } else {
+                    Mark::Field(field)
+                };
+                return Some(Event::ToggleMark(mark));
+            }
+            _ => {}
+        }
+    }
     hover_field.filter(|_| mouse_click).map(Event::Click)
 }
@@ -17,116 +17,416 @@
 
 use imgui::Ui;
 
-use crate::model::{Color, ColorMap, FieldCoord, HexCoord};
+use crate::model::{
+    Color, ColorMap, ColorTheme, FieldCoord, HexCoord, HighlightPalette, PieceOutline, PieceStyle,
+    MARK_COLORS,
+};
 use crate::view::vec2::Vec2;
 
 const SQRT_3: f32 = 1.732_050_8;
 
+/// The colors of high-visibility pieces
+const HIGH_VISIBILITY_PIECE_COLORS: ColorMap<u32> = ColorMap {
+    white: 0xff_ff_ff_ff,
+    black: 0xff_00_00_00,
+};
+
 // Slope and y-intercept
 pub const HEX_SPACING_COEFF: (f32, f32) = (0.0331, 1.45);
 
-// Color format is 0xaa_bb_gg_rr
-const FIELD_COLORS: ColorMap<u32> = ColorMap {
-    white: 0xff_e9_ef_f3,
-    black: 0xff_78_99_83,
+/// The colors the board and the windows around it are drawn with.
+pub struct Theme {
+    // Color format is 0xaa_bb_gg_rr
+    field_colors: ColorMap<u32>,
+    pub piece_outline: u32,
+    /// The colors of each side of a piece
+    piece_colors: ColorMap<[u32; 3]>,
+    /// The tint over the parts of the board that can't be clicked, such as while exchanging
+    pub dim: u32,
+    /// The color behind the windows, as sRGB components
+    pub background: [f32; 4],
+    /// The color behind the board when it has a background of its own
+    pub board_background: u32,
+    /// The lines drawn over `board_background` to give it a texture
+    pub board_texture: u32,
+}
+
+const LIGHT_THEME: Theme = Theme {
+    field_colors: ColorMap {
+        white: 0xff_e9_ef_f3,
+        black: 0xff_78_99_83,
+    },
+    piece_outline: 0xff_23_23_23,
+    piece_colors: ColorMap {
+        white: [
+            // Light, medium, and dark colors
+            0xff_f8_f8_f8,
+            0xff_e0_e0_e0,
+            0xff_bd_bd_bd,
+        ],
+        black: [
+            // Medium, light, and dark colors
+            0xff_68_68_68,
+            0xff_88_88_88,
+            0xff_58_58_58,
+        ],
+    },
+    dim: 0xa0_d0_d0_d0,
+    background: [1.0, 1.0, 1.0, 1.0],
+    // A little darker than the white fields so they stand out
+    board_background: 0xff_c8_cf_d4,
+    board_texture: 0x1c_00_00_00,
 };
-const PIECE_OUTLINE: u32 = 0xff_23_23_23;
-const PIECE_COLORS: ColorMap<[u32; 3]> = ColorMap {
-    white: [
-        // Light, medium, and dark colors
-        0xff_f8_f8_f8,
-        0xff_e0_e0_e0,
-        0xff_bd_bd_bd,
-    ],
-    black: [
-        // Medium, light, and dark colors
-        0xff_68_68_68,
-        0xff_88_88_88,
-        0xff_58_58_58,
-    ],
+
+const DARK_THEME: Theme = Theme {
+    field_colors: ColorMap {
+        white: 0xff_7a_76_72,
+        black: 0xff_3e_52_45,
+    },
+    piece_outline: 0xff_0c_0c_0c,
+    piece_colors: ColorMap {
+        white: [0xff_f0_f0_f0, 0xff_d4_d4_d4, 0xff_b0_b0_b0],
+        black: [0xff_30_30_30, 0xff_48_48_48, 0xff_22_22_22],
+    },
+    dim: 0xa0_1c_1c_1c,
+    background: [0.11, 0.11, 0.12, 1.0],
+    board_background: 0xff_2c_2a_28,
+    board_texture: 0x14_ff_ff_ff,
 };
 
+impl Theme {
+    pub fn get(theme: ColorTheme) -> &'static Self {
+        match theme {
+            ColorTheme::Light => &LIGHT_THEME,
+            ColorTheme::Dark => &DARK_THEME,
+        }
+    }
+    /// Returns the colors of each side of a player's pieces, shaded from the player's custom piece
+    /// color if they picked one.
+    pub fn piece_colors(&self, custom: ColorMap<Option<[u8; 3]>>) -> ColorMap<[u32; 3]> {
+        let colors = |color| match custom.get(color) {
+            Some(rgb) => shade_piece(rgb, color),
+            None => self.piece_colors.get(color),
+        };
+        ColorMap::new(colors(Color::White), colors(Color::Black))
+    }
+}
+
+/// Returns the colors of each side of a piece with the given medium color. Like the built-in
+/// colors, the sides facing up and left are lighter and the one facing down is darker.
+fn shade_piece(rgb: [u8; 3], color: Color) -> [u32; 3] {
+    let shade = |f: &dyn Fn(u8) -> u8| {
+        let [r, g, b] = rgb;
+        0xff_00_00_00 | u32::from(f(b)) << 16 | u32::from(f(g)) << 8 | u32::from(f(r))
+    };
+    let light = shade(&|c| c.saturating_add(0x1f));
+    let medium = shade(&|c| c);
+    let dark = shade(&|c| (u16::from(c) * 85 / 100) as u8);
+    match color {
+        Color::White => [light, medium, dark],
+        Color::Black => [medium, light, dark],
+    }
+}
+
+/// The colors of the highlights drawn over fields.
+pub struct Highlights {
+    // Color format is 0xaa_bb_gg_rr
+    pub select: u32,
+    pub last_move: u32,
+    /// The highlight for a piece captured by surrounding or exchanging
+    pub capture: u32,
+    /// The highlight for the move suggested by a hint
+    pub hint: u32,
+    /// The highlight for a piece the other player can capture next move
+    pub threat: u32,
+    /// The tint for a tile that will be removed once its pieces leave
+    pub falling_tile: u32,
+    /// The colors the player can mark fields and tiles with
+    pub marks: [u32; MARK_COLORS],
+}
+
+const STANDARD_HIGHLIGHTS: Highlights = Highlights {
+    select: 0xcc_35_bf_ff,
+    last_move: 0xc3_49_f8_f2,
+    capture: 0xcf_40_40_ff,
+    hint: 0xc3_8c_d6_4c,
+    threat: 0xc3_30_8c_ff,
+    falling_tile: 0x60_30_8c_ff,
+    marks: [0xa0_1b_78_15, 0xa0_20_20_c8, 0xa0_c8_60_00],
+};
+
+// The colorblind palettes use the Okabe-Ito colors, which were picked to stay distinct under the
+// common kinds of color blindness. Red and green are the colors that deuteranopes and protanopes
+// confuse, so selections and hints are blue instead, and the red of captures and the orange of
+// threats are far enough apart in lightness to tell apart. Protanopes see reds as darker, so their
+// captures are a brighter orange and threats a reddish purple.
+
+const DEUTERANOPIA_HIGHLIGHTS: Highlights = Highlights {
+    select: 0xcc_b2_72_00,
+    last_move: 0xc3_42_e4_f0,
+    capture: 0xcf_00_5e_d5,
+    hint: 0xc3_e9_b4_56,
+    threat: 0xc3_00_9f_e6,
+    falling_tile: 0x60_00_9f_e6,
+    marks: [0xa0_73_9e_00, 0xa0_00_5e_d5, 0xa0_e9_b4_56],
+};
+
+const PROTANOPIA_HIGHLIGHTS: Highlights = Highlights {
+    select: 0xcc_b2_72_00,
+    last_move: 0xc3_42_e4_f0,
+    capture: 0xcf_00_9f_e6,
+    hint: 0xc3_e9_b4_56,
+    threat: 0xc3_a7_79_cc,
+    falling_tile: 0x60_a7_79_cc,
+    marks: [0xa0_73_9e_00, 0xa0_00_9f_e6, 0xa0_e9_b4_56],
+};
+
+impl Highlights {
+    pub fn get(palette: HighlightPalette) -> &'static Self {
+        match palette {
+            HighlightPalette::Standard => &STANDARD_HIGHLIGHTS,
+            HighlightPalette::Deuteranopia => &DEUTERANOPIA_HIGHLIGHTS,
+            HighlightPalette::Protanopia => &PROTANOPIA_HIGHLIGHTS,
+        }
+    }
+}
+
+/// Where and how the board is drawn on the screen.
+#[derive(Clone, Copy)]
+pub struct Layout {
+    /// The pixel at the center of the center hex
+    pub origin: Vec2,
+    /// The side length of a hex
+    pub size: f32,
+    /// How far the board is turned clockwise around `origin`, in radians
+    pub rotation: f32,
+    pub theme: &'static Theme,
+    pub highlights: &'static Highlights,
+    /// The colors of each side of a piece, from `Theme::piece_colors`
+    pub piece_colors: ColorMap<[u32; 3]>,
+    pub piece_style: PieceStyle,
+    pub piece_outline: PieceOutline,
+}
+
+impl Layout {
+    /// Moves a point on the unturned board to where it is drawn.
+    fn rotate(&self, p: Vec2) -> Vec2 {
+        self.origin + (p - self.origin).rotated(self.rotation)
+    }
+    /// Moves a point on the screen to where it would be on the unturned board.
+    fn unrotate(&self, p: Vec2) -> Vec2 {
+        self.origin + (p - self.origin).rotated(-self.rotation)
+    }
+}
+
 pub fn set_alpha(mut color: u32, alpha: u8) -> u32 {
     const ALPHA_MASK: u32 = 0xff_00_00_00;
     color &= !ALPHA_MASK;
     color | u32::from(alpha) << 24
 }
 
-pub fn draw_hex(ui: &Ui, alpha: u8, coord: HexCoord, origin: Vec2, size: f32) {
+pub fn draw_hex(ui: &Ui, alpha: u8, coord: HexCoord, layout: &Layout) {
     for i in 0..6 {
         let coord = coord.to_field(i);
-        let color = FIELD_COLORS.get(coord.color());
+        let color = layout.theme.field_colors.get(coord.color());
 
-        draw_field(ui, set_alpha(color, alpha), coord, origin, size);
+        draw_field(ui, set_alpha(color, alpha), coord, layout);
     }
 }
 
-pub fn draw_field(ui: &Ui, color: u32, coord: FieldCoord, origin: Vec2, size: f32) {
-    let (v1, v2, v3) = field_vertexes(coord, origin, size);
+/// Draws a hex off the board, such as a captured tile, with its center at `center`. Unlike
+/// `draw_hex`, it isn't turned with the board.
+pub fn draw_loose_hex(ui: &Ui, center: Vec2, size: f32, theme: &Theme) {
+    let height = size * SQRT_3 / 2.0;
+    let corners = [
+        center + Vec2::new(-size, 0.0),
+        center + Vec2::new(-size / 2.0, -height),
+        center + Vec2::new(size / 2.0, -height),
+        center + Vec2::new(size, 0.0),
+        center + Vec2::new(size / 2.0, height),
+        center + Vec2::new(-size / 2.0, height),
+    ];
+    let draw_list = ui.get_window_draw_list();
+    // Fields are numbered clockwise from the top, like those on the board
+    for f in 0..6 {
+        let color = if f % 2 == 0 {
+            Color::Black
+        } else {
+            Color::White
+        };
+        draw_list
+            .add_triangle(
+                center.into(),
+                corners[(f + 1) % 6].into(),
+                corners[(f + 2) % 6].into(),
+                theme.field_colors.get(color),
+            )
+            .filled(true)
+            .build();
+    }
+}
+
+/// Draws a piece off the board, such as a captured one, as a triangle pointing up with its
+/// center at `center`.
+pub fn draw_loose_piece(ui: &Ui, center: Vec2, size: f32, fill: u32, outline: u32) {
+    let height = size * SQRT_3 / 2.0;
+    let top = (center + Vec2::new(0.0, -height * 2.0 / 3.0)).into();
+    let right = (center + Vec2::new(size / 2.0, height / 3.0)).into();
+    let left = (center + Vec2::new(-size / 2.0, height / 3.0)).into();
+
+    let draw_list = ui.get_window_draw_list();
+    draw_list
+        .add_triangle(top, right, left, fill)
+        .filled(true)
+        .build();
+    draw_list.add_triangle(top, right, left, outline).build();
+}
+
+pub fn draw_field(ui: &Ui, color: u32, coord: FieldCoord, layout: &Layout) {
+    let (v1, v2, v3) = field_vertexes(coord, layout);
     ui.get_window_draw_list()
         .add_triangle(v1.into(), v2.into(), v3.into(), color)
         .filled(true)
         .build();
 }
 
-pub fn draw_field_dot(ui: &Ui, color: u32, coord: FieldCoord, origin: Vec2, size: f32) {
-    let center = field_center(coord, origin, size);
+pub fn draw_field_dot(ui: &Ui, color: u32, coord: FieldCoord, layout: &Layout) {
+    let center = field_center(coord, layout);
     ui.get_window_draw_list()
-        .add_circle(center.into(), size / (4.0 * SQRT_3), color)
+        .add_circle(center.into(), layout.size / (4.0 * SQRT_3), color)
         .filled(true)
         .num_segments(15)
         .build();
 }
 
-pub fn draw_piece(ui: &Ui, coord: FieldCoord, origin: Vec2, size: f32) {
-    let (v1, v2, v3) = field_vertexes(coord, origin, size);
-    let center = field_center(coord, origin, size);
+/// Draws an arrow from the center of one field to the center of another. `weight` scales the
+/// arrow's thickness, from 0 to 1.
+pub fn draw_arrow(
+    ui: &Ui,
+    color: u32,
+    from: FieldCoord,
+    to: FieldCoord,
+    weight: f32,
+    layout: &Layout,
+) {
+    let start = field_center(from, layout);
+    let end = field_center(to, layout);
+    let length = (end - start).length();
+    if length == 0.0 {
+        return;
+    }
+    let direction = (end - start) / length;
+    let normal = Vec2::new(-direction.y, direction.x);
 
-    const SCALE: f32 = 0.75;
-    let v1 = (center + (v1 - center) * SCALE).into();
-    let v2 = (center + (v2 - center) * SCALE).into();
-    let v3 = (center + (v3 - center) * SCALE).into();
+    let thickness = layout.size * 0.12 * weight;
+    let head_length = (thickness * 2.5).min(length / 2.0);
+    let head_base = end - direction * head_length;
+
+    let draw_list = ui.get_window_draw_list();
+    draw_list
+        .add_line(start.into(), head_base.into(), color)
+        .thickness(thickness)
+        .build();
+    draw_list
+        .add_triangle(
+            (head_base + normal * thickness * 1.5).into(),
+            (head_base - normal * thickness * 1.5).into(),
+            end.into(),
+            color,
+        )
+        .filled(true)
+        .build();
+}
+
+/// Draws a ring around a field, for moves that don't go anywhere such as exchanges. `weight`
+/// scales the ring's thickness, from 0 to 1.
+pub fn draw_field_ring(ui: &Ui, color: u32, coord: FieldCoord, weight: f32, layout: &Layout) {
+    let center = field_center(coord, layout);
+    ui.get_window_draw_list()
+        .add_circle(center.into(), layout.size / (2.0 * SQRT_3), color)
+        .thickness(layout.size * 0.12 * weight)
+        .num_segments(15)
+        .build();
+}
+
+pub fn draw_piece(ui: &Ui, coord: FieldCoord, layout: &Layout) {
+    draw_piece_at(ui, coord, Vec2::new(0.0, 0.0), 0xff, layout);
+}
+
+/// Draws a piece `progress` of the way from one field to another. Both fields must have the same
+/// color, so the piece keeps pointing the same way.
+pub fn draw_sliding_piece(
+    ui: &Ui,
+    from: FieldCoord,
+    to: FieldCoord,
+    progress: f32,
+    layout: &Layout,
+) {
+    let offset = (field_center(to, layout) - field_center(from, layout)) * progress;
+    draw_piece_at(ui, from, offset, 0xff, layout);
+}
+
+/// Draws a piece with every color, including the outline, at the given alpha.
+pub fn draw_faded_piece(ui: &Ui, coord: FieldCoord, alpha: u8, layout: &Layout) {
+    draw_piece_at(ui, coord, Vec2::new(0.0, 0.0), alpha, layout);
+}
+
+fn draw_piece_at(ui: &Ui, coord: FieldCoord, offset: Vec2, alpha: u8, layout: &Layout) {
+    let (v1, v2, v3) = field_vertexes(coord, layout);
+    let (v1, v2, v3) = (v1 + offset, v2 + offset, v3 + offset);
+    let center = field_center(coord, layout) + offset;
+    let size = layout.size;
+
+    // High-visibility pieces fill more of their field and have a heavier outline
+    let (scale, outline_scale) = match layout.piece_style {
+        PieceStyle::HighVisibility => (0.85, 1.5),
+        PieceStyle::Shaded | PieceStyle::Flat => (0.75, 1.0),
+    };
+    let v1 = (center + (v1 - center) * scale).into();
+    let v2 = (center + (v2 - center) * scale).into();
+    let v3 = (center + (v3 - center) * scale).into();
     let center = center.into();
 
     // Linear equation derived by human testing and regression
     // TODO: Does this have to be adjusted by DPI factor, or is doubling the old value enough?
-    let outline_size = 2.0 * (0.032 * size - 0.535);
-
-    let colors = PIECE_COLORS.get_ref(coord.color());
+    let outline_size = 2.0 * (0.032 * size - 0.535) * outline_scale * layout.piece_outline.scale();
+
+    let shaded = layout.piece_colors.get(coord.color());
+    let colors = match layout.piece_style {
+        PieceStyle::Shaded => shaded,
+        // The first color is the one the trays and the eval bar use for the player
+        PieceStyle::Flat => [shaded[0]; 3],
+        PieceStyle::HighVisibility => [HIGH_VISIBILITY_PIECE_COLORS.get(coord.color()); 3],
+    };
     let draw_list = ui.get_window_draw_list();
     draw_list
-        .add_triangle(v1, v2, center, colors[0])
+        .add_triangle(v1, v2, center, set_alpha(colors[0], alpha))
         .filled(true)
         .build();
     draw_list
-        .add_triangle(v2, v3, center, colors[1])
+        .add_triangle(v2, v3, center, set_alpha(colors[1], alpha))
         .filled(true)
         .build();
     draw_list
-        .add_triangle(v3, v1, center, colors[2])
+        .add_triangle(v3, v1, center, set_alpha(colors[2], alpha))
         .filled(true)
         .build();
 
     draw_list
-        .add_triangle(v1, v2, v3, PIECE_OUTLINE)
+        .add_triangle(v1, v2, v3, set_alpha(layout.theme.piece_outline, alpha))
         .thickness(outline_size)
         .build();
 }
 
-fn field_center(coord: FieldCoord, origin: Vec2, size: f32) -> Vec2 {
-    let (v1, v2, v3) = field_vertexes(coord, origin, size);
-    let center_x = (v1.x + v2.x + v3.x) / 3.0;
-    let min_y = (v1.y).min(v2.y).min(v3.y);
-    let center_y = match coord.color() {
-        Color::White => min_y + size / SQRT_3,
-        Color::Black => min_y + size / (2.0 * SQRT_3),
-    };
-
-    Vec2::new(center_x, center_y)
+fn field_center(coord: FieldCoord, layout: &Layout) -> Vec2 {
+    // The fields are equilateral triangles, so their centers are their centroids
+    let (v1, v2, v3) = field_vertexes(coord, layout);
+    (v1 + v2 + v3) / 3.0
 }
 
-fn field_vertexes(coord: FieldCoord, origin: Vec2, size: f32) -> (Vec2, Vec2, Vec2) {
-    let center = hex_to_pixel(coord.to_hex(), origin, size);
+fn field_vertexes(coord: FieldCoord, layout: &Layout) -> (Vec2, Vec2, Vec2) {
+    let size = layout.size;
+    let center = hex_to_pixel(coord.to_hex(), layout.origin, size);
     let height = size * SQRT_3 / 2.0;
 
     let west = center + Vec2::new(-size, 0.0);
@@ -138,7 +438,7 @@ fn field_vertexes(coord: FieldCoord, origin: Vec2, size: f32) -> (Vec2, Vec2, Ve
     let southeast = center + Vec2::new(size / 2.0, height);
 
     // Vertexes are ordered clockwise for draw_piece to shade the sides.
-    match coord.f() {
+    let (v1, v2, v3) = match coord.f() {
         0 => (center, northwest, northeast),
         1 => (center, northeast, east),
         2 => (southeast, center, east),
@@ -146,7 +446,8 @@ fn field_vertexes(coord: FieldCoord, origin: Vec2, size: f32) -> (Vec2, Vec2, Ve
         4 => (southwest, west, center),
         5 => (west, northwest, center),
         _ => unreachable!(),
-    }
+    };
+    (layout.rotate(v1), layout.rotate(v2), layout.rotate(v3))
 }
 
 fn hex_spacing(size: f32) -> f32 {
@@ -165,7 +466,10 @@ fn hex_to_pixel(coord: HexCoord, origin: Vec2, size: f32) -> Vec2 {
 }
 
 // Algorithm based on http://www.redblobgames.com/grids/hexagons/#pixel-to-hex
-pub fn pixel_to_field(p: Vec2, origin: Vec2, size: f32) -> Option<FieldCoord> {
+pub fn pixel_to_field(p: Vec2, layout: &Layout) -> Option<FieldCoord> {
+    let p = layout.unrotate(p);
+    let Layout { origin, size, .. } = *layout;
+
     // Finding the hex is tricky because the hexes have gaps between them.
     // First, we find the rounded hex coordinate with a scaled up size that accounts for the gap.
 
@@ -20,28 +20,71 @@ mod board_parts;
 mod sys;
 mod vec2;
 
-use imgui::{Condition, ImStr, MenuItem, Slider, StyleVar, Ui, Window};
+use std::time::Duration;
 
-use self::board::board;
-pub use self::sys::run;
+use imgui::{
+    ChildWindow, ColorEdit, Condition, ImStr, ImString, MenuItem, ProgressBar, Slider, StyleVar,
+    Ui, Window,
+};
+
+use self::board::{board, eval_bar, is_white_on_top, piece_tray, tile_tray};
+pub use self::sys::{run, waker};
 use self::vec2::Vec2;
-use crate::model::{Color, ColorMap, GameType, Model, Player};
+use crate::ai::{clamp_param, Constraint, EngineKind, EvalParams, Solution, Strength, PARAM_RANGE};
+use crate::config;
+use crate::log::Level;
+use crate::model::rng::Rng;
+use crate::model::tutorial::{Tutorial, LESSONS};
+use crate::model::{
+    move_number, random_start, BlunderCheck, BoardBackground, BoardSize, Clock, Color, ColorMap,
+    ColorTheme, Confirmation, GameSetup, GameType, Handicap, HighlightPalette, Hint, Model,
+    MoveAnnotated, PieceOutline, PieceStyle, PlayerKind, ReplaySpeed, ReplayStep, SolveStatus,
+    TimeControl, UndoPolicy, UndoRequest, MAX_HANDICAP, SEARCH_DEPTHS, UI_SCALES,
+};
 use crate::update::Event;
 
+const EVAL_BAR_WIDTH: f32 = 16.0;
+/// The height of the line with a player's clock and captured tiles, including the spacing below it
+const PLAYER_LINE_HEIGHT: f32 = 21.0;
+/// The height of the captured pieces and tiles drawn beside a player's clock
+const TRAY_HEIGHT: f32 = 15.0;
+/// The height of the buttons for stepping through the game, including the spacing below them
+const REPLAY_LINE_HEIGHT: f32 = 24.0;
+const REPLAY_BUTTON_SIZE: [f32; 2] = [32.0, 20.0];
+/// How many of the latest moves are listed under the board
+const RECENT_MOVES: usize = 3;
+/// The height of the line listing the latest moves, including the spacing below it
+const RECENT_MOVES_LINE_HEIGHT: f32 = 25.0;
+/// The color of the clock whose time is running
+const RUNNING_CLOCK_COLOR: [f32; 4] = [0.9, 0.45, 0.0, 1.0];
+const LESSON_COMPLETE_COLOR: [f32; 4] = [0.2, 0.7, 0.2, 1.0];
+const LOG_ERROR_COLOR: [f32; 4] = [0.9, 0.2, 0.2, 1.0];
+const LOG_WARNING_COLOR: [f32; 4] = [0.9, 0.6, 0.0, 1.0];
+/// The longest seed that can be typed, with room for a leading '#'
+const SEED_INPUT_CAPACITY: usize = 18;
+
 pub fn draw(ui: &Ui, size: [f32; 2], model: &Model) -> Option<Event> {
     let mut event = None;
     let mut window_states = model.window_states.borrow_mut();
 
     ui.main_menu_bar(|| {
         ui.menu(im_str!("Game"), true, || {
-            MenuItem::new(im_str!("New game")).enabled(false).build(ui);
-
-            ui.menu(im_str!("Laurentius"), true, || {
-                player_options(ui, &mut event, GameType::Laurentius);
-            });
-            ui.menu(im_str!("Ocius"), true, || {
-                player_options(ui, &mut event, GameType::Ocius);
-            });
+            if MenuItem::new(im_str!("New game...")).build(ui) {
+                window_states.new_game = Some(new_game_setup(model));
+                window_states.new_game_seed.clear();
+            }
+            if MenuItem::new(im_str!("Play again"))
+                .shortcut(im_str!("Ctrl+N"))
+                .build(ui)
+            {
+                insert_if_empty(&mut event, play_again_event(model));
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Start a new game of the same kind with the same players. A random start \
+                     is drawn again.",
+                );
+            }
 
             ui.separator();
 
@@ -50,11 +93,51 @@ pub fn draw(ui: &Ui, size: [f32; 2], model: &Model) -> Option<Event> {
                 ui.tooltip_text("Any changes to the rules apply at the start of the next game.");
             }
 
-            MenuItem::new(im_str!("One tile to exchange"))
-                .build_with_ref(ui, &mut model.exchange_one_hex.borrow_mut());
+            ui.menu(im_str!("Draw without progress"), true, || {
+                let mut draw_after_plies = model.draw_after_plies.borrow_mut();
+                for &(option, label) in &[
+                    (None, im_str!("Never (official rules)")),
+                    (Some(60), im_str!("After 30 moves each")),
+                    (Some(100), im_str!("After 50 moves each")),
+                    (Some(200), im_str!("After 100 moves each")),
+                ] {
+                    if MenuItem::new(label)
+                        .selected(*draw_after_plies == option)
+                        .build(ui)
+                    {
+                        *draw_after_plies = option;
+                    }
+                }
+            });
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Whether the game is drawn after a number of moves without a capture, an \
+                     exchange or a tile being removed.",
+                );
+            }
+
+            ui.menu(im_str!("Undo"), true, || {
+                let mut undo_policy = model.undo_policy.borrow_mut();
+                for &(policy, label) in &[
+                    (UndoPolicy::Free, im_str!("Always allowed")),
+                    (
+                        UndoPolicy::ApprovalRequired,
+                        im_str!("Opponent must approve"),
+                    ),
+                    (UndoPolicy::Disabled, im_str!("Not allowed")),
+                ] {
+                    if MenuItem::new(label)
+                        .selected(*undo_policy == policy)
+                        .build(ui)
+                    {
+                        *undo_policy = policy;
+                    }
+                }
+            });
             if ui.is_item_hovered() {
                 ui.tooltip_text(
-                    "If selected, only one tile (rather than two) is needed to exchange for a piece."
+                    "Whether players may take back moves in games with a clock. The computer never \
+                     approves a takeback.",
                 );
             }
 
@@ -66,8 +149,11 @@ pub fn draw(ui: &Ui, size: [f32; 2], model: &Model) -> Option<Event> {
         });
 
         ui.menu(im_str!("Computer"), true, || {
-            Slider::new(im_str!("Search depth"), 1..=7)
-                .build(ui, &mut model.ai_search_depth.borrow_mut());
+            let mut depth = i32::from(*model.ai_search_depth.borrow());
+            let depths = i32::from(*SEARCH_DEPTHS.start())..=i32::from(*SEARCH_DEPTHS.end());
+            if Slider::new(im_str!("Search depth"), depths).build(ui, &mut depth) {
+                *model.ai_search_depth.borrow_mut() = depth as u8;
+            }
             if ui.is_item_hovered() {
                 ui.tooltip_text(
                     "How many moves ahead the computer will search.\nFewer moves is \
@@ -75,17 +161,373 @@ pub fn draw(ui: &Ui, size: [f32; 2], model: &Model) -> Option<Event> {
                 );
             }
 
-            MenuItem::new(im_str!("Show debug info")).build_with_ref(ui, &mut window_states.ai_debug);
+            ui.menu(im_str!("Strength"), true, || {
+                let mut strength = model.ai_strength.borrow_mut();
+                for &(option, label) in &[
+                    (Strength::Beginner, im_str!("Beginner")),
+                    (Strength::Novice, im_str!("Novice")),
+                    (Strength::Intermediate, im_str!("Intermediate")),
+                    (Strength::Full, im_str!("Full strength")),
+                ] {
+                    if MenuItem::new(label).selected(*strength == option).build(ui) {
+                        *strength = option;
+                    }
+                }
+            });
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Below full strength, the computer thinks less and sometimes plays a move \
+                     that is slightly worse than its best.",
+                );
+            }
+
+            ui.menu(im_str!("Node limit"), true, || {
+                let mut node_limit = model.ai_node_limit.borrow_mut();
+                for &(option, label) in &[
+                    (None, im_str!("None")),
+                    (Some(10_000), im_str!("10,000 nodes")),
+                    (Some(100_000), im_str!("100,000 nodes")),
+                    (Some(1_000_000), im_str!("1,000,000 nodes")),
+                ] {
+                    if MenuItem::new(label)
+                        .selected(*node_limit == option)
+                        .build(ui)
+                    {
+                        *node_limit = option;
+                    }
+                }
+            });
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Search a fixed number of positions instead of to the search depth. The \
+                     computer then plays the same way on any machine, which makes it easy to \
+                     compare engines.",
+                );
+            }
+
+            ui.menu(im_str!("Engine"), true, || {
+                let mut engine = model.ai_engine.borrow_mut();
+                for &(option, label) in &[
+                    (EngineKind::AlphaBeta, im_str!("Alpha-beta search")),
+                    (EngineKind::Mcts, im_str!("Monte-Carlo tree search")),
+                ] {
+                    if MenuItem::new(label).selected(*engine == option).build(ui) {
+                        *engine = option;
+                    }
+                }
+            });
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "How the computer searches for moves. Alpha-beta search looks at every move \
+                     to a fixed depth, while Monte-Carlo tree search focuses on promising moves.",
+                );
+            }
+
+            ui.menu(im_str!("Training"), true, || {
+                let mut constraint = model.ai_constraint.borrow_mut();
+                for &(option, label) in &[
+                    (Constraint::None, im_str!("Play normally")),
+                    (Constraint::NoExchanges, im_str!("Never exchange")),
+                    (Constraint::NoCaptures, im_str!("Never capture")),
+                ] {
+                    if MenuItem::new(label)
+                        .selected(*constraint == option)
+                        .build(ui)
+                    {
+                        *constraint = option;
+                    }
+                }
+            });
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Restrict the computer's moves to practice specific skills. For example, \
+                     practice surrounding pieces against a computer that never exchanges.",
+                );
+            }
+
+            MenuItem::new(im_str!("Use opening book"))
+                .enabled(!model.book.is_empty())
+                .build_with_ref(ui, &mut model.use_book.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(if model.book.is_empty() {
+                    "No opening book was found."
+                } else {
+                    "Play moves from the opening book instead of searching when possible."
+                });
+            }
+
+            MenuItem::new(im_str!("Use neural network evaluation"))
+                .enabled(model.network.is_some())
+                .build_with_ref(ui, &mut model.use_network.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(if model.network.is_none() {
+                    "No evaluation network was found."
+                } else {
+                    "Score positions with the neural network instead of the handcrafted \
+                     evaluation."
+                });
+            }
+
+            MenuItem::new(im_str!("Explain moves"))
+                .build_with_ref(ui, &mut model.explain_ai_moves.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text("After each move, the computer describes why it played it.");
+            }
+
+            MenuItem::new(im_str!("Warn about blunders"))
+                .build_with_ref(ui, &mut model.warn_blunders.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "After each of your moves, the computer checks it with a quick search and \
+                     offers to take it back if it may lose a piece.",
+                );
+            }
+
+            MenuItem::new(im_str!("Analysis mode"))
+                .build_with_ref(ui, &mut model.analysis_mode.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "While it's your turn, the computer searches the position and draws arrows \
+                     for its best moves. Thicker arrows are better moves.",
+                );
+            }
+
+            if MenuItem::new(im_str!("Attempt to solve"))
+                .enabled(model.solve_status.is_none() && !model.is_game_over())
+                .build(ui)
+            {
+                insert_if_empty(&mut event, Event::Solve);
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Try to prove whether the player to move can force a win. This works best \
+                     in sharp endgames with few pieces left.",
+                );
+            }
+
+            MenuItem::new(im_str!("Show debug info"))
+                .build_with_ref(ui, &mut window_states.ai_debug);
+            MenuItem::new(im_str!("Show log")).build_with_ref(ui, &mut window_states.log);
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Show messages from the app, and the result of each step of the computer's \
+                     searches.",
+                );
+            }
+            MenuItem::new(im_str!("Tune evaluation"))
+                .build_with_ref(ui, &mut window_states.eval_params);
+        });
+
+        ui.menu(im_str!("View"), true, || {
+            ui.menu(im_str!("Theme"), true, || {
+                let mut theme = model.theme.borrow_mut();
+                for &(option, label) in &[
+                    (ColorTheme::Light, im_str!("Light")),
+                    (ColorTheme::Dark, im_str!("Dark")),
+                ] {
+                    if MenuItem::new(label).selected(*theme == option).build(ui) {
+                        *theme = option;
+                    }
+                }
+            });
+            ui.menu(im_str!("Highlights"), true, || {
+                let mut palette = model.highlight_palette.borrow_mut();
+                for &(option, label) in &[
+                    (HighlightPalette::Standard, im_str!("Standard")),
+                    (HighlightPalette::Deuteranopia, im_str!("Deuteranopia")),
+                    (HighlightPalette::Protanopia, im_str!("Protanopia")),
+                ] {
+                    if MenuItem::new(label).selected(*palette == option).build(ui) {
+                        *palette = option;
+                    }
+                }
+            });
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Colors for the selection, last move, capture and hint highlights. The \
+                     alternatives stay easy to tell apart with red-green color blindness.",
+                );
+            }
+            ui.menu(im_str!("Board background"), true, || {
+                let mut background = model.board_background.borrow_mut();
+                for &(option, label) in &[
+                    (BoardBackground::None, im_str!("None")),
+                    (BoardBackground::Plain, im_str!("Plain")),
+                    (BoardBackground::Textured, im_str!("Textured")),
+                ] {
+                    if MenuItem::new(label)
+                        .selected(*background == option)
+                        .build(ui)
+                    {
+                        *background = option;
+                    }
+                }
+            });
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Draw a color behind the board, so the light fields and the gaps left by \
+                     removed tiles stand out from the window.",
+                );
+            }
+
+            Slider::new(im_str!("UI scale"), UI_SCALES)
+                .display_format(im_str!("%d%%"))
+                .build(ui, &mut model.ui_scale.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Make text, buttons and the spacing around the board larger or smaller, on \
+                     top of the scaling the system already does for high-resolution screens.",
+                );
+            }
+
+            ui.menu(im_str!("Board size"), true, || {
+                let mut board_size = model.board_size.borrow_mut();
+                for &(option, label) in &[
+                    (BoardSize::Fit, im_str!("Fit window")),
+                    (BoardSize::Small, im_str!("Small")),
+                    (BoardSize::Medium, im_str!("Medium")),
+                    (BoardSize::Large, im_str!("Large")),
+                ] {
+                    if MenuItem::new(label)
+                        .selected(*board_size == option)
+                        .build(ui)
+                    {
+                        *board_size = option;
+                    }
+                }
+            });
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Keep the board at a set size, with empty space around it, instead of \
+                     stretching it to fill the window.",
+                );
+            }
+
+            MenuItem::new(im_str!("Reduce motion"))
+                .build_with_ref(ui, &mut model.reduce_motion.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Turn off animations and pulsing highlights. Static highlights are still \
+                     shown.",
+                );
+            }
+            MenuItem::new(im_str!("Flip board"))
+                .shortcut(im_str!("F"))
+                .build_with_ref(ui, &mut model.flip_board.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Turn the board half a circle, as if sitting on the other side of it.",
+                );
+            }
+            MenuItem::new(im_str!("Auto-rotate"))
+                .build_with_ref(ui, &mut model.auto_rotate.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "When two people play each other, turn the board to face the player to move \
+                     after each move.",
+                );
+            }
+            MenuItem::new(im_str!("Piece colors"))
+                .build_with_ref(ui, &mut window_states.piece_colors);
+            ui.menu(im_str!("Piece style"), true, || {
+                let mut style = model.piece_style.borrow_mut();
+                for &(option, label) in &[
+                    (PieceStyle::Shaded, im_str!("Shaded")),
+                    (PieceStyle::Flat, im_str!("Flat")),
+                    (PieceStyle::HighVisibility, im_str!("High visibility")),
+                ] {
+                    if MenuItem::new(label).selected(*style == option).build(ui) {
+                        *style = option;
+                    }
+                }
+            });
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "High visibility draws larger pieces in pure white and black with a heavier \
+                     outline, which are easier to see on small screens and in recordings.",
+                );
+            }
+            ui.menu(im_str!("Piece outline"), true, || {
+                let mut outline = model.piece_outline.borrow_mut();
+                for &(option, label) in &[
+                    (PieceOutline::Thin, im_str!("Thin")),
+                    (PieceOutline::Normal, im_str!("Normal")),
+                    (PieceOutline::Thick, im_str!("Thick")),
+                ] {
+                    if MenuItem::new(label).selected(*outline == option).build(ui) {
+                        *outline = option;
+                    }
+                }
+            });
+            MenuItem::new(im_str!("Show threats"))
+                .build_with_ref(ui, &mut model.show_threats.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Warn about pieces the other player can capture next move, and tiles that \
+                     will be removed as soon as their pieces leave.",
+                );
+            }
+            MenuItem::new(im_str!("Beginner mode"))
+                .build_with_ref(ui, &mut model.beginner_mode.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "When a piece is selected, also show where every other piece can move, and \
+                     grey out the pieces that can't move at all.",
+                );
+            }
+            MenuItem::new(im_str!("Announce moves"))
+                .build_with_ref(ui, &mut model.announce_moves.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Read each move aloud with the system's speech synthesizer, for players \
+                     using a screen reader.",
+                );
+            }
+            ui.menu(im_str!("Replay speed"), true, || {
+                let mut speed = model.replay_speed.borrow_mut();
+                for &(option, label) in &[
+                    (ReplaySpeed::Slow, im_str!("Slow")),
+                    (ReplaySpeed::Normal, im_str!("Normal")),
+                    (ReplaySpeed::Fast, im_str!("Fast")),
+                ] {
+                    if MenuItem::new(label).selected(*speed == option).build(ui) {
+                        *speed = option;
+                    }
+                }
+            });
+            if ui.is_item_hovered() {
+                ui.tooltip_text("How long each move is shown for when playing the game back.");
+            }
+            MenuItem::new(im_str!("Pause replay on captures"))
+                .build_with_ref(ui, &mut model.pause_replay_on_captures.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Stop playing the game back after each move that captures a piece, so you \
+                     can take a closer look.",
+                );
+            }
+            MenuItem::new(im_str!("Show eval bar"))
+                .build_with_ref(ui, &mut model.show_eval_bar.borrow_mut());
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Show who the computer thinks is ahead next to the board. Hide it to play \
+                     fairly.",
+                );
+            }
         });
 
         ui.menu(im_str!("Help"), true, || {
-            MenuItem::new(im_str!("How to Play")).build_with_ref(ui, &mut window_states.how_to_play);
+            MenuItem::new(im_str!("How to Play"))
+                .build_with_ref(ui, &mut window_states.how_to_play);
             MenuItem::new(im_str!("About")).build_with_ref(ui, &mut window_states.about);
         });
     });
 
     let token = ui.push_style_var(StyleVar::WindowRounding(0.0));
     draw_window(ui, size, model, &mut event);
+    if shows_game_over_overlay(model) {
+        game_over_overlay(ui, size, model, &mut event);
+    }
     token.pop(ui);
 
     if window_states.ai_debug {
@@ -93,22 +535,193 @@ pub fn draw(ui: &Ui, size: [f32; 2], model: &Model) -> Option<Event> {
             .opened(&mut window_states.ai_debug)
             .size([300.0, 600.0], Condition::FirstUseEver)
             .build(ui, || {
-                if let Ok(debug_info) = model.ai.debug_info.read() {
-                    ui.text(debug_info.clone());
+                // Without a computer player, the last hint's search is shown
+                let info = model.latest_search_info().or_else(|| {
+                    model
+                        .hint_ai
+                        .search_info
+                        .read()
+                        .ok()
+                        .map(|info| info.clone())
+                });
+                if let Some(info) = info {
+                    if ui.button(im_str!("Copy to clipboard"), [0.0, 0.0]) {
+                        ui.set_clipboard_text(&ImString::new(info.to_string()));
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(
+                            "Copy the statistics, principal variation and root move scores as \
+                             text, for pasting into a bug report.",
+                        );
+                    }
+                    ui.separator();
+
+                    ui.columns(2, im_str!("search_info"), false);
+                    for (label, value) in info.rows() {
+                        ui.text(label);
+                        ui.next_column();
+                        ui.text(value);
+                        ui.next_column();
+                    }
+                    ui.columns(1, im_str!("search_info"), false);
+
+                    if !info.pv.is_empty() {
+                        ui.separator();
+                        ui.text("Principal variation");
+                        for (i, mv) in info.pv.iter().enumerate() {
+                            ui.text(format!("{:>3}. {}", i + 1, mv));
+                        }
+                    }
+                }
+            });
+    }
+
+    if window_states.log {
+        let mut level = window_states.log_level;
+        Window::new(im_str!("Log"))
+            .opened(&mut window_states.log)
+            .size([500.0, 400.0], Condition::FirstUseEver)
+            .build(ui, || {
+                for &(option, label) in &[
+                    (Level::Error, im_str!("Errors")),
+                    (Level::Warning, im_str!("Warnings")),
+                    (Level::Info, im_str!("Info")),
+                    (Level::Debug, im_str!("Debug")),
+                ] {
+                    ui.radio_button(label, &mut level, option);
+                    ui.same_line(0.0);
+                }
+                if ui.button(im_str!("Clear"), [60.0, 0.0]) {
+                    model.log.clear();
+                }
+                ui.separator();
+
+                // Newest first, so new messages can be seen without scrolling
+                ChildWindow::new(im_str!("log_entries")).build(ui, || {
+                    for entry in model.log.entries(level).iter().rev() {
+                        match entry.level {
+                            Level::Error => ui.text_colored(LOG_ERROR_COLOR, entry.to_string()),
+                            Level::Warning => ui.text_colored(LOG_WARNING_COLOR, entry.to_string()),
+                            Level::Info | Level::Debug => ui.text(entry.to_string()),
+                        }
+                    }
+                });
+            });
+        window_states.log_level = level;
+    }
+
+    if let Some(mut setup) = window_states.new_game {
+        let mut opened = true;
+        let mut done = false;
+        Window::new(im_str!("New Game"))
+            .position([size[0] / 2.0, size[1] / 2.0], Condition::Appearing)
+            .position_pivot([0.5, 0.5])
+            .always_auto_resize(true)
+            .collapsible(false)
+            .opened(&mut opened)
+            .build(ui, || {
+                done = new_game_dialog(
+                    ui,
+                    model,
+                    &mut setup,
+                    &mut window_states.new_game_seed,
+                    &mut event,
+                );
+            });
+        window_states.new_game = Some(setup).filter(|_| opened && !done);
+    }
+
+    if window_states.eval_params {
+        Window::new(im_str!("Evaluation Parameters"))
+            .opened(&mut window_states.eval_params)
+            .always_auto_resize(true)
+            .build(ui, || {
+                let mut params = model.eval_params.borrow_mut();
+                for (name, value) in params.named_values_mut() {
+                    let mut v = i32::from(*value);
+                    if ui.input_int(&im_str!("{}", name), &mut v).build() {
+                        *value = clamp_param(v);
+                    }
+                }
+                ui.text(format!(
+                    "Each value is kept between {} and {}.",
+                    PARAM_RANGE.start(),
+                    PARAM_RANGE.end()
+                ));
+
+                horz_button_layout(
+                    ui,
+                    vec![
+                        (true, im_str!("Load"), Event::LoadEvalParams),
+                        (true, im_str!("Save"), Event::SaveEvalParams),
+                    ],
+                    scaled_size(model, [80.0, 29.0]),
+                    &mut event,
+                );
+                if ui.button(
+                    im_str!("Reset to defaults"),
+                    scaled_size(model, [164.0, 29.0]),
+                ) {
+                    *params = EvalParams::default();
+                }
+                if let Some(path) = config::eval_params_path() {
+                    ui.text(format!("File: {}", path.display()));
+                }
+            });
+    }
+
+    if window_states.piece_colors {
+        Window::new(im_str!("Piece Colors"))
+            .opened(&mut window_states.piece_colors)
+            .always_auto_resize(true)
+            .build(ui, || {
+                let mut piece_colors = model.piece_colors.borrow_mut();
+                for &(color, label, reset_label) in &[
+                    (Color::White, im_str!("White"), im_str!("Reset white")),
+                    (Color::Black, im_str!("Black"), im_str!("Reset black")),
+                ] {
+                    let custom = piece_colors.get_mut(color);
+                    // Start from a gray close to the theme's pieces
+                    let [r, g, b] = custom.unwrap_or(match color {
+                        Color::White => [0xe0, 0xe0, 0xe0],
+                        Color::Black => [0x68, 0x68, 0x68],
+                    });
+                    let to_float = |c: u8| f32::from(c) / 255.0;
+                    let mut rgb = [to_float(r), to_float(g), to_float(b)];
+                    if ColorEdit::new(label, &mut rgb).alpha(false).build(ui) {
+                        let to_byte = |c: f32| (c * 255.0).round() as u8;
+                        *custom = Some([to_byte(rgb[0]), to_byte(rgb[1]), to_byte(rgb[2])]);
+                    }
+                    ui.same_line(0.0);
+                    if ui.button(reset_label, [120.0, 0.0]) {
+                        *custom = None;
+                    }
                 }
             });
     }
 
     if window_states.how_to_play {
-        // TODO: Create an interactive, in-game tutorial to teach the rules of the game
+        let mut start_tutorial = false;
         Window::new(im_str!("How to Play"))
             .opened(&mut window_states.how_to_play)
+            .always_auto_resize(true)
             .build(ui, || {
-                ui.text(
-                    "Unfortunately, there isn't an in-game tutorial. Sorry!\nSee coerceo.com for \
-                     the rules of the game.",
-                );
+                ui.text(format!(
+                    "The tutorial teaches the rules in {} short lessons, each on a small board.\n\
+                     See coerceo.com for the full rules of the game.",
+                    LESSONS.len()
+                ));
+                start_tutorial =
+                    ui.button(im_str!("Start tutorial"), scaled_size(model, [155.0, 29.0]));
             });
+        if start_tutorial {
+            window_states.how_to_play = false;
+            insert_if_empty(&mut event, Event::StartLesson(0));
+        }
+    }
+
+    if let Some(tutorial) = model.tutorial {
+        tutorial_window(ui, model, tutorial, &mut event);
     }
 
     if window_states.about {
@@ -143,38 +756,371 @@ Licensed under the SIL Open Font License v1.1",
     event
 }
 
-fn player_options(ui: &Ui, event: &mut Option<Event>, game_type: GameType) {
-    use self::Player::*;
-    if MenuItem::new(im_str!("Human vs. Human")).build(ui) {
-        insert_if_empty(
-            event,
-            Event::NewGame(game_type, ColorMap::new(Human, Human)),
+/// Returns the event for starting a new game like the current one, with a new random start if it
+/// had one.
+fn play_again_event(model: &Model) -> Event {
+    Event::NewGame(new_game_setup(model))
+}
+
+/// Describes how the game ended, such as "White wins by resignation!"
+fn outcome_text(model: &Model) -> String {
+    use crate::model::Outcome::*;
+    match model.game.outcome {
+        Win(color) => format!(
+            "{:?} wins by capturing all of {:?}'s pieces!",
+            color,
+            color.switch()
+        ),
+        WinByResignation(color) => format!("{:?} wins by resignation!", color),
+        WinOnTime(color) => format!("{:?} wins on time!", color),
+        DrawStalemate => String::from("It's a draw by stalemate!"),
+        DrawThreefoldRepetition => String::from("It's a draw by threefold repetition!"),
+        DrawInsufficientMaterial => String::from("It's a draw by insufficient material!"),
+        DrawNoProgress => format!(
+            "It's a draw after {} moves without progress!",
+            model.game.board.reversible_plies / 2
+        ),
+        InProgress => unreachable!(),
+    }
+}
+
+/// Returns whether a finished game is summed up over the board. The summary is left out of the
+/// tutorial, and goes away once dismissed.
+fn shows_game_over_overlay(model: &Model) -> bool {
+    model.is_game_over() && !model.game_over_dismissed && model.tutorial.is_none()
+}
+
+/// Sums up a finished game over the board, until the player dismisses it to look at the final
+/// position.
+fn game_over_overlay(ui: &Ui, size: [f32; 2], model: &Model, event: &mut Option<Event>) {
+    let mut opened = true;
+    let button_size = scaled_size(model, [110.0, 29.0]);
+    Window::new(im_str!("Game Over"))
+        .position([size[0] / 2.0, size[1] / 2.0], Condition::Always)
+        .position_pivot([0.5, 0.5])
+        .always_auto_resize(true)
+        .collapsible(false)
+        .opened(&mut opened)
+        .build(ui, || {
+            ui.text(outcome_text(model));
+            // Taken back moves aren't part of the game's length
+            let plies = model.game.plies_played();
+            let moves = plies / 2 + plies % 2;
+            ui.text(format!(
+                "The game lasted {} {} ({} {}).",
+                moves,
+                if moves == 1 { "move" } else { "moves" },
+                plies,
+                if plies == 1 { "ply" } else { "plies" },
+            ));
+            for &color in &[Color::White, Color::Black] {
+                ui.text(format!(
+                    "{:?} ended with {} and {}.",
+                    color,
+                    format_count(model.game.board.pieces(color), "piece", "pieces"),
+                    format_count(model.game.board.hexes(color), "tile", "tiles"),
+                ));
+            }
+            for &color in &[Color::White, Color::Black] {
+                ui.text(think_times_text(model, color));
+            }
+            ui.spacing();
+            horz_button_layout(
+                ui,
+                vec![
+                    (true, im_str!("Rematch"), rematch_event(model)),
+                    (true, im_str!("Review"), Event::DismissGameOver),
+                ],
+                button_size,
+                event,
+            );
+        });
+    if !opened {
+        insert_if_empty(event, Event::DismissGameOver);
+    }
+}
+
+/// Writes a count with the singular or plural form of what is being counted.
+fn format_count(count: u8, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+/// Returns the event for a rematch: a new game like the current one, with the same start but the
+/// players' colors swapped.
+fn rematch_event(model: &Model) -> Event {
+    let mut setup = model.game_setup();
+    setup.players = ColorMap::new(model.players.black, model.players.white);
+    // The handicap stays with the player who gave it
+    setup.handicap = setup.handicap.map(|handicap| Handicap {
+        color: handicap.color.switch(),
+        ..handicap
+    });
+    Event::NewGame(setup)
+}
+
+fn game_over_buttons(ui: &Ui, model: &Model, button_size: [f32; 2], event: &mut Option<Event>) {
+    horz_button_layout(
+        ui,
+        vec![
+            (model.can_undo(), im_str!("Undo"), Event::Undo),
+            (
+                model.tutorial.is_none(),
+                im_str!("Rematch"),
+                rematch_event(model),
+            ),
+        ],
+        button_size,
+        event,
+    );
+}
+
+/// Returns the setup the New Game dialog starts from: the same kind of game with the same players
+/// and rules, with a new random start if it had one.
+fn new_game_setup(model: &Model) -> GameSetup {
+    let mut setup = model.game_setup();
+    if let GameType::Random(_) = setup.game_type {
+        setup.game_type = GameType::Random(Rng::from_time().next_u64());
+    }
+    setup
+}
+
+/// Shows every choice for a new game in one place. Pressing Start sends the new game's event, with
+/// a random start generated from `seed`, or from a new seed if it is empty. Returns whether the
+/// dialog is done, after Start or Cancel.
+fn new_game_dialog(
+    ui: &Ui,
+    model: &Model,
+    setup: &mut GameSetup,
+    seed: &mut String,
+    event: &mut Option<Event>,
+) -> bool {
+    let current_game_type = model.game.rules.game_type;
+    ui.text("Board");
+    if ui.radio_button_bool(
+        im_str!("Laurentius"),
+        setup.game_type == GameType::Laurentius,
+    ) {
+        setup.game_type = GameType::Laurentius;
+    }
+    ui.same_line(0.0);
+    if ui.radio_button_bool(im_str!("Ocius"), setup.game_type == GameType::Ocius) {
+        setup.game_type = GameType::Ocius;
+    }
+    ui.same_line(0.0);
+    let random = match setup.game_type {
+        GameType::Random(_) => true,
+        GameType::Laurentius | GameType::Ocius => false,
+    };
+    if ui.radio_button_bool(im_str!("Random start"), random) && !random {
+        setup.game_type = GameType::Random(Rng::from_time().next_u64());
+    }
+    if random {
+        if let GameType::Random(current_seed) = current_game_type {
+            let current_seed = format!("{:x}", current_seed);
+            let mut same = seed.trim().trim_start_matches('#') == current_seed;
+            if ui.checkbox(im_str!("Same random start as this game"), &mut same) {
+                *seed = if same { current_seed } else { String::new() };
+            }
+        }
+        let mut buf = ImString::with_capacity(SEED_INPUT_CAPACITY);
+        buf.push_str(seed);
+        if ui.input_text(im_str!("Seed"), &mut buf).build() {
+            *seed = buf.to_str().to_owned();
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "The number the start is made from, as shown next to the players. The same seed \
+                 always gives the same start. Leave it empty for a new one.",
+            );
+        }
+    }
+
+    ui.separator();
+    ui.text("Players");
+    for &(color, human, computer) in &[
+        (
+            Color::White,
+            im_str!("Human##white"),
+            im_str!("Computer##white"),
+        ),
+        (
+            Color::Black,
+            im_str!("Human##black"),
+            im_str!("Computer##black"),
+        ),
+    ] {
+        let player = setup.players.get_mut(color);
+        ui.text(format!("{:?}", color));
+        ui.same_line(scaled(model, 70.0));
+        ui.radio_button(human, player, PlayerKind::Human);
+        ui.same_line(0.0);
+        ui.radio_button(computer, player, PlayerKind::Computer);
+    }
+    let has_computer =
+        setup.players.white == PlayerKind::Computer || setup.players.black == PlayerKind::Computer;
+    if has_computer {
+        ui.text("Computer strength");
+        for (i, &(option, label)) in [
+            (Strength::Beginner, im_str!("Beginner")),
+            (Strength::Novice, im_str!("Novice")),
+            (Strength::Intermediate, im_str!("Intermediate")),
+            (Strength::Full, im_str!("Full strength")),
+        ]
+        .iter()
+        .enumerate()
+        {
+            if i > 0 {
+                ui.same_line(0.0);
+            }
+            ui.radio_button(label, &mut setup.ai_strength, option);
+        }
+    }
+
+    ui.separator();
+    ui.text("Tiles to exchange for a piece");
+    for (i, &(count, label)) in [
+        (1, im_str!("One")),
+        (2, im_str!("Two (official rules)")),
+        (3, im_str!("Three")),
+    ]
+    .iter()
+    .enumerate()
+    {
+        if i > 0 {
+            ui.same_line(0.0);
+        }
+        ui.radio_button(label, &mut setup.hexes_to_exchange, count);
+    }
+
+    ui.separator();
+    ui.text("Handicap");
+    if ui.is_item_hovered() {
+        ui.tooltip_text(
+            "Give a weaker player a head start by taking some of the other player's pieces off \
+             the board before the game begins.",
         );
     }
-    if MenuItem::new(im_str!("Human vs. Computer")).build(ui) {
-        insert_if_empty(
-            event,
-            Event::NewGame(game_type, ColorMap::new(Human, Computer)),
+    let mut pieces = setup.handicap.map_or(0, |handicap| handicap.pieces);
+    for count in 0..=MAX_HANDICAP {
+        if count > 0 {
+            ui.same_line(0.0);
+        }
+        let label = match count {
+            0 => ImString::new("None"),
+            1 => ImString::new("1 piece"),
+            _ => im_str!("{} pieces", count),
+        };
+        ui.radio_button(&label, &mut pieces, count);
+    }
+    setup.handicap = if pieces == 0 {
+        None
+    } else {
+        // By default the computer gives the handicap, since it is usually the stronger player
+        let mut color = match setup.handicap {
+            Some(handicap) => handicap.color,
+            None if setup.players.black == PlayerKind::Computer => Color::Black,
+            None => Color::White,
+        };
+        ui.text("Taken from");
+        ui.same_line(0.0);
+        ui.radio_button(im_str!("White##handicap"), &mut color, Color::White);
+        ui.same_line(0.0);
+        ui.radio_button(im_str!("Black##handicap"), &mut color, Color::Black);
+        Some(Handicap { color, pieces })
+    };
+
+    ui.separator();
+    ui.text("Clock");
+    if ui.is_item_hovered() {
+        ui.tooltip_text(
+            "Each player's thinking time. An increment is added to a player's time after \
+             each of their moves, while a delay gives them that long to think on each \
+             move before their time starts running. With a clock, the computer plans its \
+             time instead of searching to a fixed depth.",
         );
     }
-    if MenuItem::new(im_str!("Computer vs. Human")).build(ui) {
-        insert_if_empty(
-            event,
-            Event::NewGame(game_type, ColorMap::new(Computer, Human)),
+    let minutes = |m: u64| Duration::from_secs(m * 60);
+    let seconds = Duration::from_secs;
+    for &(option, label) in &[
+        (None, im_str!("No clock")),
+        (
+            Some(TimeControl::new(minutes(3), seconds(2))),
+            im_str!("3+2 (3 minutes + 2 seconds per move)"),
+        ),
+        (
+            Some(TimeControl::new(minutes(5), seconds(0))),
+            im_str!("5+0 (5 minutes)"),
+        ),
+        (
+            Some(TimeControl::new(minutes(5), seconds(3))),
+            im_str!("5+3 (5 minutes + 3 seconds per move)"),
+        ),
+        (
+            Some(TimeControl::new(minutes(10), seconds(0))),
+            im_str!("10+0 (10 minutes)"),
+        ),
+        (
+            Some(TimeControl::new(minutes(15), seconds(10))),
+            im_str!("15+10 (15 minutes + 10 seconds per move)"),
+        ),
+        (
+            Some(TimeControl::new(minutes(5), seconds(0)).with_delay(seconds(5))),
+            im_str!("5 minutes with a 5 second delay"),
+        ),
+    ] {
+        if ui.radio_button_bool(label, setup.time_control == option) {
+            setup.time_control = option;
+        }
+    }
+    ui.text("Custom");
+    let custom = setup
+        .time_control
+        .unwrap_or_else(|| TimeControl::new(minutes(10), seconds(0)));
+    let mut time = (custom.time.as_secs() / 60) as i32;
+    let mut increment = custom.increment.as_secs() as i32;
+    let mut delay = custom.delay.as_secs() as i32;
+    let mut changed = ui.input_int(im_str!("Minutes"), &mut time).build();
+    changed |= ui
+        .input_int(im_str!("Increment (seconds)"), &mut increment)
+        .build();
+    changed |= ui.input_int(im_str!("Delay (seconds)"), &mut delay).build();
+    if changed {
+        setup.time_control = Some(
+            TimeControl::new(
+                minutes(time.max(1) as u64),
+                seconds(increment.max(0) as u64),
+            )
+            .with_delay(seconds(delay.max(0) as u64)),
         );
     }
-    if MenuItem::new(im_str!("Computer vs. Computer")).build(ui) {
+    ui.separator();
+    let game_type = match setup.game_type {
+        GameType::Random(_) => random_start(seed),
+        game_type => Some(game_type),
+    };
+    if game_type.is_none() {
+        ui.text_colored(LOG_ERROR_COLOR, "The seed must be a hexadecimal number");
+    }
+    let button_size = scaled_size(model, [110.0, 29.0]);
+    let start = ui.button(im_str!("Start"), button_size) && game_type.is_some();
+    if let (true, Some(game_type)) = (start, game_type) {
         insert_if_empty(
             event,
-            Event::NewGame(game_type, ColorMap::new(Computer, Computer)),
+            Event::NewGame(GameSetup {
+                game_type,
+                ..*setup
+            }),
         );
     }
+    ui.same_line(0.0);
+    let cancel = ui.button(im_str!("Cancel"), button_size);
+    start || cancel
 }
 
 fn draw_window(ui: &Ui, size: [f32; 2], model: &Model, event: &mut Option<Event>) {
     Window::new(im_str!("Coerceo"))
         .size(size, Condition::Always)
-        .position([0.0, 27.0], Condition::Always)
+        .position([0.0, scaled(model, 27.0)], Condition::Always)
         .title_bar(false)
         .resizable(false)
         .movable(false)
@@ -182,20 +1128,72 @@ fn draw_window(ui: &Ui, size: [f32; 2], model: &Model, event: &mut Option<Event>
         .build(ui, || {
             ui.text("Welcome to Coerceo!");
 
-            let exchange_hex_string = if model.board.hexes_to_exchange == 1 {
-                "One tile to exchange"
-            } else {
-                "Two tiles to exchange"
+            let exchange_hex_string = match model.game.board.hexes_to_exchange {
+                1 => "One tile to exchange",
+                2 => "Two tiles to exchange",
+                3 => "Three tiles to exchange",
+                _ => unreachable!(),
+            };
+            // Show the seed so that a random start can be told apart from others
+            let start_string = match model.game.rules.game_type {
+                GameType::Random(seed) => format!(", random start #{:x}", seed),
+                _ => String::new(),
             };
             ui.text(format!(
-                "{:?} vs. {:?} ({})",
-                model.players.white, model.players.black, exchange_hex_string
+                "{:?} vs. {:?} ({}{})",
+                model.players.white, model.players.black, exchange_hex_string, start_string
             ));
 
-            let board_size = Vec2::new((size[0] - 16.0).max(100.0), (size[1] - 232.0).max(100.0));
+            let mut board_size = Vec2::new(
+                (size[0] - scaled(model, 16.0)).max(100.0),
+                (size[1] - scaled(model, 232.0)).max(100.0),
+            );
+            // Each player's clock and captured tiles go on their side of the board
+            let (top, bottom) = if is_white_on_top(model) {
+                (Color::White, Color::Black)
+            } else {
+                (Color::Black, Color::White)
+            };
+            board_size.y = (board_size.y
+                - scaled(
+                    model,
+                    2.0 * PLAYER_LINE_HEIGHT + REPLAY_LINE_HEIGHT + RECENT_MOVES_LINE_HEIGHT,
+                ))
+            .max(100.0);
+            // A fixed board size is centered with empty space on either side, rather than
+            // stretching the board to fill the window
+            let show_eval_bar = *model.show_eval_bar.borrow();
+            let mut margin = 0.0;
+            if let Some(side) = model.board_size.borrow().side() {
+                board_size.y = board_size.y.min(scaled(model, side));
+                let eval_bar_space = if show_eval_bar {
+                    scaled(model, EVAL_BAR_WIDTH + 8.0)
+                } else {
+                    0.0
+                };
+                let width = board_size.x.min(board_size.y + eval_bar_space);
+                margin = (board_size.x - width) / 2.0;
+                board_size.x = width;
+            }
+            if margin > 0.0 {
+                ui.indent_by(margin);
+            }
+            player_line(ui, model, top);
+            if show_eval_bar {
+                let eval_bar_width = scaled(model, EVAL_BAR_WIDTH);
+                eval_bar(ui, model, Vec2::new(eval_bar_width, board_size.y));
+                ui.same_line(0.0);
+                board_size.x = (board_size.x - eval_bar_width - scaled(model, 8.0)).max(100.0);
+            }
             if let Some(click) = board(ui, model, board_size) {
                 insert_if_empty(event, click);
             }
+            player_line(ui, model, bottom);
+            if margin > 0.0 {
+                ui.unindent_by(margin);
+            }
+            replay_controls(ui, model, event);
+            recent_moves(ui, model);
 
             let format_piece_count = |count| match count {
                 1 => String::from("1 piece"),
@@ -208,58 +1206,95 @@ fn draw_window(ui: &Ui, size: [f32; 2], model: &Model, event: &mut Option<Event>
             };
 
             let display_vitals = || {
-                ui.text(format!(
-                    "{:?} has {} and {}.",
-                    Color::White,
-                    format_piece_count(model.board.pieces(Color::White)),
-                    format_hex_count(model.board.hexes(Color::White)),
-                ));
-                ui.text(format!(
-                    "{:?} has {} and {}.",
-                    Color::Black,
-                    format_piece_count(model.board.pieces(Color::Black)),
-                    format_hex_count(model.board.hexes(Color::Black)),
-                ));
+                let board = &model.game.board;
+                for &color in &[Color::White, Color::Black] {
+                    let them = color.switch();
+                    ui.text(format!(
+                        "{:?} has {} and {} ({}).",
+                        color,
+                        format_piece_count(board.pieces(color)),
+                        format_hex_count(board.hexes(color)),
+                        format_difference(
+                            i32::from(board.pieces(color)) - i32::from(board.pieces(them)),
+                            i32::from(board.hexes(color)) - i32::from(board.hexes(them)),
+                        ),
+                    ));
+                }
             };
 
-            let button_size = [155.0, 29.0];
+            let button_size = scaled_size(model, [155.0, 29.0]);
             use crate::model::Outcome::*;
-            match model.outcome {
-                Win(color) => {
-                    ui.text(format!("{:?} wins!", color));
-                    display_vitals();
-                    if model.can_undo() && ui.button(im_str!("Undo"), button_size) {
-                        insert_if_empty(event, Event::Undo);
-                    }
-                }
+            match model.game.outcome {
                 InProgress => {
+                    ui.text(format!(
+                        "Move {}, {:?} to play.",
+                        model.game.move_number(),
+                        model.game.board.turn
+                    ));
                     if model.players.white == model.players.black {
-                        ui.text(format!("It's {:?}'s turn.", model.board.turn,));
+                        ui.text(format!("It's {:?}'s turn.", model.game.board.turn,));
                     } else {
                         ui.text(match model.current_player() {
-                            Player::Computer => "Waiting for the computer...",
-                            Player::Human => "It's your turn.",
+                            PlayerKind::Computer => "Waiting for the computer...",
+                            PlayerKind::Human => "It's your turn.",
                         });
                     }
+                    thinking_status(ui, model, event);
+                    if let Some(premove) = model.premove {
+                        ui.text(format!("Next move: {} (Esc to cancel)", premove));
+                    }
 
                     display_vitals();
+                    game_info(ui, model);
+                    think_times(ui, model);
 
+                    if let Some(ref mv) = model.game.last_move {
+                        ui.text(format!(
+                            "Last move: {}",
+                            format_numbered_move(
+                                model.game.plies_played() - 1,
+                                model.game.board.turn.switch(),
+                                mv
+                            )
+                        ));
+                    }
+                    if let Some(illegal) = model.illegal_move {
+                        ui.text(format!("Illegal move: {}.", illegal));
+                    }
+                    if let Some(ref explanation) = model.ai_explanation {
+                        ui.text(format!("Computer: {}", explanation));
+                    }
+                    if let Some(Hint::Searching) = model.hint {
+                        ui.text("Looking for a good move...");
+                    }
+                    if let Some(status) = model.solve_status {
+                        ui.text(solve_status_text(status, model.game.board.turn));
+                    }
+
+                    let is_human_player = model.current_player() == PlayerKind::Human;
                     horz_button_layout(
                         ui,
                         vec![
                             (model.can_undo(), im_str!("Undo"), Event::Undo),
                             (model.can_redo(), im_str!("Redo"), Event::Redo),
+                            (
+                                is_human_player && model.hint.is_none(),
+                                im_str!("Hint"),
+                                Event::Hint,
+                            ),
                         ],
                         button_size,
                         event,
                     );
-                    let is_human_player = model.current_player() == Player::Human;
+                    undo_request_prompt(ui, model, button_size, event);
+                    blunder_warning_prompt(ui, model, button_size, event);
+                    confirmation_prompt(ui, model, button_size, event);
                     horz_button_layout(
                         ui,
                         vec![
                             (is_human_player, im_str!("Resign"), Event::Resign),
                             (
-                                model.board.can_exchange() && is_human_player,
+                                model.game.board.can_exchange() && is_human_player,
                                 if model.exchanging {
                                     im_str!("Stop Exchanging")
                                 } else {
@@ -272,24 +1307,431 @@ fn draw_window(ui: &Ui, size: [f32; 2], model: &Model, event: &mut Option<Event>
                         event,
                     );
                 }
-                // Draw cases
                 _ => {
-                    let message = match model.outcome {
-                        DrawStalemate => "It's a draw by stalemate!",
-                        DrawThreefoldRepetition => "It's a draw by threefold repetition!",
-                        DrawInsufficientMaterial => "It's a draw by insufficient material!",
-                        _ => unreachable!(),
-                    };
-                    ui.text(message);
-                    display_vitals();
-                    if model.can_undo() && ui.button(im_str!("Undo"), button_size) {
-                        insert_if_empty(event, Event::Undo);
+                    if !shows_game_over_overlay(model) {
+                        ui.text(outcome_text(model));
                     }
+                    display_vitals();
+                    game_info(ui, model);
+                    think_times(ui, model);
+                    game_over_buttons(ui, model, button_size, event);
+                    undo_request_prompt(ui, model, button_size, event);
+                }
+            }
+        });
+}
+
+/// Sums up the rules of the game being played and how the computer is set to play, under a header
+/// that starts closed.
+fn game_info(ui: &Ui, model: &Model) {
+    if ui.collapsing_header(im_str!("Game info")).build() {
+        for line in game_info_lines(model) {
+            ui.text(line);
+        }
+    }
+}
+
+fn game_info_lines(model: &Model) -> Vec<String> {
+    let rules = &model.game.rules;
+    let mut lines = vec![
+        match rules.game_type {
+            GameType::Laurentius => "Game: Laurentius".to_string(),
+            GameType::Ocius => "Game: Ocius".to_string(),
+            GameType::Random(seed) => format!("Game: random start #{:x}", seed),
+        },
+        format!(
+            "Exchange: {} for a piece",
+            format_count(rules.hexes_to_exchange, "tile", "tiles")
+        ),
+    ];
+    if let Some(plies) = rules.draw_after_plies {
+        lines.push(format!(
+            "Draw after {} moves each without progress",
+            plies / 2
+        ));
+    }
+    if let Some(handicap) = rules.handicap {
+        lines.push(format!(
+            "Handicap: {} {:?}",
+            format_count(handicap.pieces, "piece", "pieces"),
+            handicap.color
+        ));
+    }
+    lines.push(match rules.time_control {
+        Some(time_control) => format!("Clock: {}", format_time_control(time_control)),
+        None => "Clock: none".to_string(),
+    });
+    if model.players.white == PlayerKind::Computer || model.players.black == PlayerKind::Computer {
+        // The computer searches to a fixed depth only when it has no clock or node limit to follow
+        let limit = match (rules.time_control, *model.ai_node_limit.borrow()) {
+            (Some(_), _) => "plans its time".to_string(),
+            (None, Some(nodes)) => format!("{} nodes", nodes),
+            (None, None) => format!("depth {}", model.ai_search_depth.borrow()),
+        };
+        let strength = match *model.ai_strength.borrow() {
+            Strength::Beginner => "beginner",
+            Strength::Novice => "novice",
+            Strength::Intermediate => "intermediate",
+            Strength::Full => "full strength",
+        };
+        lines.push(format!("Computer: {}, {}", limit, strength));
+    }
+    lines
+}
+
+/// Writes a time control as minutes+increment, the way the clock presets are labelled.
+fn format_time_control(time_control: TimeControl) -> String {
+    let secs = time_control.time.as_secs();
+    let time = match (secs / 60, secs % 60) {
+        (minutes, 0) => minutes.to_string(),
+        (minutes, secs) => format!("{}:{:02}", minutes, secs),
+    };
+    let mut text = format!("{}+{}", time, time_control.increment.as_secs());
+    if time_control.delay.as_secs() > 0 {
+        text.push_str(&format!(", {} s delay", time_control.delay.as_secs()));
+    }
+    text
+}
+
+/// Shows how long each player has taken over their moves, under a header that starts closed to
+/// leave room for the buttons.
+fn think_times(ui: &Ui, model: &Model) {
+    if ui.collapsing_header(im_str!("Think times")).build() {
+        for &color in &[Color::White, Color::Black] {
+            ui.text(think_times_text(model, color));
+        }
+    }
+}
+
+fn think_times_text(model: &Model, color: Color) -> String {
+    let times = model.game.think_times(color);
+    match times.average() {
+        Some(average) => format!(
+            "{:?} took {} for {} {}: {} on average, {} at most.",
+            color,
+            format_think_time(times.total),
+            times.moves,
+            if times.moves == 1 { "move" } else { "moves" },
+            format_think_time(average),
+            format_think_time(times.longest),
+        ),
+        None => format!("{:?} hasn't moved yet.", color),
+    }
+}
+
+/// Formats a think time in seconds to a tenth, or in minutes and seconds from a minute up.
+fn format_think_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    if secs < 60 {
+        format!("{}.{} s", secs, time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Shows how far the computer has got with choosing its move, if it is the computer's turn, with a
+/// button to make it play the best move it has found so far.
+fn thinking_status(ui: &Ui, model: &Model, event: &mut Option<Event>) {
+    if let Some(info) = model.computer_search_info() {
+        ui.text(info.progress_text());
+        ProgressBar::new(info.progress())
+            .size([scaled(model, 155.0), 0.0])
+            .build(ui);
+        if info.best_move().is_some() {
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Move now")) {
+                insert_if_empty(event, Event::MoveNow);
+            }
+        }
+    }
+}
+
+/// Shows buttons for stepping back and forth through the positions of the game or playing them
+/// back, and which one is shown when it isn't the current one.
+fn replay_controls(ui: &Ui, model: &Model, event: &mut Option<Event>) {
+    let button_size = scaled_size(model, REPLAY_BUTTON_SIZE);
+    let play_button_size = [2.0 * button_size[0], button_size[1]];
+    let buttons = [
+        (im_str!("|<"), ReplayStep::First),
+        (im_str!("<"), ReplayStep::Previous),
+        (im_str!(">"), ReplayStep::Next),
+        (im_str!(">|"), ReplayStep::Last),
+    ];
+    for &(label, step) in &buttons {
+        if model.can_step_replay(step) {
+            if ui.button(label, button_size) {
+                insert_if_empty(event, Event::Replay(step));
+            }
+        } else {
+            ui.dummy(button_size);
+        }
+        ui.same_line(0.0);
+    }
+    if model.game.positions().len() > 1 {
+        let label = if model.auto_replay.is_some() {
+            im_str!("Pause")
+        } else {
+            im_str!("Play")
+        };
+        if ui.button(label, play_button_size) {
+            insert_if_empty(event, Event::ToggleAutoReplay);
+        }
+    } else {
+        ui.dummy(play_button_size);
+    }
+    ui.same_line(0.0);
+    let positions = model.game.positions();
+    match model.replay_ply {
+        Some(0) => ui.text("Viewing the start of the game"),
+        Some(ply) => match positions[ply].1 {
+            Some(mv) => ui.text(format!(
+                "Viewing {}",
+                format_numbered_move(ply - 1, positions[ply - 1].0.turn, mv)
+            )),
+            None => ui.new_line(),
+        },
+        None => ui.new_line(),
+    }
+}
+
+/// Lists the latest few moves of the game, so that what just happened can be pieced together after
+/// looking away for a moment.
+fn recent_moves(ui: &Ui, model: &Model) {
+    let positions = model.game.positions();
+    let first = positions.len().saturating_sub(RECENT_MOVES).max(1);
+    let moves: Vec<_> = (first..positions.len())
+        .filter_map(|ply| {
+            let mv = positions[ply].1?;
+            Some(format_numbered_move(ply - 1, positions[ply - 1].0.turn, mv))
+        })
+        .collect();
+    if moves.is_empty() {
+        ui.text("No moves yet");
+    } else {
+        ui.text(moves.join("    "));
+    }
+}
+
+/// Writes a move with its number, the way game records do: "7. " before White's move and
+/// "7... " before Black's. `plies` is how many plies were played before it.
+fn format_numbered_move(plies: usize, color: Color, mv: &MoveAnnotated) -> String {
+    let dots = match color {
+        Color::White => ".",
+        Color::Black => "...",
+    };
+    format!("{}{} {}", move_number(plies), dots, mv)
+}
+
+/// Describes how many more pieces and captured tiles a player has than their opponent, like
+/// "+2 pieces, -1 tile", leaving out counts that are equal.
+fn format_difference(pieces: i32, tiles: i32) -> String {
+    let mut parts = vec![];
+    for &(difference, one, many) in &[(pieces, "piece", "pieces"), (tiles, "tile", "tiles")] {
+        if difference != 0 {
+            let noun = if difference.abs() == 1 { one } else { many };
+            parts.push(format!("{:+} {}", difference, noun));
+        }
+    }
+    if parts.is_empty() {
+        String::from("even")
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// Shows a player's clock, or just their name if the game is untimed, and the pieces and tiles
+/// they have captured.
+fn player_line(ui: &Ui, model: &Model, color: Color) {
+    match model.game.clock {
+        Some(ref clock) => clock_text(ui, clock, color),
+        None => ui.text(format!("{:?}", color)),
+    }
+    ui.same_line(0.0);
+    piece_tray(ui, model, color, scaled(model, TRAY_HEIGHT));
+    ui.same_line(0.0);
+    tile_tray(ui, model, color, scaled(model, TRAY_HEIGHT));
+}
+
+/// Returns how long until a running clock shows a different time, so that the next frame only needs
+/// to be drawn then, or `None` if the clock is stopped.
+pub fn next_clock_change(clock: &Clock) -> Option<Duration> {
+    let remaining = clock.remaining(clock.running()?);
+    // The time is shown rounded down, in tenths of a second when it is short, so it changes as
+    // soon as it falls below the time shown
+    let shown = if remaining < Duration::from_secs(10) {
+        Duration::from_secs(remaining.as_secs())
+            + Duration::from_millis(u64::from(remaining.subsec_millis() / 100 * 100))
+    } else {
+        Duration::from_secs(remaining.as_secs())
+    };
+    clock
+        .time_until(shown)
+        .map(|until| until + Duration::from_millis(1))
+}
+
+/// Shows a player's remaining time, in the running clock's color if it is their turn.
+fn clock_text(ui: &Ui, clock: &Clock, color: Color) {
+    let remaining = clock.remaining(color);
+    let text = if clock.is_flagged(color) {
+        format!("{:?}: 0:00 (out of time)", color)
+    } else if remaining < Duration::from_secs(10) {
+        // Show tenths of a second when time is short
+        format!(
+            "{:?}: 0:{:02}.{}",
+            color,
+            remaining.as_secs(),
+            remaining.subsec_millis() / 100
+        )
+    } else {
+        let secs = remaining.as_secs();
+        format!("{:?}: {}:{:02}", color, secs / 60, secs % 60)
+    };
+    if clock.running() == Some(color) {
+        ui.text_colored(RUNNING_CLOCK_COLOR, text);
+    } else {
+        ui.text(text);
+    }
+}
+
+fn solve_status_text(status: SolveStatus, turn: Color) -> String {
+    match status {
+        SolveStatus::Solving => String::from("Trying to solve the position..."),
+        SolveStatus::Solved(Solution::Win(mv)) => {
+            format!("Solved: {:?} wins, starting with {}.", turn, mv)
+        }
+        SolveStatus::Solved(Solution::Loss) => {
+            format!("Solved: {:?} wins against any defense.", turn.switch())
+        }
+        SolveStatus::Solved(Solution::Draw) => {
+            String::from("Solved: neither player can force a win.")
+        }
+        SolveStatus::Solved(Solution::Unknown) => {
+            String::from("The position is too complex to solve.")
+        }
+    }
+}
+
+fn undo_request_prompt(ui: &Ui, model: &Model, button_size: [f32; 2], event: &mut Option<Event>) {
+    match model.undo_request {
+        Some(UndoRequest::Pending(approver)) => {
+            ui.text(format!(
+                "{:?} asks to take back a move. Does {:?} accept?",
+                approver.switch(),
+                approver
+            ));
+            horz_button_layout(
+                ui,
+                vec![
+                    (true, im_str!("Accept"), Event::AnswerUndo(true)),
+                    (true, im_str!("Decline"), Event::AnswerUndo(false)),
+                ],
+                button_size,
+                event,
+            );
+        }
+        Some(UndoRequest::Declined) => ui.text("The computer declined to take back the move."),
+        None => {}
+    }
+}
+
+fn confirmation_prompt(ui: &Ui, model: &Model, button_size: [f32; 2], event: &mut Option<Event>) {
+    let (question, label) = match model.confirmation {
+        Some(Confirmation::Resign) => ("Resign this game?", im_str!("Resign")),
+        Some(Confirmation::NewGame(..)) => (
+            "Abandon this game and start a new one?",
+            im_str!("New Game"),
+        ),
+        None => return,
+    };
+    ui.text(question);
+    horz_button_layout(
+        ui,
+        vec![
+            (true, label, Event::Confirm(true)),
+            (true, im_str!("Keep Playing"), Event::Confirm(false)),
+        ],
+        button_size,
+        event,
+    );
+}
+
+/// Shows the current lesson's instructions, and how the player is doing on it.
+fn tutorial_window(ui: &Ui, model: &Model, tutorial: Tutorial, event: &mut Option<Event>) {
+    let lesson = tutorial.lesson();
+    let button_size = scaled_size(model, [110.0, 29.0]);
+    Window::new(im_str!("Tutorial"))
+        .size([380.0, 300.0], Condition::FirstUseEver)
+        .build(ui, || {
+            ui.text(format!(
+                "Lesson {} of {}: {}",
+                tutorial.lesson + 1,
+                LESSONS.len(),
+                lesson.title
+            ));
+            ui.separator();
+            ui.text_wrapped(&ImString::new(lesson.instructions));
+            ui.spacing();
+
+            if model.is_lesson_complete() {
+                ui.text_colored(
+                    LESSON_COMPLETE_COLOR,
+                    if tutorial.is_last_lesson() {
+                        "Well done! You know the rules now. Capture all of your opponent's \
+                         pieces to win."
+                    } else {
+                        "Well done!"
+                    },
+                );
+            } else if tutorial.wrong_move {
+                ui.text_wrapped(&ImString::new(lesson.retry));
+            }
+
+            let next = if !model.is_lesson_complete() {
+                None
+            } else if tutorial.is_last_lesson() {
+                Some((im_str!("Finish"), Event::ExitTutorial))
+            } else {
+                Some((
+                    im_str!("Next lesson"),
+                    Event::StartLesson(tutorial.lesson + 1),
+                ))
+            };
+            if let Some((label, next)) = next {
+                if ui.button(label, button_size) {
+                    insert_if_empty(event, next);
                 }
+                ui.same_line(0.0);
+            }
+            if ui.button(im_str!("Start over"), button_size) {
+                insert_if_empty(event, Event::StartLesson(tutorial.lesson));
+            }
+            ui.same_line(0.0);
+            if ui.button(im_str!("Leave tutorial"), button_size) {
+                insert_if_empty(event, Event::ExitTutorial);
             }
         });
 }
 
+fn blunder_warning_prompt(
+    ui: &Ui,
+    model: &Model,
+    button_size: [f32; 2],
+    event: &mut Option<Event>,
+) {
+    if let Some(BlunderCheck::Blunder) = model.blunder_check {
+        ui.text("That may lose a piece. Take it back?");
+        horz_button_layout(
+            ui,
+            vec![
+                (model.can_undo(), im_str!("Take Back"), Event::Undo),
+                (true, im_str!("Keep Move"), Event::DismissBlunderWarning),
+            ],
+            button_size,
+            event,
+        );
+    }
+}
+
 fn horz_button_layout(
     ui: &Ui,
     buttons: Vec<(bool, &ImStr, Event)>,
@@ -313,6 +1755,15 @@ fn horz_button_layout(
     ui.new_line();
 }
 
+/// Scales a length in pixels by the player's UI scale.
+fn scaled(model: &Model, length: f32) -> f32 {
+    length * *model.ui_scale.borrow() as f32 / 100.0
+}
+
+fn scaled_size(model: &Model, size: [f32; 2]) -> [f32; 2] {
+    [scaled(model, size[0]), scaled(model, size[1])]
+}
+
 fn insert_if_empty<T>(a: &mut Option<T>, b: T) {
     if a.is_none() {
         *a = Some(b);
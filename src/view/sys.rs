@@ -16,6 +16,8 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
 use glium::glutin::{self, Api, GlRequest};
@@ -24,11 +26,76 @@ use imgui::{Context, FontConfig, FontSource, Ui};
 use imgui_glium_renderer::Renderer;
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
 
-use crate::model::Model;
+use crate::ai::Waker;
+use crate::model::{ColorTheme, Model, ReplayStep};
 use crate::update;
+use crate::view::board_parts::Theme;
+use crate::view::{self, board};
 
 const FRAME_DURATION: Duration = Duration::from_millis(16);
 
+/// Returns the event for a keyboard shortcut, if the key pressed is one. Shortcuts that would do
+/// nothing right now, like undoing with nothing to undo, are ignored.
+fn shortcut_event(model: &Model, input: glutin::KeyboardInput) -> Option<update::Event> {
+    use glium::glutin::VirtualKeyCode::*;
+    // Mac users expect the command key where everyone else uses control
+    let command = if cfg!(target_os = "macos") {
+        input.modifiers.logo
+    } else {
+        input.modifiers.ctrl
+    };
+    match (command, input.virtual_keycode?) {
+        (true, Z) if model.can_undo() => Some(update::Event::Undo),
+        (true, Y) if model.can_redo() => Some(update::Event::Redo),
+        (true, N) => Some(view::play_again_event(model)),
+        (false, F) => Some(update::Event::FlipBoard),
+        (false, Escape) => Some(update::Event::ClearSelection),
+        (false, Home) if model.can_step_replay(ReplayStep::First) => {
+            Some(update::Event::Replay(ReplayStep::First))
+        }
+        (false, Left) if model.can_step_replay(ReplayStep::Previous) => {
+            Some(update::Event::Replay(ReplayStep::Previous))
+        }
+        (false, Right) if model.can_step_replay(ReplayStep::Next) => {
+            Some(update::Event::Replay(ReplayStep::Next))
+        }
+        (false, End) if model.can_step_replay(ReplayStep::Last) => {
+            Some(update::Event::Replay(ReplayStep::Last))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the point halfway between two fingers on the screen and how far apart they are, if
+/// exactly two are touching it.
+fn two_finger_touch(touches: &HashMap<u64, [f32; 2]>) -> Option<([f32; 2], f32)> {
+    if touches.len() != 2 {
+        return None;
+    }
+    let mut fingers = touches.values();
+    let a = fingers.next()?;
+    let b = fingers.next()?;
+    let center = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+    let spread = (a[0] - b[0]).hypot(a[1] - b[1]);
+    Some((center, spread)).filter(|&(_, spread)| spread > 0.0)
+}
+
+/// Returns whether frames should keep being drawn without waiting for events, either to finish an
+/// animation or to play the moves back. A running clock only needs a frame when its time changes.
+fn needs_frames(model: &Model) -> bool {
+    board::is_animating(model) || model.auto_replay.is_some()
+}
+
+/// Returns a waker that wakes up `events_loop` so that another frame is drawn.
+pub fn waker(events_loop: &glutin::EventsLoop) -> Waker {
+    let events_proxy = events_loop.create_proxy();
+    Waker::new(move || {
+        events_proxy
+            .wakeup()
+            .expect("Failed to wake up events loop");
+    })
+}
+
 pub fn run<F: FnMut(&mut Model, &Ui, [f32; 2]) -> bool>(
     title: String,
     dimensions: (u32, u32),
@@ -53,7 +120,6 @@ pub fn run<F: FnMut(&mut Model, &Ui, [f32; 2]) -> bool>(
     let window = gl_window.window();
 
     let mut ctx = Context::create();
-    ctx.style_mut().use_classic_colors();
     ctx.set_ini_filename(None);
 
     let mut platform = WinitPlatform::init(&mut ctx);
@@ -70,11 +136,19 @@ pub fn run<F: FnMut(&mut Model, &Ui, [f32; 2]) -> bool>(
             ..FontConfig::default()
         }),
     }]);
-    ctx.io_mut().font_global_scale = (1.0 / hidpi_factor) as f32;
+    // The UI scale the style's sizes were last scaled to, as a percentage
+    let mut style_scale = 100;
+
+    // Where each finger on the screen is, and whether they are zooming the board rather than
+    // clicking
+    let mut touches = HashMap::new();
+    let mut pinching = false;
 
     let mut renderer = Renderer::init(&mut ctx, &display).expect("Failed to initialize renderer");
 
     let mut last_frame = Instant::now();
+    // When the running clock will next show a different time, if a wake is waiting for it
+    let clock_change = Cell::new(None);
 
     let mut render = |model: &mut Model,
                       ctx: &mut Context,
@@ -89,18 +163,54 @@ pub fn run<F: FnMut(&mut Model, &Ui, [f32; 2]) -> bool>(
             io.display_size
         };
 
+        // The font is rasterized for the screen's scaling, then the player's UI scale is applied on
+        // top of it. The style is scaled by the change, since it keeps its sizes between frames.
+        let ui_scale = *model.ui_scale.borrow();
+        ctx.io_mut().font_global_scale = (f64::from(ui_scale) / 100.0 / hidpi_factor) as f32;
+        if ui_scale != style_scale {
+            ctx.style_mut()
+                .scale_all_sizes(ui_scale as f32 / style_scale as f32);
+            style_scale = ui_scale;
+        }
+
+        let theme = *model.theme.borrow();
+        match theme {
+            ColorTheme::Light => ctx.style_mut().use_classic_colors(),
+            ColorTheme::Dark => ctx.style_mut().use_dark_colors(),
+        };
+
         let ui = ctx.frame();
         if !run_ui(model, &ui, display_size) {
             return false;
         }
 
         let mut target = display.draw();
-        target.clear_color_srgb(1.0, 1.0, 1.0, 1.0);
+        let [r, g, b, a] = Theme::get(theme).background;
+        target.clear_color_srgb(r, g, b, a);
         platform.prepare_render(&ui, &window);
         renderer
             .render(&mut target, ui.render())
             .expect("Rendering failed");
         target.finish().expect("Failed to swap buffers");
+
+        // Keep drawing frames until the animation is over, and draw the next one for a running
+        // clock when its time changes
+        if needs_frames(model) {
+            model.waker.wake();
+        } else if let Some(delay) = model.game.clock.as_ref().and_then(view::next_clock_change) {
+            let now = Instant::now();
+            let next = now + delay;
+            let waiting = match clock_change.get() {
+                Some(scheduled) => scheduled > now && scheduled <= next,
+                None => false,
+            };
+            if !waiting {
+                model.waker.wake_after(delay);
+                clock_change.set(Some(next));
+            }
+        } else {
+            clock_change.set(None);
+        }
         true
     };
 
@@ -109,11 +219,20 @@ pub fn run<F: FnMut(&mut Model, &Ui, [f32; 2]) -> bool>(
 
     events_loop.run_forever(|event| {
         use glium::glutin::WindowEvent::*;
-        use glium::glutin::{ControlFlow, Event, MouseButton, TouchPhase, VirtualKeyCode};
+        use glium::glutin::{
+            ControlFlow, ElementState, Event, MouseButton, TouchPhase, VirtualKeyCode,
+        };
         platform.handle_event(ctx.io_mut(), &window, &event);
 
         if let Event::Awakened = event {
-            if Instant::now() - last_frame < FRAME_DURATION {
+            let clock_changed = match clock_change.get() {
+                Some(scheduled) => scheduled <= Instant::now(),
+                None => false,
+            };
+            if Instant::now() - last_frame < FRAME_DURATION
+                && !needs_frames(&model)
+                && !clock_changed
+            {
                 // Receive the AI move, and queue the next one (if it's a computer-only game)
                 update::update(&mut model, None);
                 update::update(&mut model, None);
@@ -137,7 +256,8 @@ pub fn run<F: FnMut(&mut Model, &Ui, [f32; 2]) -> bool>(
             // This is so that the AI doesn't run in the background on Android. Technically, we
             // should also call update or render on Suspended(false) to restart the AI, but there's
             // no point since the app crashes when it's resumed or even right after it's suspended.
-            model.ai.stop();
+            model.stop_player();
+            model.hint_ai.stop();
         } else if let Event::WindowEvent { event, .. } = event {
             match event {
                 CloseRequested => return ControlFlow::Break,
@@ -147,6 +267,17 @@ pub fn run<F: FnMut(&mut Model, &Ui, [f32; 2]) -> bool>(
                             return ControlFlow::Break;
                         }
                     }
+                    // Leave the keys alone while a text field is being edited
+                    if input.state == ElementState::Pressed && !ctx.io().want_capture_keyboard {
+                        if let Some(event) = shortcut_event(&model, input) {
+                            if !update::update(&mut model, Some(event)) {
+                                return ControlFlow::Break;
+                            }
+                            if !render(&mut model, &mut ctx, &mut platform, &mut last_frame) {
+                                return ControlFlow::Break;
+                            }
+                        }
+                    }
                 }
                 Refresh | Resized(_) | HiDpiFactorChanged(_) => {
                     if !render(&mut model, &mut ctx, &mut platform, &mut last_frame) {
@@ -169,7 +300,8 @@ pub fn run<F: FnMut(&mut Model, &Ui, [f32; 2]) -> bool>(
                     }
                 }
                 MouseInput { button, .. } => {
-                    if MouseButton::Left == button {
+                    // The right button marks fields and draws arrows
+                    if MouseButton::Left == button || MouseButton::Right == button {
                         // Render twice to immediately show the results of the click
                         if !render(&mut model, &mut ctx, &mut platform, &mut last_frame) {
                             return ControlFlow::Break;
@@ -180,12 +312,45 @@ pub fn run<F: FnMut(&mut Model, &Ui, [f32; 2]) -> bool>(
                     }
                 }
                 Touch(glutin::Touch {
-                    phase, location, ..
+                    phase,
+                    location,
+                    id,
+                    ..
                 }) => {
-                    let io = ctx.io_mut();
                     let pos = platform.scale_pos_from_winit(&window, location);
-                    io.mouse_pos = [pos.x as f32, pos.y as f32];
-                    io.mouse_down[0] = phase == TouchPhase::Started || phase == TouchPhase::Moved;
+                    let pos = [pos.x as f32, pos.y as f32];
+                    let before = two_finger_touch(&touches);
+                    match phase {
+                        TouchPhase::Started | TouchPhase::Moved => {
+                            touches.insert(id, pos);
+                        }
+                        TouchPhase::Ended | TouchPhase::Cancelled => {
+                            touches.remove(&id);
+                        }
+                    }
+                    if touches.len() >= 2 {
+                        pinching = true;
+                    }
+
+                    let io = ctx.io_mut();
+                    if pinching {
+                        // Two fingers zoom and move the board, and nothing is clicked until they
+                        // have all been lifted
+                        io.mouse_down[0] = false;
+                        *model.press_start.borrow_mut() = None;
+                        if let (Some(before), Some(after)) = (before, two_finger_touch(&touches)) {
+                            let (center, spread) = after;
+                            let pan = [center[0] - before.0[0], center[1] - before.0[1]];
+                            model.board_zoom.borrow_mut().pinch(spread / before.1, pan);
+                        }
+                        if touches.is_empty() {
+                            pinching = false;
+                        }
+                    } else {
+                        io.mouse_pos = pos;
+                        io.mouse_down[0] =
+                            phase == TouchPhase::Started || phase == TouchPhase::Moved;
+                    }
 
                     match phase {
                         TouchPhase::Moved => {
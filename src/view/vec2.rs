@@ -28,6 +28,17 @@ impl Vec2 {
     pub fn new(x: f32, y: f32) -> Self {
         Self { x, y }
     }
+    /// Turns the vector clockwise on the screen (where y points down) by `angle` radians.
+    pub fn rotated(self, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            x: self.x * cos - self.y * sin,
+            y: self.x * sin + self.y * cos,
+        }
+    }
+    pub fn length(self) -> f32 {
+        self.x.hypot(self.y)
+    }
 }
 
 impl From<[f32; 2]> for Vec2 {